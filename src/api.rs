@@ -0,0 +1,652 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use crate::db::Database;
+use crate::store::{
+    CreatedAt, Entry, EntryValue, KeyPolicy, RecentConfig, SearchOptions, SearchResult, Store,
+    TagPolicy,
+};
+use crate::{enforce_value_limit, KvError, KvResult};
+
+/// Library entry point: keeps the SQLite database and the in-memory cache in sync
+/// and returns data instead of printing it, so other programs can embed the store.
+pub struct KvStore {
+    database: Database,
+    store: Store,
+    max_value_bytes: usize,
+    tag_policy: TagPolicy,
+    key_policy: KeyPolicy,
+    verify_on_write: bool,
+}
+
+/// Optional parts of an add; see [`KvStore::add_with`].
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions {
+    /// `None` keeps the existing notes; blank text clears them.
+    pub notes: Option<String>,
+    pub created: CreatedAt,
+}
+
+/// What [`KvStore::add`] wrote, plus the entry it replaced, if any.
+#[derive(Debug, Clone)]
+pub struct AddOutcome {
+    pub entry: Entry,
+    pub previous: Option<Entry>,
+}
+
+impl KvStore {
+    /// Opens (or creates) the database at `path`, drops expired entries and loads
+    /// the rest into the cache.
+    pub fn open<P: AsRef<Path>>(path: P) -> KvResult<Self> {
+        let database = Database::connect(path)?;
+        Self::from_database(database)
+    }
+
+    /// Like [`KvStore::open`], but leaves expired entries in the database, for commands
+    /// that must not write anything (`import --dry-run`).
+    pub fn open_without_cleanup<P: AsRef<Path>>(path: P) -> KvResult<Self> {
+        let database = Database::connect(path)?;
+        let store = Store::from_entries(database.load_entries()?);
+        Ok(Self::from_parts(database, store))
+    }
+
+    /// Like [`KvStore::open`], but loads only `keys` with indexed lookups. Suited to
+    /// commands that read or change known keys; listing and searching see only these.
+    pub fn open_keys<P: AsRef<Path>>(path: P, keys: &[String]) -> KvResult<Self> {
+        let mut database = Database::connect(path)?;
+        let _ = database.cleanup_expired_entries()?;
+        Self::from_subset(database, keys)
+    }
+
+    /// Like [`KvStore::open_keys`], loading the entries tagged `tag` via the tag index.
+    pub fn open_tagged<P: AsRef<Path>>(path: P, tag: &str) -> KvResult<Self> {
+        let mut database = Database::connect(path)?;
+        let _ = database.cleanup_expired_entries()?;
+        let keys = database.keys_with_tag(tag)?;
+        Self::from_subset(database, &keys)
+    }
+
+    fn from_subset(database: Database, keys: &[String]) -> KvResult<Self> {
+        let mut entries = database.load_entries_for(keys)?;
+        // Load alias targets too, so `resolve` works without the full store.
+        let targets: Vec<String> = entries
+            .iter()
+            .filter_map(|(_, entry)| entry.alias_of())
+            .filter(|target| !keys.iter().any(|key| key == target))
+            .map(str::to_string)
+            .collect();
+        entries.extend(database.load_entries_for(&targets)?);
+        let store = Store::from_subset(entries);
+        Ok(Self::from_parts(database, store))
+    }
+
+    pub fn from_database(mut database: Database) -> KvResult<Self> {
+        let _ = database.cleanup_expired_entries()?;
+        let store = Store::from_entries(database.load_entries()?);
+        Ok(Self::from_parts(database, store))
+    }
+
+    fn from_parts(database: Database, store: Store) -> Self {
+        Self {
+            database,
+            store,
+            max_value_bytes: 0,
+            tag_policy: TagPolicy::default(),
+            key_policy: KeyPolicy::default(),
+            verify_on_write: false,
+        }
+    }
+
+    /// Rejects values larger than `bytes` on [`KvStore::add`]; zero means unlimited.
+    pub fn with_value_limit(mut self, bytes: usize) -> Self {
+        self.max_value_bytes = bytes;
+        self
+    }
+
+    /// Logs keys as short SHA-256 digests instead of verbatim (`[logging] redact_keys`).
+    pub fn with_redacted_log_keys(mut self, redact: bool) -> Self {
+        self.database = self.database.with_redacted_log_keys(redact);
+        self.store.set_redact_log_keys(redact);
+        self
+    }
+
+    /// Applies `[tags]` rules to tags passed to [`KvStore::add`].
+    pub fn with_tag_policy(mut self, policy: TagPolicy) -> Self {
+        self.tag_policy = policy;
+        self
+    }
+
+    /// Applies `[keys]` naming rules to keys written by [`KvStore::add`] and friends.
+    /// Empty keys are rejected even without a policy.
+    pub fn with_key_policy(mut self, policy: KeyPolicy) -> Self {
+        self.key_policy = policy;
+        self
+    }
+
+    /// Checks the rows a write touches against their cached entries instead of
+    /// refusing any write after another process committed (`[database] verify_on_write`).
+    pub fn with_verify_on_write(mut self, enabled: bool) -> Self {
+        self.verify_on_write = enabled;
+        self
+    }
+
+    /// Stages `keys` for [`Database::expect_unchanged`] when verifying writes.
+    fn expect_unchanged<'k>(&self, keys: impl IntoIterator<Item = &'k str>) {
+        if self.verify_on_write {
+            let keys = keys
+                .into_iter()
+                .map(|key| (key.to_string(), self.store.get(key).map(Entry::updated_at)))
+                .collect();
+            self.database.expect_unchanged(keys);
+        }
+    }
+
+    pub fn enable_recent_history(&mut self, config: RecentConfig) {
+        self.store.enable_recent_history(config);
+    }
+
+    /// Checks `key` against the key policy without writing anything. With
+    /// case-insensitive keys, a stored key differing only in case is a collision.
+    pub fn check_key(&self, key: &str) -> KvResult<()> {
+        self.store.check_key(key, &self.key_policy)
+    }
+
+    /// Inserts or replaces `key`. Empty `tags` keep the existing entry's tags.
+    pub fn add(
+        &mut self,
+        key: &str,
+        value: impl Into<EntryValue>,
+        tags: Vec<String>,
+    ) -> KvResult<AddOutcome> {
+        self.add_with_notes(key, value, tags, None)
+    }
+
+    /// Like [`KvStore::add`], also setting notes. `None` keeps the existing notes;
+    /// blank text clears them.
+    pub fn add_with_notes(
+        &mut self,
+        key: &str,
+        value: impl Into<EntryValue>,
+        tags: Vec<String>,
+        notes: Option<String>,
+    ) -> KvResult<AddOutcome> {
+        let options = AddOptions {
+            notes,
+            ..AddOptions::default()
+        };
+        self.add_with(key, value, tags, options)
+    }
+
+    /// Like [`KvStore::add`], honoring every [`AddOptions`] field.
+    pub fn add_with(
+        &mut self,
+        key: &str,
+        value: impl Into<EntryValue>,
+        tags: Vec<String>,
+        options: AddOptions,
+    ) -> KvResult<AddOutcome> {
+        let value = value.into();
+        self.check_key(key)?;
+        enforce_value_limit(key, value.len(), self.max_value_bytes)?;
+        let existing = self.store.get(key);
+        let tags = if tags.is_empty() {
+            existing
+                .map(|entry| entry.tags().to_vec())
+                .unwrap_or_default()
+        } else {
+            Store::normalize_tags_with(tags, &self.tag_policy)?
+        };
+        let mut entry = Entry::for_update(existing, value, tags).with_created_at(options.created);
+        if options.notes.is_some() {
+            entry = entry.with_notes(options.notes);
+        }
+
+        self.expect_unchanged([key]);
+        if options.created == CreatedAt::Keep {
+            self.database.upsert_entry(key, &entry)?;
+        } else {
+            self.database.upsert_entry_redated(key, &entry)?;
+        }
+        let previous = self.store.insert(key.to_string(), entry.clone());
+        self.store.record_access(key);
+        Ok(AddOutcome { entry, previous })
+    }
+
+    /// Inserts or replaces every pair in one transaction, keeping existing tags and
+    /// notes. Nothing is written if any value exceeds the size limit.
+    pub fn add_many(&mut self, pairs: Vec<(String, EntryValue)>) -> KvResult<Vec<AddOutcome>> {
+        let mut entries = Vec::with_capacity(pairs.len());
+        for (key, value) in pairs {
+            self.check_key(&key)?;
+            enforce_value_limit(&key, value.len(), self.max_value_bytes)?;
+            let existing = self.store.get(&key);
+            let tags = existing
+                .map(|entry| entry.tags().to_vec())
+                .unwrap_or_default();
+            let tags = Store::normalize_tags_with(tags, &self.tag_policy)?;
+            let entry = Entry::for_update(existing, value, tags);
+            entries.push((key, entry));
+        }
+
+        self.expect_unchanged(entries.iter().map(|(key, _)| key.as_str()));
+        self.database.upsert_entries(&entries)?;
+        Ok(entries
+            .into_iter()
+            .map(|(key, entry)| {
+                let previous = self.store.insert(key.clone(), entry.clone());
+                self.store.record_access(&key);
+                AddOutcome { entry, previous }
+            })
+            .collect())
+    }
+
+    /// Looks up `key` without recording the access; see [`KvStore::record_access`].
+    pub fn get(&self, key: &str) -> KvResult<&Entry> {
+        self.store
+            .get(key)
+            .ok_or_else(|| KvError::NotFound(key.to_string()))
+    }
+
+    /// Like [`KvStore::get`], following an alias to the entry it points at.
+    pub fn resolve(&self, key: &str) -> KvResult<&Entry> {
+        let entry = self.get(key)?;
+        match entry.alias_of() {
+            Some(target) => self
+                .store
+                .get(target)
+                .ok_or_else(|| KvError::NotFound(format!("{target} (target of alias '{key}')"))),
+            None => Ok(entry),
+        }
+    }
+
+    /// Points `alias` at `target`. Aliases follow one level only, so the target must
+    /// be a plain entry and nothing may already point at `alias`; an existing plain
+    /// entry under `alias` is never overwritten.
+    pub fn alias(&mut self, alias: &str, target: &str) -> KvResult<AddOutcome> {
+        self.check_key(alias)?;
+        if alias == target {
+            return Err(KvError::InvalidInput(format!(
+                "'{alias}' cannot be an alias of itself"
+            )));
+        }
+        if let Some(next) = self.get(target)?.alias_of() {
+            let problem = if next == alias {
+                "would form a cycle"
+            } else {
+                "would chain aliases"
+            };
+            return Err(KvError::InvalidInput(format!(
+                "'{target}' is an alias of '{next}'; pointing '{alias}' at it {problem}"
+            )));
+        }
+        let pointing = self.store.aliases_of(alias);
+        if !pointing.is_empty() {
+            return Err(KvError::InvalidInput(format!(
+                "'{alias}' is the target of {}; an alias cannot be a target",
+                pointing.join(", ")
+            )));
+        }
+        let existing = self.store.get(alias);
+        if existing.is_some_and(|entry| entry.alias_of().is_none()) {
+            return Err(KvError::InvalidInput(format!(
+                "'{alias}' already holds a value; remove it first"
+            )));
+        }
+
+        let entry = Entry::alias(existing, target.to_string());
+        self.expect_unchanged([alias, target]);
+        self.database.upsert_entry(alias, &entry)?;
+        let previous = self.store.insert(alias.to_string(), entry.clone());
+        Ok(AddOutcome { entry, previous })
+    }
+
+    /// Deletes `key` and returns the entry that was stored.
+    pub fn remove(&mut self, key: &str) -> KvResult<Entry> {
+        if self.store.get(key).is_none() {
+            return Err(KvError::NotFound(key.to_string()));
+        }
+        self.expect_unchanged([key]);
+        self.database.delete_entry(key)?;
+        self.store
+            .remove(key)
+            .ok_or_else(|| KvError::NotFound(key.to_string()))
+    }
+
+    /// Deletes `keys` atomically and returns the removed entries. With `ignore_missing`,
+    /// keys that do not exist are skipped; otherwise any missing key aborts the batch.
+    pub fn remove_many(
+        &mut self,
+        keys: &[String],
+        ignore_missing: bool,
+    ) -> KvResult<Vec<(String, Entry)>> {
+        let mut present = Vec::with_capacity(keys.len());
+        for key in keys {
+            if self.store.get(key).is_some() {
+                if !present.contains(key) {
+                    present.push(key.clone());
+                }
+            } else if !ignore_missing {
+                return Err(KvError::NotFound(key.clone()));
+            }
+        }
+
+        self.expect_unchanged(present.iter().map(String::as_str));
+        self.database.delete_entries(&present)?;
+        Ok(present
+            .into_iter()
+            .filter_map(|key| {
+                let entry = self.store.remove(&key)?;
+                Some((key, entry))
+            })
+            .collect())
+    }
+
+    /// Entries in key order, limited to keys starting with `prefix`.
+    pub fn list<'a>(&'a self, prefix: &'a str) -> Vec<(&'a String, &'a Entry)> {
+        self.store.with_prefix(prefix).collect()
+    }
+
+    pub fn search<'a>(&'a self, pattern: &str, options: &SearchOptions) -> Vec<SearchResult<'a>> {
+        self.store.search_with(pattern, options)
+    }
+
+    /// Bumps `updated_at` on `key` and returns the new timestamp.
+    pub fn touch(&mut self, key: &str) -> KvResult<DateTime<Utc>> {
+        let mut entry = self.get(key)?.clone();
+        let now = Utc::now();
+        self.expect_unchanged([key]);
+        self.database.touch(key, now)?;
+        entry.touch(now);
+        self.store.insert(key.to_string(), entry);
+        Ok(now)
+    }
+
+    /// Removes every entry and returns how many were deleted.
+    pub fn clear(&mut self) -> KvResult<usize> {
+        let count = self.store.len();
+        self.database.replace_all(&[])?;
+        self.store.reset(Vec::new());
+        Ok(count)
+    }
+
+    pub fn random(&self, count: usize, tag: Option<&str>) -> Vec<(&String, &Entry)> {
+        self.store.random(count, tag)
+    }
+
+    pub fn recent(&self, limit: usize) -> Vec<String> {
+        self.store.recent(limit)
+    }
+
+    pub fn record_access(&mut self, key: &str) {
+        self.store.record_access(key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    /// Both halves at once, for callers that must update the database and cache
+    /// together (imports, the interactive session).
+    pub fn parts_mut(&mut self) -> (&mut Database, &mut Store) {
+        (&mut self.database, &mut self.store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KvStore;
+    use crate::db::Database;
+    use crate::store::{Entry, KeyPolicy, RecentConfig, SearchOptions, SearchScope, TagPolicy};
+    use crate::KvError;
+    use chrono::{Duration, Utc};
+    use tempfile::tempdir;
+
+    #[test]
+    fn crud_round_trips_through_the_database() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        {
+            let mut kv = KvStore::open(&path).unwrap();
+            let added = kv.add("alpha", "one", vec!["t".into()]).unwrap();
+            assert!(added.previous.is_none());
+            let updated = kv.add("alpha", "two", Vec::new()).unwrap();
+            assert_eq!(updated.previous.unwrap().value(), "one");
+            assert_eq!(updated.entry.tags(), ["t".to_string()]);
+            kv.add("beta", "three", Vec::new()).unwrap();
+            assert_eq!(kv.remove("beta").unwrap().value(), "three");
+            assert!(matches!(kv.remove("beta"), Err(KvError::NotFound(_))));
+        }
+
+        let kv = KvStore::open(&path).unwrap();
+        assert_eq!(kv.get("alpha").unwrap().value(), "two");
+        let keys: Vec<_> = kv.list("").into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["alpha"]);
+        let found = kv.search("alp", &SearchOptions::new(10, SearchScope::All));
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn removed_keys_leave_the_recent_history() {
+        let dir = tempdir().unwrap();
+        let recent_path = dir.path().join("recent.log");
+        let mut kv = KvStore::open(dir.path().join("data.db")).unwrap();
+        kv.enable_recent_history(RecentConfig::new(recent_path.clone(), 10));
+        kv.add("alpha", "one", Vec::new()).unwrap();
+        kv.add("beta", "two", Vec::new()).unwrap();
+        for key in ["alpha", "beta"] {
+            kv.get(key).unwrap();
+            kv.record_access(key);
+        }
+        assert_eq!(kv.recent(10), ["beta", "alpha"]);
+
+        kv.remove("alpha").unwrap();
+        assert_eq!(kv.recent(10), ["beta"]);
+        assert_eq!(std::fs::read_to_string(&recent_path).unwrap(), "beta");
+    }
+
+    #[test]
+    fn verify_on_write_refuses_only_rows_changed_since_load() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        let mut editor = KvStore::open(&path).unwrap();
+        editor.add("edited", "before", Vec::new()).unwrap();
+        editor.add("other", "one", Vec::new()).unwrap();
+
+        let mut kv = KvStore::open(&path).unwrap().with_verify_on_write(true);
+        let mut strict = KvStore::open(&path).unwrap();
+        editor.add("edited", "behind our back", Vec::new()).unwrap();
+
+        let error = kv.add("edited", "stomp", Vec::new()).unwrap_err();
+        assert!(
+            matches!(&error, KvError::InvalidInput(message) if message.contains("'edited'")),
+            "{error}"
+        );
+        assert!(matches!(kv.remove("edited"), Err(KvError::InvalidInput(_))));
+        kv.add("other", "two", Vec::new()).unwrap();
+        kv.add("new", "three", Vec::new()).unwrap();
+        assert!(strict.add("other", "three", Vec::new()).is_err());
+
+        let reopened = KvStore::open(&path).unwrap();
+        assert_eq!(reopened.get("edited").unwrap().value(), "behind our back");
+        assert_eq!(reopened.get("other").unwrap().value(), "two");
+    }
+
+    #[test]
+    fn open_keys_loads_only_the_requested_entries() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        {
+            let mut kv = KvStore::open(&path).unwrap();
+            for key in ["a", "b", "c"] {
+                kv.add(key, "v", Vec::new()).unwrap();
+            }
+        }
+
+        let mut kv = KvStore::open_keys(&path, &["b".into(), "missing".into()]).unwrap();
+        assert_eq!(kv.len(), 1);
+        assert!(kv.get("a").is_err());
+        assert_eq!(kv.remove("b").unwrap().value(), "v");
+
+        let keys: Vec<_> = KvStore::open(&path)
+            .unwrap()
+            .list("")
+            .into_iter()
+            .map(|(key, _)| key.clone())
+            .collect();
+        assert_eq!(keys, ["a", "c"]);
+    }
+
+    #[test]
+    fn open_tagged_loads_entries_with_the_tag() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        {
+            let mut kv = KvStore::open(&path).unwrap();
+            kv.add("a", "v", vec!["prod".into()]).unwrap();
+            kv.add("b", "v", vec!["dev".into()]).unwrap();
+            kv.add("c", "v", vec!["prod".into(), "dev".into()]).unwrap();
+            kv.add("c", "v", vec!["dev".into()]).unwrap();
+        }
+
+        let kv = KvStore::open_tagged(&path, "prod").unwrap();
+        let keys: Vec<_> = kv.list("").into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["a"]);
+    }
+
+    #[test]
+    fn add_many_upserts_all_pairs_and_keeps_tags() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        {
+            let mut kv = KvStore::open(&path).unwrap();
+            kv.add("a", "old", vec!["t".into()]).unwrap();
+            let outcomes = kv
+                .add_many(vec![("a".into(), "1".into()), ("b".into(), "2".into())])
+                .unwrap();
+            let updated = outcomes.iter().filter(|o| o.previous.is_some()).count();
+            assert_eq!(updated, 1);
+        }
+
+        let kv = KvStore::open(&path).unwrap();
+        assert_eq!(kv.get("a").unwrap().value(), "1");
+        assert_eq!(kv.get("a").unwrap().tags(), ["t".to_string()]);
+        assert_eq!(kv.get("b").unwrap().value(), "2");
+    }
+
+    #[test]
+    fn add_many_applies_the_tag_policy_to_kept_tags() {
+        let dir = tempdir().unwrap();
+        let mut kv = KvStore::open(dir.path().join("data.db")).unwrap();
+        kv.add("a", "old", vec!["Docs".into()]).unwrap();
+        let mut kv = kv.with_tag_policy(TagPolicy {
+            lowercase: true,
+            ..TagPolicy::default()
+        });
+        kv.add_many(vec![("a".into(), "new".into())]).unwrap();
+        assert_eq!(kv.get("a").unwrap().tags(), ["docs".to_string()]);
+    }
+
+    #[test]
+    fn aliases_resolve_one_level_and_reject_cycles() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        {
+            let mut kv = KvStore::open(&path).unwrap();
+            kv.add("config-v3", "three", Vec::new()).unwrap();
+            kv.add("plain", "p", Vec::new()).unwrap();
+            kv.alias("config", "config-v3").unwrap();
+            assert_eq!(kv.resolve("config").unwrap().value(), "three");
+            assert_eq!(kv.get("config").unwrap().alias_of(), Some("config-v3"));
+
+            for (alias, target) in [
+                ("self", "self"),
+                ("config-v3", "config"),
+                ("other", "config"),
+                ("plain", "config-v3"),
+            ] {
+                let result = kv.alias(alias, target);
+                assert!(
+                    matches!(result, Err(KvError::InvalidInput(_))),
+                    "{alias} -> {target}"
+                );
+            }
+            assert!(matches!(
+                kv.alias("dangling", "missing"),
+                Err(KvError::NotFound(_))
+            ));
+        }
+
+        let kv = KvStore::open_keys(&path, &["config".into()]).unwrap();
+        assert_eq!(kv.resolve("config").unwrap().value(), "three");
+    }
+
+    #[test]
+    fn case_insensitive_keys_reject_case_only_collisions() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        KvStore::open(&path)
+            .unwrap()
+            .add("Legacy", "v", Vec::new())
+            .unwrap();
+
+        let mut kv = KvStore::open(&path).unwrap().with_key_policy(KeyPolicy {
+            case_insensitive: true,
+            ..KeyPolicy::default()
+        });
+        let error = kv.add("legacy", "w", Vec::new()).unwrap_err().to_string();
+        assert!(error.contains("'Legacy'"), "{error}");
+        kv.add("fresh", "x", Vec::new()).unwrap();
+        kv.add("fresh", "y", Vec::new()).unwrap();
+    }
+
+    #[test]
+    fn empty_keys_are_rejected_without_a_policy() {
+        let dir = tempdir().unwrap();
+        let mut kv = KvStore::open(dir.path().join("data.db")).unwrap();
+        assert!(matches!(
+            kv.add("", "value", Vec::new()),
+            Err(KvError::InvalidInput(_))
+        ));
+        assert!(kv.is_empty());
+    }
+
+    #[test]
+    fn value_limit_is_enforced_on_add() {
+        let dir = tempdir().unwrap();
+        let mut kv = KvStore::open(dir.path().join("data.db"))
+            .unwrap()
+            .with_value_limit(3);
+        assert!(kv.add("k", "four", Vec::new()).is_err());
+        assert!(kv.is_empty());
+    }
+
+    #[test]
+    fn open_without_cleanup_keeps_expired_rows() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        let long_ago = Utc::now() - Duration::days(2);
+        let expired = Entry::with_timestamps("v", Vec::new(), long_ago, long_ago, Some(long_ago));
+        Database::connect(&path)
+            .unwrap()
+            .upsert_entry("old", &expired)
+            .unwrap();
+
+        KvStore::open_without_cleanup(&path).unwrap();
+        assert_eq!(Database::connect(&path).unwrap().entry_count().unwrap(), 1);
+        KvStore::open(&path).unwrap();
+        assert_eq!(Database::connect(&path).unwrap().entry_count().unwrap(), 0);
+    }
+}
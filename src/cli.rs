@@ -1,30 +1,86 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use crate::db::CheckpointMode;
+use crate::output::ColorChoice;
+use crate::store::SortOrder;
 
 pub const DEFAULT_INTERACTIVE_LIMIT: usize = 10;
 const HELP_EXAMPLES: &str = r#"Examples:
   kv foo bar            # Add key/value implicitly
   kv foo bar @prod @api # Add with tags
   kv -n work foo bar    # Same command in 'work' namespace
+  kv -P work list       # Use the store configured as [profiles.work]
+  kv --config ci.toml list # Read settings from ci.toml only
   kv foo                # Get value implicitly
-  kv foo @prod          # Add with empty value and tags
+  kv foo @prod          # Add with empty value and tags (tags make it allowed)
   kv                    # Interactive fuzzy finder
 
 Explicit commands:
   kv add foo bar @prod  # Add/update with tags
+  kv add foo --note "rotated monthly" bar # Annotate an entry
+  kv -q add foo bar     # Add without the confirmation line
+  kv --verbose list     # Show info logs on stderr
   kv get foo            # Get a value
+  kv get foo bar --ignore-missing # Print 'key: value' for each existing key
+  kv get foo --field tags # Print just the tags, comma-separated
   kv remove foo         # Delete a key
+  kv remove a b c       # Delete several keys atomically
   kv list               # List all keys
+  kv list --prefix db/ --values-only # Just the values under db/
   kv search api -l 5    # Fuzzy search with limit
+  kv search api --prefix project/ # Search only under 'project/'
+  kv search api --limit 20 --offset 40 # Third page of 20 matches
+  kv search api --show-scores --min-score 60 # Keep only strong matches
   kv interactive        # Live fuzzy finder mode
+  cd "$(kv f --print-value)" # Pick an entry and use its value
+  kv f --copy           # Copy the picked value to the clipboard
   kv export backup.json # Export to JSON
+  kv export backup.json --compact # Export single-line JSON
+  kv export backup.jsonl # Export one JSON object per line
+  kv list --order insertion # List in the order keys were added
+  kv list --timestamps  # Include created/updated times
+  kv list --table --width 30 # Aligned key/value/tags table, values cut at 30 chars
+  kv list --template '{key}\t{value}' # Custom line per entry
   kv import backup.json # Import from JSON
+  kv import backup.jsonl # Import JSON Lines
   kv html               # Generate browser view
   kv serve              # Run local live viewer (polling)
   kv put-file notes README.md @project # Save markdown file contents
   kv get-file notes out.md             # Write value to markdown file
+  kv add logo --binary < logo.png      # Store raw bytes from stdin
+  kv get logo --binary > logo.png      # Write raw bytes to stdout
   kv recent             # Show recently accessed keys
+  kv list --color never # Disable colored output
+  kv list --no-pager    # Never page long output through $PAGER
+  kv --data-file team.db --no-create list # Fail on a mistyped path instead of creating it
+  kv add foo --reset-created bar # Update foo and re-date it as if new
+  cut -f1 keys.txt | kv get --stdin-keys # Print key<TAB>value for each listed key
+  kv search dpl --in-tag prod # Fuzzy match only entries tagged prod
+  kv search api --no-highlight # Do not underline matched characters
+  kv search api --explain # Show every matching field and its score
+  kv search api --tag-weight 0.5 # Rank tag matches further below key matches
+  kv dump > backup.sql  # SQL dump, replay with: sqlite3 new.db < backup.sql
+  kv verify             # Check stored rows for corruption
+  kv touch foo          # Bump updated_at without editing
+  kv clear --yes        # Delete every entry without prompting
+  kv random -c 3 --tag review # Three random entries tagged 'review'
+  kv grep -i "todo"     # Entries whose value contains "todo" (any case)
+  kv checkpoint         # Flush the WAL into data.db and truncate it
+  kv migrate --from ~/.kvstore/data.json # Merge a legacy JSON store into SQLite
+  kv completions zsh > ~/.zfunc/_kvstore # Install zsh completions
+  kv by-tag prod        # Entries tagged prod
+  kv add-many a 1 b 2   # Seed several keys in one transaction
+  kv diff other.db --values # Keys that differ from another store
+  kv tags --unused      # Tags carried by only one entry
+  kv alias config config-v3 # `kv get config` prints the value of config-v3
+  kv watch --prefix config/ # print config changes made by other processes
+  kv snapshot before-cleanup # copy the database to snapshots/before-cleanup.db
+  kv restore before-cleanup # put that copy back after confirmation
+  kv stats --tag work    # Entry count and sizes for one tag
 "#;
 
 pub const RESERVED_KEYWORDS: &[&str] = &[
@@ -54,6 +110,26 @@ pub const RESERVED_KEYWORDS: &[&str] = &[
     "get-file",
     "gf",
     "recent",
+    "dump",
+    "verify",
+    "touch",
+    "clear",
+    "random",
+    "grep",
+    "checkpoint",
+    "migrate",
+    "completions",
+    "complete-keys",
+    "by-tag",
+    "tagged",
+    "add-many",
+    "diff",
+    "tags",
+    "alias",
+    "watch",
+    "snapshot",
+    "restore",
+    "stats",
 ];
 
 /// Public CLI representation consumed by the application.
@@ -61,6 +137,18 @@ pub const RESERVED_KEYWORDS: &[&str] = &[
 pub struct Cli {
     pub data_file: Option<PathBuf>,
     pub namespace: Option<String>,
+    pub profile: Option<String>,
+    /// Settings file that replaces the default lookup.
+    pub config: Option<PathBuf>,
+    pub color: ColorChoice,
+    /// Suppress confirmation messages from commands that change data.
+    pub quiet: bool,
+    /// Number of `-v` flags; mirrors log output to stderr when non-zero.
+    pub verbose: u8,
+    /// Print load, command and total wall time to stderr.
+    pub timing: bool,
+    /// Fail instead of creating a missing data file.
+    pub no_create: bool,
     pub command: Command,
 }
 
@@ -77,14 +165,43 @@ Supports implicit commands (kv <key> ...) and explicit subcommands for advanced
     after_long_help = HELP_EXAMPLES
 )]
 struct RawCli {
-    /// Namespace for storage under $HOME/.kvstore/namespaces/<name>/...
+    /// Namespace for storage under $XDG_DATA_HOME/kvstore/namespaces/<name>/...
     #[arg(short, long, global = true, value_name = "NAME")]
     namespace: Option<String>,
 
-    /// Path to the SQLite database file (advanced override; bypasses namespace DB path)
+    /// Path to the SQLite database file (advanced override; bypasses namespace DB path;
+    /// `:memory:` keeps nothing after exit)
     #[arg(long, global = true, value_name = "FILE")]
     data_file: Option<PathBuf>,
 
+    /// Named store from `[profiles.<NAME>]` in kvstore.toml
+    #[arg(short = 'P', long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Read settings only from this file (overrides KVSTORE_CONFIG and the default locations)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// When to colorize output; `auto` honors NO_COLOR and only colors terminals
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorArg::Auto)]
+    color: ColorArg,
+
+    /// Suppress confirmation messages (add, remove, import, export, ...); query output and errors still print
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Also print log messages to stderr: once for info, twice for debug, three times for trace
+    #[arg(long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Print how long loading the store, the command, and the whole run took to stderr
+    #[arg(long, global = true)]
+    timing: bool,
+
+    /// Fail if the data file does not exist instead of creating an empty store
+    #[arg(long, global = true)]
+    no_create: bool,
+
     #[command(subcommand)]
     command: Option<RawCommand>,
 }
@@ -96,60 +213,259 @@ enum RawCommand {
     #[command(name = "add", alias = "a", trailing_var_arg = true)]
     Add {
         key: String,
+        /// Read the value as raw bytes from stdin instead of the command line.
+        #[arg(long)]
+        binary: bool,
+        /// Annotate the entry; an empty string clears existing notes.
+        #[arg(long, value_name = "TEXT")]
+        note: Option<String>,
+        /// Strip leading and trailing whitespace from the value (default: `[input] trim`).
+        #[arg(long, overrides_with = "no_trim")]
+        trim: bool,
+        /// Store the value exactly as given.
+        #[arg(long, overrides_with = "trim")]
+        no_trim: bool,
+        /// Store an empty value for a new key given no tags instead of rejecting it.
+        #[arg(long)]
+        allow_empty: bool,
+        /// On an update, set the creation time to now instead of keeping it.
+        #[arg(long, conflicts_with = "created_at")]
+        reset_created: bool,
+        /// Set the creation time explicitly, e.g. `2024-03-05T14:07:00Z`.
+        #[arg(long, value_name = "RFC3339")]
+        created_at: Option<String>,
         #[arg(value_name = "VALUE|@TAG", num_args = 0..)]
         rest: Vec<String>,
     },
-    /// Retrieves the value stored for a key. Shortcut: `g`
+    /// Retrieves the values stored for one or more keys. Shortcut: `g`
     #[command(name = "get", alias = "g")]
-    Get { key: String },
-    /// Removes the given key and its value. Shortcut: `r`
+    Get {
+        /// One key prints its bare value; several print `key: value` lines.
+        #[arg(required_unless_present_any = ["stdin_keys", "stdin0"], num_args = 1..)]
+        keys: Vec<String>,
+        /// Write the raw value bytes to stdout without a trailing newline.
+        #[arg(long, conflicts_with_all = ["stdin_keys", "stdin0"])]
+        binary: bool,
+        /// Skip keys that do not exist instead of failing.
+        #[arg(long)]
+        ignore_missing: bool,
+        /// Read keys from stdin, one per line, and print `key<TAB>value` for each.
+        #[arg(long, conflicts_with = "keys")]
+        stdin_keys: bool,
+        /// Like `--stdin-keys`, with keys separated by NUL bytes instead of newlines.
+        /// This only changes how keys are read; `--print0` changes the output.
+        #[arg(short = '0', long, conflicts_with = "keys")]
+        stdin0: bool,
+        /// Print only this field instead of the value; tags are comma-separated and
+        /// times are RFC 3339.
+        #[arg(long, value_enum, conflicts_with = "binary")]
+        field: Option<GetField>,
+        /// With `--field`, end each record with NUL instead of a newline and separate
+        /// tags with NUL. This only changes the output; `-0/--stdin0` reads NUL-separated
+        /// keys.
+        #[arg(long, requires = "field")]
+        print0: bool,
+    },
+    /// Removes one or more keys in a single transaction. Shortcut: `r`
     #[command(name = "remove", alias = "r", aliases = ["delete", "rm"])]
     Remove {
-        /// Key to remove.
-        key: String,
+        /// Keys to remove; nothing is removed if any of them is missing.
+        #[arg(required_unless_present = "glob", conflicts_with = "glob", num_args = 1..)]
+        keys: Vec<String>,
+        /// Skip keys that do not exist instead of aborting.
+        #[arg(long)]
+        ignore_missing: bool,
+        /// Remove every key matching a shell-style pattern such as `tmp/*`.
+        #[arg(long, value_name = "PATTERN")]
+        glob: Option<String>,
+        /// Confirm a `--glob` removal; without it the matching keys are only listed.
+        #[arg(short, long, requires = "glob")]
+        yes: bool,
+        /// Print only the removed values, one line per key (empty if it was missing),
+        /// instead of a confirmation.
+        #[arg(long, conflicts_with = "glob")]
+        print_value: bool,
     },
     /// Lists all stored key-value pairs. Shortcut: `l`
     #[command(name = "list", alias = "l")]
-    List,
+    List {
+        /// Only list keys starting with this prefix.
+        #[arg(long, value_name = "STR")]
+        prefix: Option<String>,
+        /// Only list entries carrying this exact tag.
+        #[arg(long, value_name = "TAG")]
+        tag: Option<String>,
+        /// Only list keys matching a shell-style pattern such as `project/*`.
+        #[arg(long, value_name = "PATTERN")]
+        glob: Option<String>,
+        /// Sort by key (default) or by the order entries were first added.
+        #[arg(long, value_enum, default_value_t = SortOrderArg::Key)]
+        order: SortOrderArg,
+        /// Show created/updated times (format: `[display] time_format`).
+        #[arg(long)]
+        timestamps: bool,
+        /// Print only the values, one per line, with no keys, tags, or color.
+        #[arg(long, conflicts_with = "timestamps")]
+        values_only: bool,
+        /// Show keys, values, and tags as an aligned table.
+        #[arg(long, conflicts_with_all = ["timestamps", "values_only"])]
+        table: bool,
+        /// With `--table`, cut values longer than this many characters, `0` for none.
+        #[arg(long, value_name = "CHARS", default_value_t = 40)]
+        width: usize,
+        /// Print each entry as this line, filling `{key}`, `{value}`, `{tags}`,
+        /// `{created}`, and `{updated}`; `\t` and `\n` become tab and newline.
+        #[arg(long, value_name = "STR", conflicts_with_all = ["timestamps", "values_only", "table"])]
+        template: Option<String>,
+        /// Page output through `$PAGER` (or `less`) whenever stdout is a terminal
+        /// (default: only when it does not fit on the screen).
+        #[arg(long, overrides_with = "no_pager")]
+        pager: bool,
+        /// Never page output.
+        #[arg(long, overrides_with = "pager")]
+        no_pager: bool,
+    },
     /// Performs fuzzy search on keys. Shortcut: `s`
     #[command(name = "search", alias = "s")]
     Search {
         /// Pattern to fuzzy match against stored keys.
         pattern: String,
-        /// Maximum number of matches to display.
-        #[arg(short, long, default_value_t = DEFAULT_INTERACTIVE_LIMIT)]
-        limit: usize,
+        /// Only consider keys starting with this prefix; the pattern matches the rest.
+        #[arg(long, value_name = "STR")]
+        prefix: Option<String>,
+        /// Only consider entries carrying this exact tag.
+        #[arg(long, value_name = "TAG")]
+        in_tag: Option<String>,
+        /// Maximum number of matches to display, `0` for all (default: `[search]
+        /// default_limit` or 10).
+        #[arg(short, long)]
+        limit: Option<usize>,
+        /// Skip this many ranked matches before applying the limit.
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        offset: usize,
+        /// Drop matches scoring below this (default: `[search] min_score` or 0).
+        #[arg(long, value_name = "N")]
+        min_score: Option<i64>,
+        /// Print each match's score, e.g. to tune `--min-score`.
+        #[arg(long)]
+        show_scores: bool,
         /// Search only within tags.
         #[arg(long = "tags", conflicts_with = "keys_only")]
         tags_only: bool,
         /// Search only within keys.
         #[arg(long = "keys", conflicts_with = "tags_only")]
         keys_only: bool,
+        /// Underline matched characters (default: on when stdout is a terminal).
+        #[arg(long, overrides_with = "no_highlight")]
+        highlight: bool,
+        /// Never underline matched characters.
+        #[arg(long, overrides_with = "highlight")]
+        no_highlight: bool,
+        /// Show whether each result matched on its key or on a tag.
+        #[arg(long)]
+        explain: bool,
+        /// Multiplier for key match scores (default: `[search] key_weight` or 1.0).
+        #[arg(long, value_name = "FACTOR")]
+        key_weight: Option<f64>,
+        /// Multiplier for tag match scores (default: `[search] tag_weight` or 0.9).
+        #[arg(long, value_name = "FACTOR")]
+        tag_weight: Option<f64>,
+        /// Print each match as this line, like `list --template`, with `{score}` too.
+        #[arg(long, value_name = "STR", conflicts_with_all = ["show_scores", "explain"])]
+        template: Option<String>,
+        /// Page output through `$PAGER` (or `less`) whenever stdout is a terminal
+        /// (default: only when it does not fit on the screen).
+        #[arg(long, overrides_with = "no_pager")]
+        pager: bool,
+        /// Never page output.
+        #[arg(long, overrides_with = "pager")]
+        no_pager: bool,
     },
     /// Opens live fuzzy search. Shortcut: `f`
     #[command(name = "interactive", alias = "f", aliases = ["live"])]
     Interactive {
-        /// Maximum number of matches to display.
-        #[arg(short, long, default_value_t = DEFAULT_INTERACTIVE_LIMIT)]
-        limit: usize,
+        /// Maximum number of matches to display (default: `[search] default_limit` or 10).
+        #[arg(short, long)]
+        limit: Option<usize>,
+        /// Drop matches scoring below this (default: `[search] min_score` or 0).
+        #[arg(long, value_name = "N")]
+        min_score: Option<i64>,
         /// Search only within tags.
         #[arg(long = "tags", conflicts_with = "keys_only")]
         tags_only: bool,
         /// Search only within keys.
         #[arg(long = "keys", conflicts_with = "tags_only")]
         keys_only: bool,
+        /// Underline matched characters (default: on when stdout is a terminal).
+        #[arg(long, overrides_with = "no_highlight")]
+        highlight: bool,
+        /// Never underline matched characters.
+        #[arg(long, overrides_with = "highlight")]
+        no_highlight: bool,
+        /// Print the selected entry's value instead of its key.
+        #[arg(long)]
+        print_value: bool,
+        /// Copy the selected entry's value to the clipboard on Enter.
+        #[arg(long, conflicts_with = "print_value")]
+        copy: bool,
+        /// Hide the match count and timing line.
+        #[arg(long)]
+        no_status: bool,
     },
     /// Exports all entries. Shortcut: `e`
     #[command(name = "export", alias = "e")]
     Export {
         /// Destination file path.
         path: PathBuf,
+        /// Sort by key (default) or by the order entries were first added.
+        #[arg(long, value_enum, default_value_t = SortOrderArg::Key)]
+        order: SortOrderArg,
+        /// Write timestamps in `[display] timezone` (or the local zone) instead of UTC.
+        #[arg(long)]
+        local_time: bool,
+        /// Write single-line JSON instead of pretty-printed output.
+        #[arg(long)]
+        compact: bool,
+        /// File format (default: `jsonl` for `.jsonl`/`.ndjson` paths, otherwise `json`).
+        #[arg(long, value_enum)]
+        format: Option<ExportFormat>,
+        /// Export only this key; repeat for several.
+        #[arg(long = "key", value_name = "KEY")]
+        keys: Vec<String>,
+        /// Skip `--key` values that do not exist instead of failing.
+        #[arg(long, requires = "keys")]
+        ignore_missing: bool,
+        /// Also write `<path>.sha256`, which `import` checks before replacing data.
+        #[arg(long)]
+        with_checksum: bool,
     },
     /// Imports entries from the provided JSON file, replacing current data. Shortcut: `i`
     #[command(name = "import", alias = "i")]
     Import {
         /// Source file path.
         path: PathBuf,
+        /// File format (default: `jsonl` for `.jsonl`/`.ndjson` paths, otherwise `json`).
+        #[arg(long, value_enum)]
+        format: Option<ExportFormat>,
+        /// Replace existing entries without asking.
+        #[arg(short, long)]
+        yes: bool,
+        /// Report how many keys would be added, removed, or changed; write nothing.
+        #[arg(long)]
+        dry_run: bool,
+        /// Prepend this to every imported key, e.g. `teamB/`; existing keys are kept.
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Import entries with empty values instead of rejecting the file.
+        #[arg(long)]
+        allow_empty: bool,
+        /// Import even if `<path>.sha256` does not match the file.
+        #[arg(long)]
+        no_verify: bool,
+        /// Write entries to the database as they are parsed instead of reading the
+        /// whole file first; for imports too large to hold in memory.
+        #[arg(long, conflicts_with = "dry_run")]
+        stream: bool,
     },
     /// Generates a standalone HTML file to browse all entries.
     #[command(name = "html", aliases = ["view", "browse"])]
@@ -181,6 +497,15 @@ enum RawCommand {
         /// Allow non-markdown file paths.
         #[arg(long)]
         any_file: bool,
+        /// Store the file as raw bytes (implies --any-file).
+        #[arg(long)]
+        binary: bool,
+        /// Strip leading and trailing whitespace from the contents (default: `[input] trim`).
+        #[arg(long, overrides_with = "no_trim")]
+        trim: bool,
+        /// Store the file contents exactly as read.
+        #[arg(long, overrides_with = "trim")]
+        no_trim: bool,
     },
     /// Writes key value into a local file (Markdown by default). Shortcut: `gf`
     #[command(name = "get-file", alias = "gf")]
@@ -200,6 +525,159 @@ enum RawCommand {
         #[arg(short, long, value_name = "COUNT", default_value_t = DEFAULT_INTERACTIVE_LIMIT)]
         limit: usize,
     },
+    /// Prints the database as SQL statements replayable with `sqlite3`.
+    #[command(name = "dump")]
+    Dump,
+    /// Scans stored rows for corrupt tags or timestamps; exits non-zero on problems.
+    #[command(name = "verify")]
+    Verify,
+    /// Sets a key's `updated_at` to now without changing its value or tags.
+    #[command(name = "touch")]
+    Touch {
+        /// Key to touch.
+        key: String,
+    },
+    /// Deletes every entry after a y/N confirmation.
+    #[command(name = "clear")]
+    Clear {
+        /// Skip the confirmation prompt (required when stdin is not a terminal).
+        #[arg(short, long)]
+        yes: bool,
+        /// Report how many keys would be removed; delete nothing.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Prints random entries, e.g. for reviewing stored snippets.
+    #[command(name = "random")]
+    Random {
+        /// Number of distinct entries to print.
+        #[arg(short, long, default_value_t = 1)]
+        count: usize,
+        /// Only sample entries with this tag (leading `@` optional).
+        #[arg(short, long, value_name = "TAG")]
+        tag: Option<String>,
+    },
+    /// Prints entries whose value contains a literal substring.
+    #[command(name = "grep")]
+    Grep {
+        /// Exact text to look for in values (not fuzzy).
+        pattern: String,
+        /// Match regardless of letter case.
+        #[arg(short, long)]
+        ignore_case: bool,
+        /// Print entries whose value does not contain the pattern.
+        #[arg(short = 'v', long)]
+        invert: bool,
+    },
+    /// Checkpoints the write-ahead log into the main database file.
+    #[command(name = "checkpoint")]
+    Checkpoint {
+        /// SQLite checkpoint mode; `truncate` also shrinks the `-wal` file to zero bytes.
+        #[arg(long, value_enum, default_value_t = CheckpointModeArg::Truncate)]
+        mode: CheckpointModeArg,
+    },
+    /// Merges a legacy JSON store into the SQLite database.
+    #[command(name = "migrate")]
+    Migrate {
+        /// Legacy JSON file: `{"key": "value"}` or `{"key": {"value": ..., "tags": [...]}}`.
+        #[arg(long, value_name = "PATH")]
+        from: PathBuf,
+    },
+    /// Prints a shell completion script, e.g. `kv completions zsh > _kv`.
+    #[command(name = "completions", hide = true)]
+    Completions {
+        /// Shell to generate the script for.
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Prints stored keys starting with a prefix, one per line, for shell completion.
+    #[command(name = "complete-keys", hide = true)]
+    CompleteKeys {
+        /// Only keys starting with this; empty prints every key.
+        #[arg(default_value = "")]
+        prefix: String,
+    },
+    /// Lists entries carrying a tag, using the tag index instead of loading every entry.
+    #[command(name = "by-tag", alias = "tagged")]
+    ByTag {
+        /// Exact tag to look up (without `@`).
+        tag: String,
+    },
+    /// Adds or updates several key-value pairs in one transaction.
+    #[command(name = "add-many")]
+    AddMany {
+        /// Alternating keys and values: `k1 v1 k2 v2 ...`.
+        #[arg(required = true, num_args = 1.., value_name = "KEY VALUE")]
+        pairs: Vec<String>,
+    },
+    /// Compares this store with another database or export file.
+    #[command(name = "diff")]
+    Diff {
+        /// SQLite database, or a `.json`/`.jsonl`/`.ndjson` export.
+        other: PathBuf,
+        /// Also print the values on each side.
+        #[arg(long)]
+        values: bool,
+        /// Print the differences as a JSON object.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Lists every tag with the number of entries carrying it.
+    #[command(name = "tags")]
+    Tags {
+        /// Only tags on a single entry; same as `--min-count 2`.
+        #[arg(long, conflicts_with = "min_count")]
+        unused: bool,
+        /// Only tags used by fewer than N entries.
+        #[arg(long, value_name = "N")]
+        min_count: Option<usize>,
+    },
+    /// Makes `alias` a pointer that `get` resolves to `target`'s value.
+    #[command(name = "alias")]
+    Alias {
+        alias: String,
+        /// Existing key to point at; it must not be an alias itself.
+        target: String,
+    },
+    /// Prints a line whenever a watched key changes, until interrupted.
+    #[command(name = "watch")]
+    Watch {
+        /// Key to watch.
+        #[arg(required_unless_present = "prefix", conflicts_with = "prefix")]
+        key: Option<String>,
+        /// Watch every key starting with this prefix instead.
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Milliseconds between polls of the database.
+        #[arg(long, value_name = "MS", default_value_t = 1000)]
+        interval: u64,
+    },
+    /// Copies the database to `snapshots/<name>.db` next to the data file.
+    #[command(name = "snapshot")]
+    Snapshot {
+        /// Snapshot name (default: the current UTC time, e.g. `20260115-093000`).
+        #[arg(conflicts_with = "list")]
+        name: Option<String>,
+        /// List existing snapshots, oldest first.
+        #[arg(long)]
+        list: bool,
+    },
+    /// Replaces the database with a snapshot taken by `snapshot`.
+    #[command(name = "restore")]
+    Restore {
+        /// Snapshot name, as shown by `snapshot --list`.
+        name: String,
+        /// Replace the database without asking.
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Shows entry counts, value sizes, and the oldest and newest entries.
+    #[command(name = "stats")]
+    Stats {
+        /// Only count entries carrying this exact tag.
+        #[arg(long, value_name = "TAG")]
+        tag: Option<String>,
+    },
     /// Captures any external/unknown subcommand for implicit inference.
     #[command(external_subcommand)]
     External(Vec<String>),
@@ -211,30 +689,90 @@ pub enum Command {
         key: String,
         value: String,
         tags: Vec<String>,
+        binary: bool,
+        note: Option<String>,
+        trim: Option<bool>,
+        allow_empty: bool,
+        reset_created: bool,
+        /// Unparsed `--created-at` text; validated when the command runs.
+        created_at: Option<String>,
     },
     Get {
-        key: String,
+        keys: Vec<String>,
+        binary: bool,
+        ignore_missing: bool,
+        /// `Some(separator)` when keys come from stdin (`--stdin-keys`, `-0/--stdin0`).
+        stdin_keys: Option<u8>,
+        field: Option<GetField>,
+        print0: bool,
     },
     Remove {
-        key: String,
+        keys: Vec<String>,
+        ignore_missing: bool,
+        glob: Option<String>,
+        yes: bool,
+        print_value: bool,
+    },
+    List {
+        prefix: Option<String>,
+        tag: Option<String>,
+        glob: Option<String>,
+        order: SortOrder,
+        timestamps: bool,
+        values_only: bool,
+        /// Value width for `--table`, `None` for the usual `key = value` lines.
+        table: Option<usize>,
+        template: Option<String>,
+        /// `Some(true)` for `--pager`, `Some(false)` for `--no-pager`, `None` for auto.
+        pager: Option<bool>,
     },
-    List,
     Search {
         pattern: String,
-        limit: usize,
+        prefix: Option<String>,
+        in_tag: Option<String>,
+        limit: Option<usize>,
+        offset: usize,
+        min_score: Option<i64>,
+        show_scores: bool,
         tags_only: bool,
         keys_only: bool,
+        highlight: Option<bool>,
+        explain: bool,
+        key_weight: Option<f64>,
+        tag_weight: Option<f64>,
+        template: Option<String>,
+        pager: Option<bool>,
     },
     Interactive {
-        limit: usize,
+        limit: Option<usize>,
+        min_score: Option<i64>,
         tags_only: bool,
         keys_only: bool,
+        highlight: Option<bool>,
+        print_value: bool,
+        copy: bool,
+        no_status: bool,
     },
     Export {
         path: PathBuf,
+        order: SortOrder,
+        local_time: bool,
+        compact: bool,
+        format: ExportFormat,
+        /// Keys to export; empty exports everything.
+        keys: Vec<String>,
+        ignore_missing: bool,
+        with_checksum: bool,
     },
     Import {
         path: PathBuf,
+        format: ExportFormat,
+        yes: bool,
+        dry_run: bool,
+        prefix: Option<String>,
+        allow_empty: bool,
+        no_verify: bool,
+        stream: bool,
     },
     Html {
         path: PathBuf,
@@ -248,6 +786,8 @@ pub enum Command {
         path: PathBuf,
         tags: Vec<String>,
         any_file: bool,
+        binary: bool,
+        trim: Option<bool>,
     },
     GetFile {
         key: String,
@@ -257,16 +797,178 @@ pub enum Command {
     Recent {
         limit: usize,
     },
+    Dump,
+    Verify,
+    Touch {
+        key: String,
+    },
+    Clear {
+        yes: bool,
+        dry_run: bool,
+    },
+    Random {
+        count: usize,
+        tag: Option<String>,
+    },
+    Grep {
+        pattern: String,
+        ignore_case: bool,
+        invert: bool,
+    },
+    Checkpoint {
+        mode: CheckpointMode,
+    },
+    Migrate {
+        from: PathBuf,
+    },
+    Completions {
+        shell: Shell,
+    },
+    CompleteKeys {
+        prefix: String,
+    },
+    ByTag {
+        tag: String,
+    },
+    AddMany {
+        pairs: Vec<String>,
+    },
+    Diff {
+        other: PathBuf,
+        values: bool,
+        json: bool,
+    },
+    Tags {
+        /// Tags used by at least this many entries are hidden; `None` lists all.
+        below: Option<usize>,
+    },
+    Alias {
+        alias: String,
+        target: String,
+    },
+    Watch {
+        key: Option<String>,
+        prefix: Option<String>,
+        interval: Duration,
+    },
+    Snapshot {
+        name: Option<String>,
+        list: bool,
+    },
+    Restore {
+        name: String,
+        yes: bool,
+    },
+    Stats {
+        tag: Option<String>,
+    },
+}
+
+/// Entry field printed by `get --field`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum GetField {
+    Value,
+    Tags,
+    Created,
+    Updated,
+}
+
+/// File layout used by `export` and `import`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object keyed by entry key.
+    #[default]
+    Json,
+    /// JSON Lines: one `{"key": ..., "value": ...}` record per line.
+    Jsonl,
+}
+
+impl ExportFormat {
+    /// The explicit `--format`, else inferred from the file extension.
+    pub fn resolve(explicit: Option<Self>, path: &Path) -> Self {
+        explicit.unwrap_or_else(|| {
+            let extension = path.extension().and_then(|ext| ext.to_str());
+            match extension.map(str::to_ascii_lowercase).as_deref() {
+                Some("jsonl" | "ndjson") => ExportFormat::Jsonl,
+                _ => ExportFormat::Json,
+            }
+        })
+    }
+}
+
+/// `--color`, converted to [`ColorChoice`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum ColorArg {
+    /// Color only when writing to a terminal and `NO_COLOR` is unset.
+    Auto,
+    Always,
+    Never,
+}
+
+impl From<ColorArg> for ColorChoice {
+    fn from(choice: ColorArg) -> Self {
+        match choice {
+            ColorArg::Auto => ColorChoice::Auto,
+            ColorArg::Always => ColorChoice::Always,
+            ColorArg::Never => ColorChoice::Never,
+        }
+    }
+}
+
+/// `list --order` and `export --order`, converted to [`SortOrder`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum SortOrderArg {
+    /// Lexical key order.
+    Key,
+    /// The order keys were first added; unsaved entries come last.
+    Insertion,
+}
+
+impl From<SortOrderArg> for SortOrder {
+    fn from(order: SortOrderArg) -> Self {
+        match order {
+            SortOrderArg::Key => SortOrder::Key,
+            SortOrderArg::Insertion => SortOrder::Insertion,
+        }
+    }
+}
+
+/// `checkpoint --mode`, converted to [`CheckpointMode`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum CheckpointModeArg {
+    Passive,
+    Full,
+    Truncate,
+}
+
+impl From<CheckpointModeArg> for CheckpointMode {
+    fn from(mode: CheckpointModeArg) -> Self {
+        match mode {
+            CheckpointModeArg::Passive => CheckpointMode::Passive,
+            CheckpointModeArg::Full => CheckpointMode::Full,
+            CheckpointModeArg::Truncate => CheckpointMode::Truncate,
+        }
+    }
 }
 
 impl Cli {
+    /// The full clap definition, e.g. for generating shell completions.
+    pub fn command() -> clap::Command {
+        RawCli::command()
+    }
+
     pub fn parse() -> Self {
         let raw = RawCli::parse();
         let command = match raw.command {
             None => Command::Interactive {
-                limit: DEFAULT_INTERACTIVE_LIMIT,
+                limit: None,
+                min_score: None,
                 tags_only: false,
                 keys_only: false,
+                highlight: None,
+                print_value: false,
+                copy: false,
+                no_status: false,
             },
             Some(raw_command) => convert_command(raw_command),
         };
@@ -274,6 +976,13 @@ impl Cli {
         Self {
             data_file: raw.data_file,
             namespace: raw.namespace,
+            profile: raw.profile,
+            config: raw.config,
+            color: raw.color.into(),
+            quiet: raw.quiet,
+            verbose: raw.verbose,
+            timing: raw.timing,
+            no_create: raw.no_create,
             command,
         }
     }
@@ -281,35 +990,179 @@ impl Cli {
 
 fn convert_command(raw: RawCommand) -> Command {
     match raw {
-        RawCommand::Add { key, rest } => {
+        RawCommand::Add {
+            key,
+            binary,
+            note,
+            trim,
+            no_trim,
+            allow_empty,
+            reset_created,
+            created_at,
+            rest,
+        } => {
             let (value, tags) = parse_value_and_tags(&rest);
-            Command::Add { key, value, tags }
+            Command::Add {
+                key,
+                value,
+                tags,
+                binary,
+                note,
+                trim: flag_choice(trim, no_trim),
+                allow_empty,
+                reset_created,
+                created_at,
+            }
         }
-        RawCommand::Get { key } => Command::Get { key },
-        RawCommand::Remove { key } => Command::Remove { key },
-        RawCommand::List => Command::List,
+        RawCommand::Get {
+            keys,
+            binary,
+            ignore_missing,
+            stdin_keys,
+            stdin0,
+            field,
+            print0,
+        } => Command::Get {
+            keys,
+            binary,
+            ignore_missing,
+            stdin_keys: match (stdin_keys, stdin0) {
+                (_, true) => Some(b'\0'),
+                (true, false) => Some(b'\n'),
+                (false, false) => None,
+            },
+            field,
+            print0,
+        },
+        RawCommand::Remove {
+            keys,
+            ignore_missing,
+            glob,
+            yes,
+            print_value,
+        } => Command::Remove {
+            keys,
+            ignore_missing,
+            glob,
+            yes,
+            print_value,
+        },
+        RawCommand::List {
+            prefix,
+            tag,
+            glob,
+            order,
+            timestamps,
+            values_only,
+            table,
+            width,
+            template,
+            pager,
+            no_pager,
+        } => Command::List {
+            prefix,
+            tag,
+            glob,
+            order: order.into(),
+            timestamps,
+            values_only,
+            table: table.then_some(width),
+            template,
+            pager: flag_choice(pager, no_pager),
+        },
         RawCommand::Search {
             pattern,
+            prefix,
+            in_tag,
             limit,
+            offset,
+            min_score,
+            show_scores,
             tags_only,
             keys_only,
+            highlight,
+            no_highlight,
+            explain,
+            key_weight,
+            tag_weight,
+            template,
+            pager,
+            no_pager,
         } => Command::Search {
             pattern,
+            prefix,
+            in_tag: in_tag.map(|tag| tag.trim_start_matches('@').to_string()),
             limit,
+            offset,
+            min_score,
+            show_scores,
             tags_only,
             keys_only,
+            highlight: flag_choice(highlight, no_highlight),
+            explain,
+            key_weight,
+            tag_weight,
+            template,
+            pager: flag_choice(pager, no_pager),
         },
         RawCommand::Interactive {
             limit,
+            min_score,
             tags_only,
             keys_only,
+            highlight,
+            no_highlight,
+            print_value,
+            copy,
+            no_status,
         } => Command::Interactive {
             limit,
+            min_score,
             tags_only,
             keys_only,
+            highlight: flag_choice(highlight, no_highlight),
+            print_value,
+            copy,
+            no_status,
+        },
+        RawCommand::Export {
+            path,
+            order,
+            local_time,
+            compact,
+            format,
+            keys,
+            ignore_missing,
+            with_checksum,
+        } => Command::Export {
+            format: ExportFormat::resolve(format, &path),
+            path,
+            order: order.into(),
+            local_time,
+            compact,
+            keys,
+            ignore_missing,
+            with_checksum,
+        },
+        RawCommand::Import {
+            path,
+            format,
+            yes,
+            dry_run,
+            prefix,
+            allow_empty,
+            no_verify,
+            stream,
+        } => Command::Import {
+            format: ExportFormat::resolve(format, &path),
+            path,
+            yes,
+            dry_run,
+            prefix,
+            allow_empty,
+            no_verify,
+            stream,
         },
-        RawCommand::Export { path } => Command::Export { path },
-        RawCommand::Import { path } => Command::Import { path },
         RawCommand::Html { path } => Command::Html { path },
         RawCommand::Serve { host, port } => Command::Serve { host, port },
         RawCommand::PutFile {
@@ -317,11 +1170,16 @@ fn convert_command(raw: RawCommand) -> Command {
             path,
             tags,
             any_file,
+            binary,
+            trim,
+            no_trim,
         } => Command::PutFile {
             key,
             path,
             tags: parse_tags_only(&tags),
             any_file,
+            binary,
+            trim: flag_choice(trim, no_trim),
         },
         RawCommand::GetFile {
             key,
@@ -333,16 +1191,81 @@ fn convert_command(raw: RawCommand) -> Command {
             any_file,
         },
         RawCommand::Recent { limit } => Command::Recent { limit },
+        RawCommand::Dump => Command::Dump,
+        RawCommand::Verify => Command::Verify,
+        RawCommand::Touch { key } => Command::Touch { key },
+        RawCommand::Clear { yes, dry_run } => Command::Clear { yes, dry_run },
+        RawCommand::Random { count, tag } => Command::Random {
+            count,
+            tag: tag.map(|tag| tag.trim_start_matches('@').to_string()),
+        },
+        RawCommand::Grep {
+            pattern,
+            ignore_case,
+            invert,
+        } => Command::Grep {
+            pattern,
+            ignore_case,
+            invert,
+        },
+        RawCommand::Checkpoint { mode } => Command::Checkpoint { mode: mode.into() },
+        RawCommand::Migrate { from } => Command::Migrate { from },
+        RawCommand::Completions { shell } => Command::Completions { shell },
+        RawCommand::CompleteKeys { prefix } => Command::CompleteKeys { prefix },
+        RawCommand::ByTag { tag } => Command::ByTag { tag },
+        RawCommand::AddMany { pairs } => Command::AddMany { pairs },
+        RawCommand::Diff {
+            other,
+            values,
+            json,
+        } => Command::Diff {
+            other,
+            values,
+            json,
+        },
+        RawCommand::Tags { unused, min_count } => Command::Tags {
+            below: if unused { Some(2) } else { min_count },
+        },
+        RawCommand::Alias { alias, target } => Command::Alias { alias, target },
+        RawCommand::Watch {
+            key,
+            prefix,
+            interval,
+        } => Command::Watch {
+            key,
+            prefix,
+            interval: Duration::from_millis(interval.max(1)),
+        },
+        RawCommand::Snapshot { name, list } => Command::Snapshot { name, list },
+        RawCommand::Restore { name, yes } => Command::Restore { name, yes },
+        RawCommand::Stats { tag } => Command::Stats {
+            tag: tag.map(|tag| tag.trim_start_matches('@').to_string()),
+        },
         RawCommand::External(args) => infer_command(args),
     }
 }
 
+/// Collapses a `--flag`/`--no-flag` pair; `None` defers to the configured or
+/// detected default.
+fn flag_choice(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
 fn infer_command(args: Vec<String>) -> Command {
     match args.as_slice() {
         [] => Command::Interactive {
-            limit: DEFAULT_INTERACTIVE_LIMIT,
+            limit: None,
+            min_score: None,
             tags_only: false,
             keys_only: false,
+            highlight: None,
+            print_value: false,
+            copy: false,
+            no_status: false,
         },
         [candidate] => {
             if is_reserved(candidate) {
@@ -354,7 +1277,12 @@ fn infer_command(args: Vec<String>) -> Command {
                 );
             }
             Command::Get {
-                key: candidate.clone(),
+                keys: vec![candidate.clone()],
+                binary: false,
+                ignore_missing: false,
+                stdin_keys: None,
+                field: None,
+                print0: false,
             }
         }
         [key, rest @ ..] => {
@@ -371,6 +1299,12 @@ fn infer_command(args: Vec<String>) -> Command {
                 key: key.clone(),
                 value,
                 tags,
+                binary: false,
+                note: None,
+                trim: None,
+                allow_empty: false,
+                reset_created: false,
+                created_at: None,
             }
         }
     }
@@ -449,3 +1383,65 @@ fn is_reserved(word: &str) -> bool {
 fn usage_error(kind: ErrorKind, message: &str) -> ! {
     RawCli::command().error(kind, message).exit()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{convert_command, Command, RawCli, RawCommand};
+    use clap::{CommandFactory, Parser};
+
+    #[test]
+    fn global_flags_do_not_clash_with_subcommand_flags() {
+        RawCli::command().debug_assert();
+    }
+
+    #[test]
+    fn grep_short_v_inverts_instead_of_turning_on_logging() {
+        let raw = RawCli::try_parse_from(["kv", "grep", "-v", "x"]).unwrap();
+        assert_eq!(raw.verbose, 0);
+        assert!(matches!(
+            raw.command,
+            Some(RawCommand::Grep { invert: true, .. })
+        ));
+    }
+
+    #[test]
+    fn stdin0_reads_nul_keys_and_print0_only_shapes_output() {
+        let raw = RawCli::try_parse_from(["kv", "get", "--stdin0", "--field", "tags", "--print0"])
+            .unwrap();
+        assert!(matches!(
+            convert_command(raw.command.unwrap()),
+            Command::Get {
+                stdin_keys: Some(b'\0'),
+                print0: true,
+                ..
+            }
+        ));
+
+        let raw =
+            RawCli::try_parse_from(["kv", "get", "k", "--field", "tags", "--print0"]).unwrap();
+        assert!(matches!(
+            convert_command(raw.command.unwrap()),
+            Command::Get {
+                stdin_keys: None,
+                print0: true,
+                ..
+            }
+        ));
+        assert!(RawCli::try_parse_from(["kv", "get", "--null"]).is_err());
+    }
+
+    #[test]
+    fn put_file_trim_falls_back_to_the_setting_unless_a_flag_is_given() {
+        let trim_of = |args: &[&str]| {
+            let raw =
+                RawCli::try_parse_from([&["kv", "put-file", "k", "f.md"], args].concat()).unwrap();
+            match convert_command(raw.command.unwrap()) {
+                Command::PutFile { trim, .. } => trim,
+                other => panic!("unexpected command {other:?}"),
+            }
+        };
+        assert_eq!(trim_of(&[]), None);
+        assert_eq!(trim_of(&["--trim"]), Some(true));
+        assert_eq!(trim_of(&["--trim", "--no-trim"]), Some(false));
+    }
+}
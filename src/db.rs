@@ -1,101 +1,425 @@
+use std::cell::{Cell, RefCell};
+use std::fmt::Write as _;
 use std::path::Path;
 
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use log::{debug, info};
-use rusqlite::{params, Connection, Transaction};
+use rusqlite::backup::Progress;
+use rusqlite::types::{ToSqlOutput, ValueRef};
+use rusqlite::{
+    params, Connection, ErrorCode, OpenFlags, OptionalExtension, ToSql, Transaction,
+    TransactionBehavior, MAIN_DB,
+};
 
-use crate::store::Entry;
-use crate::{KvError, KvResult};
+use crate::store::{Entry, EntryValue};
+use crate::{bad_alias_target, log_key, KvError, KvResult};
+
+/// Current `PRAGMA user_version`; older supported versions are migrated forward.
+const SCHEMA_VERSION: i64 = 7;
+/// Oldest schema version that can still be migrated in place.
+const MIN_SUPPORTED_VERSION: i64 = 2;
+/// Data file name that opens a private in-memory database, lost when it closes.
+pub const IN_MEMORY: &str = ":memory:";
+
+/// Mode passed to `PRAGMA wal_checkpoint`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum CheckpointMode {
+    Passive,
+    Full,
+    #[default]
+    Truncate,
+}
+
+impl CheckpointMode {
+    pub fn pragma_arg(self) -> &'static str {
+        match self {
+            CheckpointMode::Passive => "PASSIVE",
+            CheckpointMode::Full => "FULL",
+            CheckpointMode::Truncate => "TRUNCATE",
+        }
+    }
+}
+
+/// Frame counts reported by `PRAGMA wal_checkpoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointReport {
+    /// Whether the checkpoint could not finish because of concurrent readers or writers.
+    pub busy: bool,
+    /// Frames in the WAL, or -1 when the database is not in WAL mode.
+    pub log_frames: i64,
+    /// Frames copied back into the database file, or -1 when not in WAL mode.
+    pub checkpointed_frames: i64,
+}
 
 pub struct Database {
     conn: Connection,
+    /// `PRAGMA data_version` seen by the last [`Database::load_entries`]; writes
+    /// refuse to proceed if another connection has committed since.
+    loaded_revision: Cell<Option<i64>>,
+    /// Keys the next write touches, each with the `updated_at` of its cached entry
+    /// (`None` when the cache has no entry); see [`Database::expect_unchanged`].
+    expected: RefCell<Vec<(String, Option<DateTime<Utc>>)>>,
+    /// Log keys as short digests (`[logging] redact_keys`).
+    redact_log_keys: bool,
 }
 
 impl Database {
     /// Opens or creates the SQLite database, ensuring the schema is up to date.
     pub fn connect<P: AsRef<Path>>(path: P) -> KvResult<Self> {
+        Self::open(path.as_ref(), true)
+    }
+
+    /// Like [`Database::connect`], but fails with [`KvError::InvalidInput`] instead of
+    /// creating a missing file (`--no-create`).
+    pub fn connect_existing<P: AsRef<Path>>(path: P) -> KvResult<Self> {
+        Self::open(path.as_ref(), false)
+    }
+
+    /// Logs keys as short SHA-256 digests instead of verbatim.
+    pub fn with_redacted_log_keys(mut self, redact: bool) -> Self {
+        self.redact_log_keys = redact;
+        self
+    }
+
+    /// Opens an existing database read-only, for looking at another store: the schema
+    /// is left as it is and nothing is ever written. A missing file is an error.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> KvResult<Self> {
         let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            if !parent.as_os_str().is_empty() {
-                std::fs::create_dir_all(parent).map_err(|error| {
-                    KvError::io_path("creating database directory", parent.to_path_buf(), error)
-                })?;
+        let flags = (OpenFlags::default()
+            - OpenFlags::SQLITE_OPEN_READ_WRITE
+            - OpenFlags::SQLITE_OPEN_CREATE)
+            | OpenFlags::SQLITE_OPEN_READ_ONLY;
+        let conn = Connection::open_with_flags(path, flags).map_err(|source| {
+            if source.sqlite_error_code() == Some(ErrorCode::CannotOpen) {
+                KvError::InvalidInput(format!("{} does not exist", path.display()))
+            } else {
+                KvError::DbPath {
+                    path: path.to_path_buf(),
+                    source,
+                }
+            }
+        })?;
+        conn.busy_timeout(std::time::Duration::from_secs(3))?;
+        Ok(Self {
+            conn,
+            loaded_revision: Cell::new(None),
+            expected: RefCell::default(),
+            redact_log_keys: false,
+        })
+    }
+
+    fn open(path: &Path, create: bool) -> KvResult<Self> {
+        if is_in_memory(path) {
+            let conn = Connection::open_in_memory()?;
+            let mut db = Self {
+                conn,
+                loaded_revision: Cell::new(None),
+                expected: RefCell::default(),
+                redact_log_keys: false,
+            };
+            db.initialize_schema()?;
+            info!("in-memory database open");
+            return Ok(db);
+        }
+        let mut flags = OpenFlags::default();
+        if create {
+            if let Some(parent) = path.parent() {
+                if !parent.as_os_str().is_empty() {
+                    std::fs::create_dir_all(parent).map_err(|error| {
+                        KvError::io_path("creating database directory", parent.to_path_buf(), error)
+                    })?;
+                }
             }
+        } else {
+            flags.remove(OpenFlags::SQLITE_OPEN_CREATE);
         }
 
-        let conn = Connection::open(path).map_err(|source| KvError::DbPath {
-            path: path.to_path_buf(),
-            source,
+        let conn = Connection::open_with_flags(path, flags).map_err(|source| {
+            if !create && source.sqlite_error_code() == Some(ErrorCode::CannotOpen) {
+                KvError::InvalidInput(format!(
+                    "data file '{}' does not exist; drop --no-create to create it",
+                    path.display()
+                ))
+            } else {
+                KvError::DbPath {
+                    path: path.to_path_buf(),
+                    source,
+                }
+            }
         })?;
         conn.busy_timeout(std::time::Duration::from_secs(3))?;
-        let mut db = Self { conn };
-        db.initialize_schema()?;
+        let mut db = Self {
+            conn,
+            loaded_revision: Cell::new(None),
+            expected: RefCell::default(),
+            redact_log_keys: false,
+        };
+        // SQLite opens lazily, so a non-database file is only noticed on first use.
+        // A zero-byte file is fine: SQLite treats it as a new, empty database.
+        db.initialize_schema().map_err(|error| match error {
+            KvError::Db(source) if source.sqlite_error_code() == Some(ErrorCode::NotADatabase) => {
+                KvError::InvalidInput(format!(
+                    "'{}' is not a SQLite database; if it is a legacy JSON store, move it \
+                     aside and import it with `kv migrate --from <file>`",
+                    path.display()
+                ))
+            }
+            other => other,
+        })?;
         info!("database connection open");
         Ok(db)
     }
 
     /// Loads every entry from the database so the in-memory cache can be primed.
     pub fn load_entries(&self) -> KvResult<Vec<(String, Entry)>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT key, value, tags, created_at, updated_at, expires_at FROM kv ORDER BY key ASC",
-        )?;
-        let rows = stmt.query_map([], |row| {
-            Ok(Row {
-                key: row.get(0)?,
-                value: row.get(1)?,
-                tags: row.get(2)?,
-                created_at: row.get(3)?,
-                updated_at: row.get(4)?,
-                expires_at: row.get(5)?,
-            })
-        })?;
+        // Read before the SELECT so a concurrent commit is reported, never missed.
+        self.loaded_revision.set(Some(self.current_revision()?));
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {ROW_COLUMNS} FROM kv ORDER BY key ASC"))?;
+        let rows = stmt.query_map([], Row::read)?;
 
         let mut entries = Vec::new();
         for row in rows {
-            let row = row?;
-            let entry = Entry::from_persisted(
-                row.value,
-                &row.tags,
-                &row.created_at,
-                &row.updated_at,
-                row.expires_at.as_deref(),
-            )?;
-            entries.push((row.key, entry));
+            entries.push(row?.into_entry()?);
         }
 
         info!("loaded {} entries from sqlite", entries.len());
         Ok(entries)
     }
 
+    /// Reads a single entry with one primary-key lookup.
+    pub fn get_entry(&self, key: &str) -> KvResult<Option<Entry>> {
+        let row = self
+            .conn
+            .query_row(
+                &format!("SELECT {ROW_COLUMNS} FROM kv WHERE key = ?1"),
+                params![key],
+                Row::read,
+            )
+            .optional()?;
+        row.map(|row| row.into_entry().map(|(_, entry)| entry))
+            .transpose()
+    }
+
+    /// Loads only `keys` (skipping missing ones), recording the revision like
+    /// [`Database::load_entries`] so later writes are still conflict-checked.
+    pub fn load_entries_for(&self, keys: &[String]) -> KvResult<Vec<(String, Entry)>> {
+        self.loaded_revision.set(Some(self.current_revision()?));
+        let mut entries = Vec::with_capacity(keys.len());
+        for key in keys {
+            if let Some(entry) = self.get_entry(key)? {
+                entries.push((key.clone(), entry));
+            }
+        }
+        debug!(
+            "loaded {} of {} requested entries",
+            entries.len(),
+            keys.len()
+        );
+        Ok(entries)
+    }
+
+    /// Counter that changes whenever another connection commits to the database file.
+    pub fn current_revision(&self) -> KvResult<i64> {
+        Ok(self
+            .conn
+            .query_row("PRAGMA data_version", [], |row| row.get(0))?)
+    }
+
+    /// Stops checking writes against the last load, for callers that keep no cache.
+    pub fn forget_loaded_revision(&self) {
+        self.loaded_revision.set(None);
+    }
+
+    /// Makes the next write check each key's stored `updated_at` against the cached
+    /// one (`[database] verify_on_write`) instead of the file-wide revision, so edits
+    /// to other keys since the load no longer block it.
+    pub fn expect_unchanged(&self, keys: Vec<(String, Option<DateTime<Utc>>)>) {
+        *self.expected.borrow_mut() = keys;
+    }
+
+    /// Begins a write transaction, failing if the file changed since the last load,
+    /// or, after [`Database::expect_unchanged`], if one of the expected rows did.
+    fn write_transaction(&mut self) -> KvResult<Transaction<'_>> {
+        let expected = self.expected.take();
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if !expected.is_empty() {
+            for (key, cached) in &expected {
+                let stored: Option<String> = tx
+                    .query_row(
+                        "SELECT updated_at FROM kv WHERE key = ?1",
+                        params![key],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                let unchanged = match (stored, cached) {
+                    (None, None) => true,
+                    (Some(text), Some(cached)) => {
+                        DateTime::parse_from_rfc3339(&text).is_ok_and(|stored| stored == *cached)
+                    }
+                    _ => false,
+                };
+                if !unchanged {
+                    return Err(KvError::InvalidInput(format!(
+                        "'{key}' changed in the database since it was loaded; re-run the \
+                         command to pick up the change"
+                    )));
+                }
+            }
+        } else if let Some(loaded) = self.loaded_revision.get() {
+            let current: i64 = tx.query_row("PRAGMA data_version", [], |row| row.get(0))?;
+            if current != loaded {
+                return Err(KvError::InvalidInput(
+                    "database changed since load, retry".to_string(),
+                ));
+            }
+        }
+        Ok(tx)
+    }
+
     /// Persists the provided entry using an UPSERT wrapped in a transaction for atomicity.
     pub fn upsert_entry(&mut self, key: &str, entry: &Entry) -> KvResult<()> {
-        let tx = self.conn.transaction()?;
+        let tx = self.write_transaction()?;
         Self::execute_upsert(&tx, key, entry)?;
         tx.commit()?;
         info!(
             "stored key={} updated_at={}",
-            key,
+            log_key(key, self.redact_log_keys),
             entry.updated_at().to_rfc3339()
         );
         Ok(())
     }
 
+    /// Like [`Database::upsert_entry`], but an update also overwrites the stored
+    /// `created_at`, which a plain upsert keeps.
+    pub fn upsert_entry_redated(&mut self, key: &str, entry: &Entry) -> KvResult<()> {
+        let tx = self.write_transaction()?;
+        Self::execute_upsert(&tx, key, entry)?;
+        tx.execute(
+            "UPDATE kv SET created_at = ?2 WHERE key = ?1",
+            params![key, entry.created_at().to_rfc3339()],
+        )?;
+        tx.commit()?;
+        info!(
+            "stored key={} created_at={}",
+            log_key(key, self.redact_log_keys),
+            entry.created_at().to_rfc3339()
+        );
+        Ok(())
+    }
+
     /// Deletes the matching entry inside a transaction.
     pub fn delete_entry(&mut self, key: &str) -> KvResult<()> {
-        let tx = self.conn.transaction()?;
+        let tx = self.write_transaction()?;
         let affected = tx.execute("DELETE FROM kv WHERE key = ?1", params![key])?;
         if affected == 0 {
             return Err(KvError::NotFound(key.to_string()));
         }
+        tx.execute("DELETE FROM kv_tags WHERE key = ?1", params![key])?;
+        tx.commit()?;
+        info!("deleted key={}", log_key(key, self.redact_log_keys));
+        Ok(())
+    }
+
+    /// Keys carrying `tag` (exact match) in key order, answered from the `kv_tags` index.
+    pub fn keys_with_tag(&self, tag: &str) -> KvResult<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key FROM kv_tags WHERE tag = ?1 ORDER BY key ASC")?;
+        let keys = stmt
+            .query_map(params![tag], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(keys)
+    }
+
+    /// Keys starting with `prefix` in key order, read with one primary-key range scan
+    /// instead of loading every entry.
+    pub fn keys_with_prefix(&self, prefix: &str) -> KvResult<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key FROM kv WHERE key >= ?1 ORDER BY key ASC")?;
+        let mut rows = stmt.query(params![prefix])?;
+        let mut keys = Vec::new();
+        while let Some(row) = rows.next()? {
+            let key: String = row.get(0)?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
+    /// Inserts or replaces every entry in one transaction; other keys are untouched.
+    pub fn upsert_entries(&mut self, entries: &[(String, Entry)]) -> KvResult<()> {
+        let tx = self.write_transaction()?;
+        for (key, entry) in entries {
+            Self::execute_upsert(&tx, key, entry)?;
+        }
+        tx.commit()?;
+        info!("stored keys count={}", entries.len());
+        Ok(())
+    }
+
+    /// Deletes every key in one transaction; a missing key rolls back the whole batch.
+    pub fn delete_entries(&mut self, keys: &[String]) -> KvResult<usize> {
+        let tx = self.write_transaction()?;
+        {
+            let mut statement = tx.prepare("DELETE FROM kv WHERE key = ?1")?;
+            let mut tags = tx.prepare("DELETE FROM kv_tags WHERE key = ?1")?;
+            for key in keys {
+                if statement.execute(params![key])? == 0 {
+                    return Err(KvError::NotFound(key.clone()));
+                }
+                tags.execute(params![key])?;
+            }
+        }
+        tx.commit()?;
+        info!("deleted keys count={}", keys.len());
+        Ok(keys.len())
+    }
+
+    /// Runs `PRAGMA wal_checkpoint(<mode>)` and returns SQLite's frame counts.
+    pub fn checkpoint(&self, mode: CheckpointMode) -> KvResult<CheckpointReport> {
+        let sql = format!("PRAGMA wal_checkpoint({})", mode.pragma_arg());
+        let report = self.conn.query_row(&sql, [], |row| {
+            Ok(CheckpointReport {
+                busy: row.get::<_, i64>(0)? != 0,
+                log_frames: row.get(1)?,
+                checkpointed_frames: row.get(2)?,
+            })
+        })?;
+        info!("wal checkpoint mode={:?} report={:?}", mode, report);
+        Ok(report)
+    }
+
+    /// Sets only `updated_at` for `key`, leaving the stored value untouched.
+    pub fn touch(&mut self, key: &str, at: DateTime<Utc>) -> KvResult<()> {
+        let tx = self.write_transaction()?;
+        let affected = tx.execute(
+            "UPDATE kv SET updated_at = ?1 WHERE key = ?2",
+            params![at.to_rfc3339(), key],
+        )?;
+        if affected == 0 {
+            return Err(KvError::NotFound(key.to_string()));
+        }
         tx.commit()?;
-        info!("deleted key={}", key);
+        info!(
+            "touched key={} updated_at={}",
+            log_key(key, self.redact_log_keys),
+            at.to_rfc3339()
+        );
         Ok(())
     }
 
     /// Replaces the database contents with the provided entries atomically.
     pub fn replace_all(&mut self, entries: &[(String, Entry)]) -> KvResult<()> {
-        let tx = self.conn.transaction()?;
+        let tx = self.write_transaction()?;
         tx.execute("DELETE FROM kv", [])?;
+        tx.execute("DELETE FROM kv_tags", [])?;
         for (key, entry) in entries {
             Self::execute_upsert(&tx, key, entry)?;
         }
@@ -104,25 +428,109 @@ impl Database {
         Ok(())
     }
 
+    /// Like [`Database::replace_all`], but `feed` hands over entries one at a time inside
+    /// the transaction, so a large import never has to sit in memory. An error from
+    /// `feed` rolls everything back. Returns how many entries were written.
+    pub fn replace_all_streaming(
+        &mut self,
+        feed: impl FnOnce(&mut dyn FnMut(String, Entry) -> KvResult<()>) -> KvResult<()>,
+    ) -> KvResult<usize> {
+        let count = self.write_streaming(true, feed)?;
+        info!("replaced all entries from a stream (count={count})");
+        Ok(count)
+    }
+
+    /// Like [`Database::replace_all_streaming`], but keys not handed over by `feed` are
+    /// left alone.
+    pub fn upsert_streaming(
+        &mut self,
+        feed: impl FnOnce(&mut dyn FnMut(String, Entry) -> KvResult<()>) -> KvResult<()>,
+    ) -> KvResult<usize> {
+        let count = self.write_streaming(false, feed)?;
+        info!("stored entries from a stream (count={count})");
+        Ok(count)
+    }
+
+    fn write_streaming(
+        &mut self,
+        clear: bool,
+        feed: impl FnOnce(&mut dyn FnMut(String, Entry) -> KvResult<()>) -> KvResult<()>,
+    ) -> KvResult<usize> {
+        let tx = self.write_transaction()?;
+        if clear {
+            tx.execute("DELETE FROM kv", [])?;
+            tx.execute("DELETE FROM kv_tags", [])?;
+        }
+        let mut count = 0;
+        let mut aliases = Vec::new();
+        feed(&mut |key, entry| {
+            Self::execute_upsert(&tx, &key, &entry)?;
+            if let Some(target) = entry.alias_of() {
+                aliases.push((key, target.to_string()));
+            }
+            count += 1;
+            Ok(())
+        })?;
+        // Targets may arrive after their aliases, so they are checked once all is written.
+        for (key, target) in &aliases {
+            let found: Option<Option<String>> = tx
+                .query_row(
+                    "SELECT alias_of FROM kv WHERE key = ?1",
+                    params![target],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            match found {
+                Some(None) => {}
+                Some(Some(_)) => return Err(bad_alias_target(key, target, true)),
+                None => return Err(bad_alias_target(key, target, false)),
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Number of stored entries, counted without loading them.
+    pub fn entry_count(&self) -> KvResult<usize> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM kv", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
     fn execute_upsert(tx: &Transaction<'_>, key: &str, entry: &Entry) -> KvResult<()> {
         let tags_json = entry.tags_json()?;
         tx.execute(
-            "INSERT INTO kv (key, value, tags, created_at, updated_at, expires_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "INSERT INTO kv (key, value, tags, created_at, updated_at, expires_at, value_type, seq,
+                             notes, alias_of)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7,
+                     COALESCE(?8, (SELECT COALESCE(MAX(seq), 0) + 1 FROM kv)), ?9, ?10)
              ON CONFLICT(key)
              DO UPDATE SET value = excluded.value,
                            tags = excluded.tags,
                            updated_at = excluded.updated_at,
-                           expires_at = excluded.expires_at",
+                           expires_at = excluded.expires_at,
+                           value_type = excluded.value_type,
+                           notes = excluded.notes,
+                           alias_of = excluded.alias_of",
             params![
                 key,
-                entry.value(),
+                entry.payload(),
                 tags_json,
                 entry.created_at().to_rfc3339(),
                 entry.updated_at().to_rfc3339(),
                 entry.expires_at().map(|ts| ts.to_rfc3339()),
+                entry.payload().type_name(),
+                entry.seq(),
+                entry.notes(),
+                entry.alias_of(),
             ],
         )?;
+        tx.execute("DELETE FROM kv_tags WHERE key = ?1", params![key])?;
+        let mut insert_tag = tx.prepare_cached("INSERT INTO kv_tags (key, tag) VALUES (?1, ?2)")?;
+        for tag in entry.tags() {
+            insert_tag.execute(params![key, tag])?;
+        }
         Ok(())
     }
 
@@ -157,25 +565,199 @@ impl Database {
                     tags TEXT NOT NULL,
                     created_at TEXT NOT NULL,
                     updated_at TEXT NOT NULL,
-                    expires_at TEXT
+                    expires_at TEXT,
+                    value_type TEXT NOT NULL DEFAULT 'text',
+                    seq INTEGER,
+                    notes TEXT,
+                    alias_of TEXT
                 );
-                PRAGMA user_version = 2;
+                CREATE TABLE kv_tags (
+                    key TEXT NOT NULL,
+                    tag TEXT NOT NULL,
+                    PRIMARY KEY (key, tag)
+                );
+                CREATE INDEX kv_tags_tag ON kv_tags (tag);
             ",
             )?;
+            tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
             tx.commit()?;
-            info!("initialized kv schema (user_version=2)");
+            info!("initialized kv schema (user_version={SCHEMA_VERSION})");
             return Ok(());
         }
 
-        if user_version != 2 {
+        if !(MIN_SUPPORTED_VERSION..=SCHEMA_VERSION).contains(&user_version) {
             return Err(KvError::InvalidInput(format!(
                 "unsupported database schema version {user_version}; delete the database file to recreate it"
             )));
         }
 
+        if user_version < SCHEMA_VERSION {
+            self.migrate_schema(user_version)?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies each schema step newer than `from` inside a single transaction.
+    fn migrate_schema(&mut self, from: i64) -> KvResult<()> {
+        let tx = self.conn.transaction()?;
+        if from < 3 {
+            tx.execute_batch("ALTER TABLE kv ADD COLUMN value_type TEXT NOT NULL DEFAULT 'text';")?;
+        }
+        if from < 4 {
+            // Upserts never change rowid, so it is the best record of insertion order.
+            tx.execute_batch("ALTER TABLE kv ADD COLUMN seq INTEGER; UPDATE kv SET seq = rowid;")?;
+        }
+        if from < 5 {
+            tx.execute_batch("ALTER TABLE kv ADD COLUMN notes TEXT;")?;
+        }
+        if from < 6 {
+            // Rows with unparsable tags are skipped here; `kv verify` reports them.
+            tx.execute_batch(
+                "CREATE TABLE kv_tags (
+                     key TEXT NOT NULL,
+                     tag TEXT NOT NULL,
+                     PRIMARY KEY (key, tag)
+                 );
+                 CREATE INDEX kv_tags_tag ON kv_tags (tag);
+                 INSERT OR IGNORE INTO kv_tags (key, tag)
+                     SELECT kv.key, tags.value
+                     FROM kv, json_each(CASE WHEN json_valid(kv.tags)
+                                              AND json_type(kv.tags) = 'array'
+                                             THEN kv.tags ELSE '[]' END) AS tags;",
+            )?;
+        }
+        if from < 7 {
+            tx.execute_batch("ALTER TABLE kv ADD COLUMN alias_of TEXT;")?;
+        }
+        tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        tx.commit()?;
+        info!("migrated kv schema from user_version={from} to {SCHEMA_VERSION}");
+        Ok(())
+    }
+
+    /// Copies the whole database to a new file at `path` with SQLite's online backup
+    /// API, so concurrent writers never leave the copy half-updated.
+    pub fn backup_to(&self, path: &Path) -> KvResult<()> {
+        self.conn.backup(MAIN_DB, path, None)?;
+        info!("backed up database to {}", path.display());
+        Ok(())
+    }
+
+    /// Replaces every table with the contents of the database at `path`.
+    pub fn restore_from(&mut self, path: &Path) -> KvResult<()> {
+        self.conn.restore(MAIN_DB, path, None::<fn(Progress)>)?;
+        info!("restored database from {}", path.display());
         Ok(())
     }
 
+    /// Renders the schema and every row as SQL that `sqlite3` can replay into an
+    /// empty database, including the `user_version` pragma.
+    pub fn dump_sql(&self) -> KvResult<String> {
+        let user_version: i64 = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let mut output = String::new();
+        writeln!(output, "PRAGMA user_version = {user_version};").ok();
+        output.push_str("BEGIN TRANSACTION;\n");
+
+        let mut tables = self.conn.prepare(
+            "SELECT name, sql FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let tables = tables
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (name, create_sql) in tables {
+            writeln!(output, "{create_sql};").ok();
+            let table = quote_identifier(&name);
+            let mut stmt = self.conn.prepare(&format!("SELECT * FROM {table}"))?;
+            let columns = stmt.column_count();
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let values = (0..columns)
+                    .map(|idx| row.get_ref(idx).map(sql_literal))
+                    .collect::<Result<Vec<_>, _>>()?;
+                writeln!(output, "INSERT INTO {table} VALUES({});", values.join(",")).ok();
+            }
+        }
+
+        let mut indexes = self.conn.prepare(
+            "SELECT sql FROM sqlite_master
+             WHERE type IN ('index', 'trigger', 'view') AND sql IS NOT NULL ORDER BY name",
+        )?;
+        for sql in indexes.query_map([], |row| row.get::<_, String>(0))? {
+            writeln!(output, "{};", sql?).ok();
+        }
+
+        output.push_str("COMMIT;\n");
+        Ok(output)
+    }
+
+    /// Checks every row's raw columns for problems that would break loading, without
+    /// stopping at the first bad row the way [`Database::load_entries`] does.
+    pub fn verify(&self) -> KvResult<VerifyReport> {
+        let mut stmt = self.conn.prepare(
+            "SELECT key, tags, created_at, updated_at, expires_at FROM kv ORDER BY key ASC",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut report = VerifyReport::default();
+        while let Some(row) = rows.next()? {
+            report.checked += 1;
+            let key = raw_text(row.get_ref(0)?).unwrap_or_default();
+            let mut flag = |problem: String| {
+                report.issues.push(VerifyIssue {
+                    key: key.clone(),
+                    problem,
+                })
+            };
+
+            match raw_text(row.get_ref(1)?) {
+                Some(tags) if !tags.trim().is_empty() => {
+                    if let Err(error) = serde_json::from_str::<Vec<String>>(&tags) {
+                        flag(format!("tags are not a JSON string array: {error}"));
+                    }
+                }
+                Some(_) => {}
+                None => flag("tags column is NULL".to_string()),
+            }
+
+            let mut timestamp = |column: &str, raw: Option<String>, required: bool| match raw {
+                Some(text) if !text.trim().is_empty() => {
+                    match DateTime::parse_from_rfc3339(&text) {
+                        Ok(parsed) => Some(parsed),
+                        Err(error) => {
+                            flag(format!("{column} '{text}' is not RFC3339: {error}"));
+                            None
+                        }
+                    }
+                }
+                _ if required => {
+                    flag(format!("{column} is missing"));
+                    None
+                }
+                _ => None,
+            };
+            let created_at = timestamp("created_at", raw_text(row.get_ref(2)?), true);
+            let updated_at = timestamp("updated_at", raw_text(row.get_ref(3)?), true);
+            timestamp("expires_at", raw_text(row.get_ref(4)?), false);
+
+            if let (Some(created_at), Some(updated_at)) = (created_at, updated_at) {
+                if created_at > updated_at {
+                    flag(format!(
+                        "created_at {} is after updated_at {}",
+                        created_at.to_rfc3339(),
+                        updated_at.to_rfc3339()
+                    ));
+                }
+            }
+        }
+        Ok(report)
+    }
+
     pub fn cleanup_expired_entries(&mut self) -> KvResult<usize> {
         let tx = self.conn.transaction()?;
         let threshold = (Utc::now() - Duration::hours(1)).to_rfc3339();
@@ -183,6 +765,12 @@ impl Database {
             "DELETE FROM kv WHERE expires_at IS NOT NULL AND expires_at <= ?1",
             params![threshold],
         )?;
+        if deleted > 0 {
+            tx.execute(
+                "DELETE FROM kv_tags WHERE key NOT IN (SELECT key FROM kv)",
+                [],
+            )?;
+        }
         tx.commit()?;
         if deleted > 0 {
             info!("cleaned {} ttl-expired entries", deleted);
@@ -191,11 +779,509 @@ impl Database {
     }
 }
 
+impl ToSql for EntryValue {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(match self {
+            EntryValue::Text(text) => ToSqlOutput::from(text.as_str()),
+            EntryValue::Blob(bytes) => ToSqlOutput::from(bytes.as_slice()),
+        })
+    }
+}
+
+/// Outcome of [`Database::verify`].
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+/// One problem found in a stored row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyIssue {
+    pub key: String,
+    pub problem: String,
+}
+
+/// Reads a column as text regardless of its storage class; `None` for NULL.
+fn raw_text(value: ValueRef<'_>) -> Option<String> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(number) => Some(number.to_string()),
+        ValueRef::Real(number) => Some(number.to_string()),
+        ValueRef::Text(bytes) | ValueRef::Blob(bytes) => {
+            Some(String::from_utf8_lossy(bytes).into_owned())
+        }
+    }
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Formats a column value as an SQLite literal: text is single-quoted with quotes
+/// doubled, blobs become `X'..'` hex.
+fn sql_literal(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => "NULL".to_string(),
+        ValueRef::Integer(number) => number.to_string(),
+        ValueRef::Real(number) => format!("{number:?}"),
+        ValueRef::Text(bytes) => {
+            format!("'{}'", String::from_utf8_lossy(bytes).replace('\'', "''"))
+        }
+        ValueRef::Blob(bytes) => {
+            let mut hex = String::with_capacity(bytes.len() * 2 + 3);
+            hex.push_str("X'");
+            for byte in bytes {
+                write!(hex, "{byte:02X}").ok();
+            }
+            hex.push('\'');
+            hex
+        }
+    }
+}
+
+/// Columns read by [`Row::read`], in order.
+const ROW_COLUMNS: &str =
+    "key, value, tags, created_at, updated_at, expires_at, value_type, seq, notes, alias_of";
+
 struct Row {
     key: String,
-    value: String,
+    value: EntryValue,
     tags: String,
     created_at: String,
     updated_at: String,
     expires_at: Option<String>,
+    seq: Option<i64>,
+    notes: Option<String>,
+    alias_of: Option<String>,
+}
+
+impl Row {
+    fn read(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        let value_type: String = row.get(6)?;
+        let value = if value_type == EntryValue::BLOB {
+            EntryValue::Blob(row.get(1)?)
+        } else {
+            EntryValue::Text(row.get(1)?)
+        };
+        Ok(Row {
+            key: row.get(0)?,
+            value,
+            tags: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+            expires_at: row.get(5)?,
+            seq: row.get(7)?,
+            notes: row.get(8)?,
+            alias_of: row.get(9)?,
+        })
+    }
+
+    fn into_entry(self) -> KvResult<(String, Entry)> {
+        let entry = Entry::from_persisted(
+            self.value,
+            &self.tags,
+            &self.created_at,
+            &self.updated_at,
+            self.expires_at.as_deref(),
+        )?;
+        let entry = match self.seq {
+            Some(seq) => entry.with_seq(seq),
+            None => entry,
+        };
+        let entry = entry.with_notes(self.notes).with_alias_of(self.alias_of);
+        Ok((self.key, entry))
+    }
+}
+
+/// Whether `path` is the [`IN_MEMORY`] sentinel rather than a file.
+pub fn is_in_memory(path: &Path) -> bool {
+    path.as_os_str() == IN_MEMORY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn connect_existing_refuses_to_create_the_file() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("missing").join("data.db");
+        let error = Database::connect_existing(&path).err().unwrap();
+        assert!(matches!(error, KvError::InvalidInput(_)), "{error}");
+        assert!(!path.parent().unwrap().exists());
+
+        Database::connect(&path).unwrap();
+        assert!(Database::connect_existing(&path).is_ok());
+    }
+
+    #[test]
+    fn streamed_aliases_are_checked_before_commit() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        let alias = |target: &str| Entry::alias(None, target.to_string());
+        db.replace_all_streaming(|write| {
+            write("current".to_string(), alias("api"))?;
+            write("api".to_string(), Entry::new("v".to_string(), vec![]))
+        })
+        .unwrap();
+
+        let error = db
+            .upsert_streaming(|write| write("latest".to_string(), alias("current")))
+            .unwrap_err();
+        assert!(matches!(error, KvError::InvalidInput(_)), "{error}");
+        let error = db
+            .replace_all_streaming(|write| write("current".to_string(), alias("gone")))
+            .unwrap_err();
+        assert!(matches!(error, KvError::InvalidInput(_)), "{error}");
+        assert_eq!(
+            db.entry_count().unwrap(),
+            2,
+            "failed imports must roll back"
+        );
+    }
+
+    #[test]
+    fn read_only_databases_are_never_created_or_written() {
+        let temp = tempdir().unwrap();
+        let missing = temp.path().join("missing.db");
+        let error = Database::open_read_only(&missing).err().unwrap();
+        assert!(matches!(error, KvError::InvalidInput(_)), "{error}");
+        assert!(!missing.exists());
+
+        let path = temp.path().join("data.db");
+        Database::connect(&path)
+            .unwrap()
+            .upsert_entry("k", &Entry::new("v".to_string(), vec![]))
+            .unwrap();
+        let mut db = Database::open_read_only(&path).unwrap();
+        assert_eq!(db.load_entries().unwrap().len(), 1);
+        assert!(db
+            .upsert_entry("other", &Entry::new("v".to_string(), vec![]))
+            .is_err());
+    }
+
+    #[test]
+    fn in_memory_databases_are_private_and_leave_no_file() {
+        let mut db = Database::connect(IN_MEMORY).unwrap();
+        db.upsert_entry("k", &Entry::new("v".to_string(), vec![]))
+            .unwrap();
+        assert_eq!(db.load_entries().unwrap().len(), 1);
+        assert!(!Path::new(IN_MEMORY).exists());
+
+        let other = Database::connect(IN_MEMORY).unwrap();
+        assert!(other.load_entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn binary_values_round_trip_through_sqlite() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        let bytes = vec![0u8, 159, 146, 150, 255];
+        db.upsert_entry("blob", &Entry::new(bytes.clone(), vec![]))
+            .unwrap();
+        db.upsert_entry("text", &Entry::new("plain".to_string(), vec![]))
+            .unwrap();
+
+        let entries = db.load_entries().unwrap();
+        assert_eq!(entries[0].0, "blob");
+        assert_eq!(entries[0].1.payload(), &EntryValue::Blob(bytes));
+        assert_eq!(entries[1].1.payload(), &EntryValue::Text("plain".into()));
+    }
+
+    #[test]
+    fn version_two_schema_is_migrated_in_place() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("data.db");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE kv (
+                    key TEXT PRIMARY KEY,
+                    value TEXT NOT NULL,
+                    tags TEXT NOT NULL,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    expires_at TEXT
+                );
+                INSERT INTO kv VALUES ('old', 'v', '[\"t\"]', '2024-01-01T00:00:00+00:00',
+                                       '2024-01-01T00:00:00+00:00', NULL);
+                PRAGMA user_version = 2;",
+            )
+            .unwrap();
+        }
+
+        let db = Database::connect(&path).unwrap();
+        let entries = db.load_entries().unwrap();
+        assert_eq!(entries[0].1.value(), "v");
+        assert_eq!(entries[0].1.seq(), Some(1));
+        assert_eq!(entries[0].1.notes(), None);
+        assert_eq!(db.keys_with_tag("t").unwrap(), ["old"]);
+        let version: i64 = db
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn restore_brings_back_a_backup() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        db.upsert_entry("kept", &Entry::new("v1".to_string(), vec!["t".into()]))
+            .unwrap();
+        let copy = temp.path().join("copy.db");
+        db.backup_to(&copy).unwrap();
+
+        db.upsert_entry("kept", &Entry::new("v2".to_string(), vec![]))
+            .unwrap();
+        db.upsert_entry("added", &Entry::new("x".to_string(), vec![]))
+            .unwrap();
+        db.restore_from(&copy).unwrap();
+
+        let entries = db.load_entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].1.value(), "v1");
+        assert_eq!(db.keys_with_tag("t").unwrap(), ["kept"]);
+    }
+
+    #[test]
+    fn dump_sql_replays_into_an_empty_database() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        db.upsert_entry(
+            "it's",
+            &Entry::new("say 'hi'".to_string(), vec!["a".into()]),
+        )
+        .unwrap();
+        db.upsert_entry("blob", &Entry::new(vec![0u8, 255], vec![]))
+            .unwrap();
+
+        let dump = db.dump_sql().unwrap();
+        assert!(dump.contains("'say ''hi'''"));
+        assert!(dump.contains("X'00FF'"));
+
+        let replay_path = temp.path().join("replay.db");
+        Connection::open(&replay_path)
+            .unwrap()
+            .execute_batch(&dump)
+            .unwrap();
+        let replayed = Database::connect(&replay_path).unwrap();
+        let original = db.load_entries().unwrap();
+        let restored = replayed.load_entries().unwrap();
+        assert_eq!(original.len(), restored.len());
+        for ((key, entry), (restored_key, restored_entry)) in original.iter().zip(&restored) {
+            assert_eq!(key, restored_key);
+            assert_eq!(entry.payload(), restored_entry.payload());
+            assert_eq!(entry.tags(), restored_entry.tags());
+        }
+    }
+
+    #[test]
+    fn verify_reports_every_corrupt_row() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        db.upsert_entry("good", &Entry::new("v".to_string(), vec!["t".into()]))
+            .unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO kv (key, value, tags, created_at, updated_at) VALUES
+                 ('bad-tags', 'v', '{oops', '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00'),
+                 ('bad-time', 'v', '[]', 'yesterday', '2024-01-01T00:00:00+00:00'),
+                 ('backwards', 'v', '[]', '2024-02-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00');",
+            )
+            .unwrap();
+
+        let report = db.verify().unwrap();
+        assert_eq!(report.checked, 4);
+        let keys: Vec<_> = report
+            .issues
+            .iter()
+            .map(|issue| issue.key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["backwards", "bad-tags", "bad-time"]);
+        assert!(report.issues[0].problem.contains("after updated_at"));
+    }
+
+    #[test]
+    fn touch_updates_only_updated_at() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        let entry = Entry::new("v".to_string(), vec!["t".into()]);
+        db.upsert_entry("k", &entry).unwrap();
+
+        let later = entry.updated_at() + Duration::hours(2);
+        db.touch("k", later).unwrap();
+        let (_, loaded) = db.load_entries().unwrap().remove(0);
+        assert_eq!(loaded.updated_at(), later);
+        assert_eq!(loaded.created_at(), entry.created_at());
+        assert_eq!(loaded.value(), "v");
+
+        assert!(matches!(
+            db.touch("missing", later),
+            Err(KvError::NotFound(key)) if key == "missing"
+        ));
+    }
+
+    #[test]
+    fn truncate_checkpoint_empties_the_wal() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        db.upsert_entry("k", &Entry::new("v".to_string(), vec![]))
+            .unwrap();
+
+        let report = db.checkpoint(CheckpointMode::Truncate).unwrap();
+        assert!(!report.busy);
+        assert_eq!(report.log_frames, 0);
+        let wal = std::fs::metadata(temp.path().join("data.db-wal")).unwrap();
+        assert_eq!(wal.len(), 0);
+    }
+
+    #[test]
+    fn writes_fail_after_another_connection_commits() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("data.db");
+        let mut ours = Database::connect(&path).unwrap();
+        let mut theirs = Database::connect(&path).unwrap();
+        let entry = Entry::new("v".to_string(), vec![]);
+
+        ours.load_entries().unwrap();
+        ours.upsert_entry("mine", &entry).unwrap();
+        theirs.upsert_entry("external", &entry).unwrap();
+
+        let error = ours.upsert_entry("mine", &entry).unwrap_err();
+        assert!(error.to_string().contains("changed since load"), "{error}");
+        assert_eq!(ours.load_entries().unwrap().len(), 2);
+        ours.upsert_entry("mine", &entry).unwrap();
+    }
+
+    #[test]
+    fn notes_round_trip_and_survive_updates() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        let entry = Entry::new("v".to_string(), vec![]).with_notes(Some("why".into()));
+        db.upsert_entry("k", &entry).unwrap();
+
+        let loaded = db.load_entries().unwrap();
+        assert_eq!(loaded[0].1.notes(), Some("why"));
+        db.upsert_entry("k", &Entry::for_update(Some(&loaded[0].1), "new", vec![]))
+            .unwrap();
+        assert_eq!(db.load_entries().unwrap()[0].1.notes(), Some("why"));
+    }
+
+    #[test]
+    fn seq_is_assigned_on_insert_and_kept_on_update() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        for key in ["b", "a"] {
+            db.upsert_entry(key, &Entry::new("v".to_string(), vec![]))
+                .unwrap();
+        }
+        let loaded = db.load_entries().unwrap();
+        let (_, b) = &loaded[1];
+        db.upsert_entry("b", &Entry::for_update(Some(b), "new", vec![]))
+            .unwrap();
+
+        let seqs: Vec<_> = db
+            .load_entries()
+            .unwrap()
+            .into_iter()
+            .map(|(key, entry)| (key, entry.seq()))
+            .collect();
+        assert_eq!(seqs, [("a".into(), Some(2)), ("b".into(), Some(1))]);
+    }
+
+    #[test]
+    fn non_database_file_gets_a_friendly_error() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("data.db");
+        std::fs::write(
+            &path,
+            r#"{"key": "value", "other": "a longer value to fill the header"}"#,
+        )
+        .unwrap();
+
+        let message = match Database::connect(&path) {
+            Err(error) => error.to_string(),
+            Ok(_) => panic!("text file opened as a database"),
+        };
+        assert!(message.contains("is not a SQLite database"), "{message}");
+        assert!(message.contains("kv migrate --from"), "{message}");
+    }
+
+    #[test]
+    fn zero_byte_file_opens_as_an_empty_database() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("data.db");
+        std::fs::write(&path, b"").unwrap();
+
+        let db = Database::connect(&path).unwrap();
+        assert!(db.load_entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tag_index_follows_upserts_and_deletes() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        let tagged = |tags: &[&str]| {
+            Entry::new(
+                "v".to_string(),
+                tags.iter().map(|tag| tag.to_string()).collect(),
+            )
+        };
+        db.upsert_entry("a", &tagged(&["x", "y"])).unwrap();
+        db.upsert_entry("b", &tagged(&["x"])).unwrap();
+        assert_eq!(db.keys_with_tag("x").unwrap(), ["a", "b"]);
+
+        db.upsert_entry("a", &tagged(&["y"])).unwrap();
+        assert_eq!(db.keys_with_tag("x").unwrap(), ["b"]);
+        db.delete_entry("b").unwrap();
+        assert!(db.keys_with_tag("x").unwrap().is_empty());
+        db.replace_all(&[("c".to_string(), tagged(&["y"]))])
+            .unwrap();
+        assert_eq!(db.keys_with_tag("y").unwrap(), ["c"]);
+    }
+
+    #[test]
+    fn keys_with_prefix_stops_at_the_end_of_the_range() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        for key in ["app", "project/a", "project/b", "projects", "zeta"] {
+            db.upsert_entry(key, &Entry::new("v".to_string(), vec![]))
+                .unwrap();
+        }
+        assert_eq!(
+            db.keys_with_prefix("project/").unwrap(),
+            ["project/a", "project/b"]
+        );
+        assert_eq!(db.keys_with_prefix("").unwrap().len(), 5);
+        assert!(db.keys_with_prefix("nope").unwrap().is_empty());
+    }
+
+    #[test]
+    fn delete_entries_is_all_or_nothing() {
+        let temp = tempdir().unwrap();
+        let mut db = Database::connect(temp.path().join("data.db")).unwrap();
+        for key in ["a", "b", "c"] {
+            db.upsert_entry(key, &Entry::new("v".to_string(), vec![]))
+                .unwrap();
+        }
+
+        let batch = ["a".to_string(), "missing".to_string()];
+        assert!(matches!(
+            db.delete_entries(&batch),
+            Err(KvError::NotFound(key)) if key == "missing"
+        ));
+        assert_eq!(db.load_entries().unwrap().len(), 3);
+
+        assert_eq!(db.delete_entries(&["a".into(), "c".into()]).unwrap(), 2);
+        let remaining: Vec<_> = db
+            .load_entries()
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(remaining, ["b"]);
+    }
 }
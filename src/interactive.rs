@@ -1,167 +1,814 @@
-use std::io::{self, stdout, Write};
-use std::time::Duration;
+use std::fs;
+use std::io::{self, stderr, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use crossterm::cursor::{Hide, MoveToColumn, MoveUp, Show};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use crossterm::queue;
 use crossterm::terminal::{self, ClearType};
+use log::warn;
+use serde::Deserialize;
 
-use crate::store::{SearchScope, Store};
+use crate::db::Database;
+use crate::output::OutputStyle;
+use crate::store::{
+    MatchIndices, SearchAlgorithm, SearchOptions, SearchResult, SearchScope, SearchWeights, Store,
+};
 use crate::KvResult;
 
-const POLL_INTERVAL: Duration = Duration::from_millis(120);
 const KEY_PREVIEW_CHARS: usize = 56;
 const VALUE_PREVIEW_CHARS: usize = 110;
 const TAGS_PREVIEW_CHARS: usize = 56;
+/// Rows kept free for the query line, the `(n more)` indicator, and the cursor line.
+const RESERVED_ROWS: usize = 3;
+const DEFAULT_RESULT_ROWS: usize = 20;
+const EMACS_HELP: &[&str] = &[
+    "Keybindings (press any key to close)",
+    "  Up/Down, Ctrl-P/Ctrl-N   move selection",
+    "  Enter                    pick selection (prints key, or value with --print-value)",
+    "  Ctrl-Y                   copy selected value to the clipboard",
+    "  Ctrl-X                   delete selected entry (asks y/N)",
+    "  Tab, Ctrl-T              cycle scope: all -> keys -> tags",
+    "  Left/Right, Home/End     move cursor (Ctrl-A/Ctrl-E for start/end)",
+    "  Backspace/Delete         delete character before/under cursor",
+    "  Ctrl-W / Ctrl-U          delete previous word / clear to start",
+    "  F1, ? on empty query     toggle this help",
+    "  Esc, Ctrl-C              exit without picking",
+];
+const VIM_HELP: &[&str] = &[
+    "Keybindings (press any key to close)",
+    "  Esc                      insert -> normal mode",
+    "  i / a / I / A            normal -> insert (at, after, start, end)",
+    "  j/k, Up/Down             move selection",
+    "  h/l, 0/$                 move cursor",
+    "  x                        delete character under cursor",
+    "  dd                       delete selected entry (asks y/N)",
+    "  Enter                    pick selection",
+    "  Ctrl-Y                   copy selected value to the clipboard",
+    "  Tab                      cycle scope: all -> keys -> tags",
+    "  F1, ? in normal mode     toggle this help",
+    "  q, Esc                   exit without picking (normal mode)",
+];
+const SELECTED_MARKER: &str = "> ";
+const UNSELECTED_MARKER: &str = "  ";
+
+/// Settings for a live search session, resolved from flags and config by the caller.
+#[derive(Debug, Clone)]
+pub struct LiveSearchOptions {
+    pub limit: usize,
+    pub scope: SearchScope,
+    pub style: OutputStyle,
+    pub highlight: bool,
+    /// Copy the value on Enter instead of printing the key.
+    pub copy_on_enter: bool,
+    /// Show the match count and search time under the query.
+    pub show_status: bool,
+    /// Maximum wait for a terminal event per loop iteration.
+    pub poll_interval: Duration,
+    /// Idle time after the last edit before searching; zero disables debouncing.
+    pub debounce: Duration,
+    pub keybindings: Keybindings,
+    pub weights: SearchWeights,
+    /// Matches scoring below this are hidden.
+    pub min_score: i64,
+    pub algorithm: SearchAlgorithm,
+    /// Where the last query is kept between sessions (`[interactive] remember_query`).
+    pub query_file: Option<PathBuf>,
+}
+
+/// Key map used by interactive mode (`[interactive] keybindings`).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Keybindings {
+    /// Readline-style editing; every printable key edits the query.
+    #[default]
+    Emacs,
+    /// Modal editing with normal and insert modes.
+    Vim,
+}
+
+/// Vim-mode state; ignored with emacs bindings.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+enum Mode {
+    #[default]
+    Insert,
+    Normal,
+}
+
+impl Mode {
+    fn label(self) -> &'static str {
+        match self {
+            Mode::Insert => "insert",
+            Mode::Normal => "normal",
+        }
+    }
+}
+
+/// Entry chosen when a session ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    pub key: String,
+    /// The caller should copy the value to the clipboard rather than print it.
+    pub copy: bool,
+}
+
+/// What the loop should do after a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Continue,
+    Cancel,
+    Accept,
+    Copy,
+    /// Ask for confirmation before deleting the selected entry.
+    Delete,
+}
+
+/// Mutable state of one session.
+#[derive(Debug, Default)]
+struct Session {
+    input: String,
+    /// Active scope; starts from the options and is cycled with Tab.
+    scope: SearchScope,
+    /// Cursor position in `input`, counted in chars rather than bytes.
+    cursor: usize,
+    selected: usize,
+    result_count: usize,
+    /// Index of the first result shown in the viewport.
+    scroll: usize,
+    /// Key awaiting a y/N confirmation before it is deleted.
+    pending_delete: Option<String>,
+    /// One-off feedback shown under the query until the next key press.
+    message: Option<String>,
+    /// Set while the query changed within the debounce window and the search is deferred.
+    typing: bool,
+    keybindings: Keybindings,
+    mode: Mode,
+    /// First key of a pending vim operator such as `dd`.
+    pending_operator: Option<char>,
+    /// Keybinding overlay shown in place of the results.
+    show_help: bool,
+    /// Matches of the last search, reused while the query only grows.
+    narrowed: Option<Narrowed>,
+}
+
+/// Every key that matched `query` in `scope`, ignoring the limit and min score.
+#[derive(Debug)]
+struct Narrowed {
+    query: String,
+    scope: SearchScope,
+    keys: Vec<String>,
+}
+
+impl Session {
+    fn byte_offset(&self, char_idx: usize) -> usize {
+        self.input
+            .char_indices()
+            .nth(char_idx)
+            .map_or(self.input.len(), |(offset, _)| offset)
+    }
+
+    fn char_len(&self) -> usize {
+        self.input.chars().count()
+    }
+
+    fn insert_char(&mut self, c: char) {
+        let offset = self.byte_offset(self.cursor);
+        self.input.insert(offset, c);
+        self.cursor += 1;
+    }
+
+    fn delete_before_cursor(&mut self) {
+        if self.cursor == 0 {
+            return;
+        }
+        self.cursor -= 1;
+        let offset = self.byte_offset(self.cursor);
+        self.input.remove(offset);
+    }
+
+    fn delete_at_cursor(&mut self) {
+        if self.cursor < self.char_len() {
+            let offset = self.byte_offset(self.cursor);
+            self.input.remove(offset);
+        }
+    }
+
+    /// Removes the word before the cursor along with any whitespace after it.
+    fn delete_word_before_cursor(&mut self) {
+        let chars: Vec<char> = self.input.chars().collect();
+        let mut start = self.cursor;
+        while start > 0 && chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.delete_range(start, self.cursor);
+    }
+
+    fn delete_to_start(&mut self) {
+        self.delete_range(0, self.cursor);
+    }
+
+    fn delete_range(&mut self, start: usize, end: usize) {
+        let range = self.byte_offset(start)..self.byte_offset(end);
+        self.input.replace_range(range, "");
+        self.cursor = start;
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        self.cursor = self
+            .cursor
+            .saturating_add_signed(delta)
+            .min(self.char_len());
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let last = self.result_count.saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    fn clamp_selection(&mut self) {
+        self.selected = self.selected.min(self.result_count.saturating_sub(1));
+    }
+
+    /// Shifts the viewport of `rows` results so the selection stays visible.
+    fn scroll_to_selection(&mut self, rows: usize) {
+        let rows = rows.max(1);
+        let max_scroll = self.result_count.saturating_sub(rows);
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + rows {
+            self.scroll = self.selected + 1 - rows;
+        }
+        self.scroll = self.scroll.min(max_scroll);
+    }
+}
 
 /// Runs an interactive fuzzy-search session that refreshes results as the user types.
-pub fn live_search(storage: &Store, limit: usize, scope: SearchScope) -> KvResult<()> {
-    let mut stdout = stdout();
+///
+/// The UI is drawn on stderr so stdout stays clean for shell substitution; the key
+/// selected with Enter (or Ctrl-Y to copy) is returned, or `None` when the session
+/// was cancelled. Entries deleted with Ctrl-X are removed from both `storage` and
+/// `database` immediately.
+pub fn live_search(
+    storage: &mut Store,
+    database: &mut Database,
+    options: &LiveSearchOptions,
+) -> KvResult<Option<Selection>> {
+    let mut out = stderr();
+    let input = options
+        .query_file
+        .as_deref()
+        .map(load_query)
+        .unwrap_or_default();
     let guard = RawTerminalGuard::new()?;
-    let mut input = String::new();
+    let mut session = Session {
+        cursor: input.chars().count(),
+        input,
+        scope: options.scope,
+        keybindings: options.keybindings,
+        ..Session::default()
+    };
     let mut needs_render = true;
-    let mut rendered_lines = 0usize;
     let mut first_draw = true;
+    let mut last_edit = Instant::now();
 
-    loop {
+    let action = loop {
         if needs_render {
             if !first_draw {
-                clear_previous(&mut stdout, rendered_lines)?;
+                clear_previous(&mut out)?;
             } else {
                 first_draw = false;
             }
-            rendered_lines = render(&mut stdout, storage, &input, limit, scope)?;
+            render(&mut out, storage, &mut session, options)?;
             needs_render = false;
         }
 
-        if !event::poll(POLL_INTERVAL)? {
+        let mut timeout = options.poll_interval;
+        if session.typing {
+            let idle = last_edit.elapsed();
+            if idle >= options.debounce {
+                session.typing = false;
+                needs_render = true;
+                continue;
+            }
+            timeout = timeout.min(options.debounce - idle);
+        }
+
+        if !event::poll(timeout)? {
             continue;
         }
 
         match event::read()? {
             Event::Key(key) if key.kind == KeyEventKind::Press => {
-                if handle_key_event(key, &mut input) {
-                    break;
-                }
                 needs_render = true;
+                session.message = None;
+                if session.show_help {
+                    session.show_help = false;
+                    continue;
+                }
+                if let Some(pending) = session.pending_delete.take() {
+                    confirm_delete(key, pending, storage, database, &mut session)?;
+                    continue;
+                }
+                let previous_input = session.input.clone();
+                match handle_key_event(key, &mut session) {
+                    Action::Continue => {
+                        if !options.debounce.is_zero() && session.input != previous_input {
+                            session.typing = true;
+                            last_edit = Instant::now();
+                        }
+                    }
+                    Action::Delete => {
+                        session.pending_delete = selected_key(storage, &mut session, options);
+                    }
+                    action => break action,
+                }
             }
             Event::Resize(_, _) => {
                 needs_render = true;
             }
             _ => {}
         }
-    }
+    };
 
+    clear_previous(&mut out)?;
     drop(guard);
+
+    if let Some(path) = &options.query_file {
+        let confirmed = matches!(action, Action::Accept | Action::Copy);
+        if let Err(error) = save_query(path, &session.input, confirmed) {
+            warn!(
+                "failed to save interactive query '{}': {error}",
+                path.display()
+            );
+        }
+    }
+
+    let copy = match action {
+        Action::Accept => options.copy_on_enter,
+        Action::Copy => true,
+        Action::Continue | Action::Cancel | Action::Delete => return Ok(None),
+    };
+    Ok(selected_key(storage, &mut session, options).map(|key| Selection { key, copy }))
+}
+
+/// The query saved by the previous session, or empty when there is none.
+fn load_query(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default()
+}
+
+/// Remembers a non-empty `query` for the next session. An empty one forgets the saved
+/// query only when `confirmed` (the session ended by picking an entry), so leaving
+/// with Esc after clearing the line keeps it.
+fn save_query(path: &Path, query: &str, confirmed: bool) -> io::Result<()> {
+    if !query.is_empty() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        return fs::write(path, query);
+    }
+    if !confirmed {
+        return Ok(());
+    }
+    match fs::remove_file(path) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+fn selected_key(
+    storage: &Store,
+    session: &mut Session,
+    options: &LiveSearchOptions,
+) -> Option<String> {
+    search(storage, session, options)
+        .into_iter()
+        .nth(session.selected)
+        .map(|result| result.key.to_string())
+}
+
+/// Resolves a pending Ctrl-X: `y` deletes the entry, any other key keeps it.
+fn confirm_delete(
+    event: KeyEvent,
+    key: String,
+    storage: &mut Store,
+    database: &mut Database,
+    session: &mut Session,
+) -> KvResult<()> {
+    if matches!(event.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+        database.delete_entry(&key)?;
+        storage.remove(&key);
+        session.message = Some(format!("Deleted '{key}'."));
+    } else {
+        session.message = Some(format!("Kept '{key}'."));
+    }
     Ok(())
 }
 
-fn handle_key_event(event: KeyEvent, input: &mut String) -> bool {
-    if event.modifiers.contains(KeyModifiers::CONTROL) {
-        match event.code {
-            KeyCode::Char('c') | KeyCode::Char('d') => return true,
-            _ => return false,
+/// Places `text` on the system clipboard.
+pub fn copy_to_clipboard(text: &str) -> Result<(), arboard::Error> {
+    arboard::Clipboard::new()?.set_text(text)
+}
+
+/// Routes a key press to the bindings for the current mode.
+fn handle_key_event(event: KeyEvent, session: &mut Session) -> Action {
+    if event.code == KeyCode::F(1) {
+        session.show_help = true;
+        return Action::Continue;
+    }
+    match (session.keybindings, session.mode) {
+        (Keybindings::Vim, Mode::Normal) => handle_normal_key(event, session),
+        (Keybindings::Vim, Mode::Insert) if event.code == KeyCode::Esc => {
+            session.mode = Mode::Normal;
+            Action::Continue
         }
+        _ => handle_editing_key(event, session),
+    }
+}
+
+/// Vim normal mode: motions and commands instead of text entry.
+fn handle_normal_key(event: KeyEvent, session: &mut Session) -> Action {
+    let pending = session.pending_operator.take();
+    if event.modifiers.contains(KeyModifiers::CONTROL) {
+        return handle_editing_key(event, session);
     }
 
     match event.code {
-        KeyCode::Char(c) => {
-            input.push(c);
+        KeyCode::Char('j') | KeyCode::Down => session.move_selection(1),
+        KeyCode::Char('k') | KeyCode::Up => session.move_selection(-1),
+        KeyCode::Char('h') | KeyCode::Left => session.move_cursor(-1),
+        KeyCode::Char('l') | KeyCode::Right => session.move_cursor(1),
+        KeyCode::Char('0') | KeyCode::Home => session.cursor = 0,
+        KeyCode::Char('$') | KeyCode::End => session.cursor = session.char_len(),
+        KeyCode::Char('x') => session.delete_at_cursor(),
+        KeyCode::Char('i') => session.mode = Mode::Insert,
+        KeyCode::Char('a') => {
+            session.move_cursor(1);
+            session.mode = Mode::Insert;
         }
-        KeyCode::Backspace => {
-            input.pop();
+        KeyCode::Char('I') => {
+            session.cursor = 0;
+            session.mode = Mode::Insert;
         }
-        KeyCode::Esc | KeyCode::Enter => return true,
-        KeyCode::Delete => {
-            input.clear();
+        KeyCode::Char('A') => {
+            session.cursor = session.char_len();
+            session.mode = Mode::Insert;
         }
+        KeyCode::Char('d') if pending == Some('d') => return Action::Delete,
+        KeyCode::Char('d') => session.pending_operator = Some('d'),
+        KeyCode::Char('?') => session.show_help = true,
+        KeyCode::Tab => session.scope = session.scope.next(),
+        KeyCode::Enter => return Action::Accept,
+        KeyCode::Char('q') | KeyCode::Esc => return Action::Cancel,
         _ => {}
     }
-    false
+    Action::Continue
+}
+
+/// Emacs-style bindings, also used for vim insert mode.
+fn handle_editing_key(event: KeyEvent, session: &mut Session) -> Action {
+    if event.modifiers.contains(KeyModifiers::CONTROL) {
+        return match event.code {
+            KeyCode::Char('c') | KeyCode::Char('d') => Action::Cancel,
+            KeyCode::Char('y') => Action::Copy,
+            KeyCode::Char('x') => Action::Delete,
+            KeyCode::Char('p') => {
+                session.move_selection(-1);
+                Action::Continue
+            }
+            KeyCode::Char('n') => {
+                session.move_selection(1);
+                Action::Continue
+            }
+            KeyCode::Char('a') => {
+                session.cursor = 0;
+                Action::Continue
+            }
+            KeyCode::Char('e') => {
+                session.cursor = session.char_len();
+                Action::Continue
+            }
+            KeyCode::Char('w') => {
+                session.delete_word_before_cursor();
+                Action::Continue
+            }
+            KeyCode::Char('u') => {
+                session.delete_to_start();
+                Action::Continue
+            }
+            KeyCode::Char('t') => {
+                session.scope = session.scope.next();
+                Action::Continue
+            }
+            _ => Action::Continue,
+        };
+    }
+
+    match event.code {
+        KeyCode::Char('?') if session.input.is_empty() => session.show_help = true,
+        KeyCode::Char(c) => session.insert_char(c),
+        KeyCode::Backspace => session.delete_before_cursor(),
+        KeyCode::Delete => session.delete_at_cursor(),
+        KeyCode::Left => session.move_cursor(-1),
+        KeyCode::Right => session.move_cursor(1),
+        KeyCode::Home => session.cursor = 0,
+        KeyCode::End => session.cursor = session.char_len(),
+        KeyCode::Tab => session.scope = session.scope.next(),
+        KeyCode::Up => session.move_selection(-1),
+        KeyCode::Down => session.move_selection(1),
+        KeyCode::Esc => return Action::Cancel,
+        KeyCode::Enter => return Action::Accept,
+        _ => {}
+    }
+    Action::Continue
+}
+
+fn search<'a>(
+    storage: &'a Store,
+    session: &mut Session,
+    options: &LiveSearchOptions,
+) -> Vec<SearchResult<'a>> {
+    let search_options = SearchOptions {
+        with_indices: options.highlight,
+        weights: options.weights,
+        min_score: options.min_score,
+        algorithm: options.algorithm,
+        ..SearchOptions::new(options.limit, session.scope)
+    };
+    // A query that extends the last one can only lose matches, so only those are
+    // re-scored; a backspace, edit, or scope change starts over from every key.
+    let previous = session.narrowed.take().filter(|narrowed| {
+        narrowed.scope == session.scope
+            && !narrowed.query.is_empty()
+            && session.input.starts_with(&narrowed.query)
+    });
+    let mut keys = Vec::new();
+    let results = storage.search_narrowing(
+        &session.input,
+        &search_options,
+        previous.as_ref().map(|narrowed| narrowed.keys.as_slice()),
+        &mut keys,
+    );
+    session.narrowed = Some(Narrowed {
+        query: session.input.clone(),
+        scope: session.scope,
+        keys,
+    });
+    results
+}
+
+fn status_line(count: usize, elapsed: Duration) -> String {
+    let noun = if count == 1 { "match" } else { "matches" };
+    format!("{count} {noun} ({:.1} ms)", elapsed.as_secs_f64() * 1000.0)
+}
+
+fn query_prompt(session: &Session) -> String {
+    match session.keybindings {
+        Keybindings::Emacs => format!("Query [{}]: ", session.scope.label()),
+        Keybindings::Vim => format!(
+            "Query [{}] ({}): ",
+            session.scope.label(),
+            session.mode.label()
+        ),
+    }
 }
 
 fn render(
-    stdout: &mut io::Stdout,
+    out: &mut impl Write,
     storage: &Store,
-    input: &str,
-    limit: usize,
-    scope: SearchScope,
-) -> KvResult<usize> {
+    session: &mut Session,
+    options: &LiveSearchOptions,
+) -> KvResult<()> {
     let mut lines = 0usize;
     let max_line_chars = current_line_width_limit();
+    let style = &options.style;
+    queue!(out, Hide)?;
 
-    let query_line = fit_for_terminal(&format!("Query: {input}"), max_line_chars);
-    write_line(stdout, &query_line)?;
+    let prompt = query_prompt(session);
+    let query_line = fit_for_terminal(&format!("{prompt}{}", session.input), max_line_chars);
+    write_line(out, &query_line)?;
     lines += 1;
 
-    if input.is_empty() {
-        let message = fit_for_terminal("Type to search (Esc to exit).", max_line_chars);
-        write_line(stdout, &message)?;
+    let notice = match (&session.pending_delete, &session.message) {
+        (Some(key), _) => Some(format!("Delete '{key}'? [y/N]")),
+        (None, Some(message)) => Some(message.clone()),
+        (None, None) => None,
+    };
+    if let Some(notice) = &notice {
+        write_line(out, &style.key(&fit_for_terminal(notice, max_line_chars)))?;
+        lines += 1;
+    }
+
+    if session.show_help {
+        let help = match session.keybindings {
+            Keybindings::Emacs => EMACS_HELP,
+            Keybindings::Vim => VIM_HELP,
+        };
+        for text in help.iter().take(current_result_rows(RESERVED_ROWS)) {
+            write_line(out, &fit_for_terminal(text, max_line_chars))?;
+            lines += 1;
+        }
+    } else if session.input.is_empty() {
+        session.result_count = 0;
+        let message = fit_for_terminal(
+            "Type to search (Up/Down to select, Enter to pick, Ctrl-Y to copy, F1 for help, Esc to exit).",
+            max_line_chars,
+        );
+        write_line(out, &message)?;
+        lines += 1;
+    } else if session.typing {
+        write_line(
+            out,
+            &style.tags(&fit_for_terminal("typing…", max_line_chars)),
+        )?;
         lines += 1;
     } else {
-        let matches = storage.search(input, limit, scope);
+        let started = Instant::now();
+        let matches = search(storage, session, options);
+        let elapsed = started.elapsed();
+        session.result_count = matches.len();
+        session.clamp_selection();
+        if options.show_status {
+            let status = fit_for_terminal(&status_line(matches.len(), elapsed), max_line_chars);
+            write_line(out, &style.tags(&status))?;
+            lines += 1;
+        }
         if matches.is_empty() {
             let message = fit_for_terminal("No matches found.", max_line_chars);
-            write_line(stdout, &message)?;
+            write_line(out, &message)?;
             lines += 1;
         } else {
-            for entry in matches {
-                let preview = preview_line(entry.key, entry.entry.value(), entry.entry.tags());
-                let bounded_preview = fit_for_terminal(&preview, max_line_chars);
-                write_line(stdout, &bounded_preview)?;
+            let reserved =
+                RESERVED_ROWS + usize::from(options.show_status) + usize::from(notice.is_some());
+            let rows = current_result_rows(reserved);
+            session.scroll_to_selection(rows);
+            let row_chars = max_line_chars.saturating_sub(SELECTED_MARKER.len());
+            let window = matches.iter().enumerate().skip(session.scroll).take(rows);
+            for (idx, entry) in window {
+                let preview = Preview::new(
+                    entry.key,
+                    &entry.entry.display_value(),
+                    entry.entry.tags(),
+                    entry.matches.as_ref(),
+                );
+                let row = preview.render(row_chars, style);
+                let line = if idx == session.selected {
+                    format!("{SELECTED_MARKER}{}", style.selected(&row))
+                } else {
+                    format!("{UNSELECTED_MARKER}{row}")
+                };
+                write_line(out, &line)?;
+                lines += 1;
+            }
+
+            let hidden = matches.len().saturating_sub(rows);
+            if hidden > 0 {
+                let indicator = fit_for_terminal(&format!("({hidden} more)"), max_line_chars);
+                write_line(out, &style.tags(&indicator))?;
                 lines += 1;
             }
         }
     }
 
-    stdout.flush()?;
-    Ok(lines)
+    place_cursor(out, lines, prompt.len() + session.cursor, max_line_chars)?;
+    out.flush()?;
+    Ok(())
 }
 
-fn clear_previous(stdout: &mut io::Stdout, lines: usize) -> KvResult<()> {
-    if lines == 0 {
-        return Ok(());
-    }
+/// Moves the visible cursor from below the last rendered line back into the query.
+fn place_cursor(
+    out: &mut impl Write,
+    lines: usize,
+    column: usize,
+    max_line_chars: usize,
+) -> KvResult<()> {
+    move_up(out, lines)?;
+    let column = column.min(max_line_chars);
+    queue!(out, MoveToColumn(column as u16), Show)?;
+    Ok(())
+}
 
+fn move_up(out: &mut impl Write, lines: usize) -> KvResult<()> {
     let mut remaining = lines;
     while remaining > 0 {
         let step = remaining.min(u16::MAX as usize) as u16;
-        queue!(stdout, MoveUp(step))?;
+        queue!(out, MoveUp(step))?;
         remaining -= step as usize;
     }
-    queue!(stdout, MoveToColumn(0))?;
-    queue!(stdout, terminal::Clear(ClearType::FromCursorDown))?;
-    stdout.flush()?;
     Ok(())
 }
 
-fn write_line(stdout: &mut io::Stdout, text: &str) -> KvResult<()> {
+/// Clears the previous frame; the cursor rests on the query line between frames.
+fn clear_previous(out: &mut impl Write) -> KvResult<()> {
+    queue!(out, MoveToColumn(0))?;
+    queue!(out, terminal::Clear(ClearType::FromCursorDown))?;
+    out.flush()?;
+    Ok(())
+}
+
+fn write_line(out: &mut impl Write, text: &str) -> KvResult<()> {
     queue!(
-        stdout,
+        out,
         terminal::Clear(ClearType::CurrentLine),
         MoveToColumn(0)
     )?;
-    writeln!(stdout, "{text}")?;
+    writeln!(out, "{text}")?;
     Ok(())
 }
 
-fn preview_line(key: &str, value: &str, tags: &[String]) -> String {
-    let key = truncate_for_display(&single_line(key), KEY_PREVIEW_CHARS);
-    let value = if value.trim().is_empty() {
-        "(empty)".to_string()
-    } else {
-        truncate_for_display(&single_line(value), VALUE_PREVIEW_CHARS)
-    };
+/// Individually truncated parts of a result row, kept apart so they can be styled.
+struct Preview {
+    key: String,
+    value: String,
+    tags: Option<String>,
+    key_matches: Vec<usize>,
+    tag_matches: Vec<usize>,
+}
 
-    if tags.is_empty() {
-        format!("{key} = {value}")
-    } else {
+impl Preview {
+    fn new(key: &str, value: &str, tags: &[String], matches: Option<&MatchIndices>) -> Self {
         let joined_tags = tags.join(", ");
-        let tags = truncate_for_display(&single_line(&joined_tags), TAGS_PREVIEW_CHARS);
-        format!("{key} = {value} [tags: {tags}]")
+        let (key_matches, tag_matches) = match matches {
+            Some(matches) => (
+                visible_matches(key, &matches.key, KEY_PREVIEW_CHARS),
+                visible_matches(
+                    &joined_tags,
+                    &matches.joined_tags(tags, ", "),
+                    TAGS_PREVIEW_CHARS,
+                ),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        let key = truncate_for_display(&single_line(key), KEY_PREVIEW_CHARS);
+        let value = if value.trim().is_empty() {
+            "(empty)".to_string()
+        } else {
+            truncate_for_display(&single_line(value), VALUE_PREVIEW_CHARS)
+        };
+        let tags = (!tags.is_empty())
+            .then(|| truncate_for_display(&single_line(&joined_tags), TAGS_PREVIEW_CHARS));
+        Self {
+            key,
+            value,
+            tags,
+            key_matches,
+            tag_matches,
+        }
+    }
+
+    fn plain(&self) -> String {
+        match &self.tags {
+            Some(tags) => format!("{} = {} [tags: {tags}]", self.key, self.value),
+            None => format!("{} = {}", self.key, self.value),
+        }
+    }
+
+    /// Styles the row when it fits; rows that must be cut are emitted plain so
+    /// truncation never splits an escape sequence.
+    fn render(&self, max_chars: usize, style: &OutputStyle) -> String {
+        let plain = self.plain();
+        if !style.is_colored() || plain.chars().count() > max_chars {
+            return fit_for_terminal(&plain, max_chars);
+        }
+
+        let mut line = format!(
+            "{} = {}",
+            style.key(&style.highlight(&self.key, &self.key_matches)),
+            style.value(&self.value)
+        );
+        if let Some(tags) = &self.tags {
+            let tags = style.highlight(tags, &self.tag_matches);
+            line.push(' ');
+            line.push_str(&style.tags(&format!("[tags: {tags}]")));
+        }
+        line
     }
 }
 
+/// Match positions still valid after flattening and truncation; flattened text
+/// shifts characters, so it is not highlighted at all.
+fn visible_matches(text: &str, indices: &[usize], max_chars: usize) -> Vec<usize> {
+    if single_line(text) != text {
+        return Vec::new();
+    }
+    indices
+        .iter()
+        .copied()
+        .filter(|&idx| idx < max_chars)
+        .collect()
+}
+
+/// Result rows that fit below the query without scrolling the terminal.
+fn current_result_rows(reserved: usize) -> usize {
+    terminal::size()
+        .map(|(_, height)| (height as usize).saturating_sub(reserved))
+        .unwrap_or(DEFAULT_RESULT_ROWS)
+        .max(1)
+}
+
 fn current_line_width_limit() -> usize {
     terminal::size()
         .map(|(width, _)| width.saturating_sub(1) as usize)
@@ -202,9 +849,6 @@ struct RawTerminalGuard;
 impl RawTerminalGuard {
     fn new() -> KvResult<Self> {
         terminal::enable_raw_mode()?;
-        let mut out = stdout();
-        queue!(out, Hide)?;
-        out.flush()?;
         Ok(Self)
     }
 }
@@ -212,7 +856,7 @@ impl RawTerminalGuard {
 impl Drop for RawTerminalGuard {
     fn drop(&mut self) {
         let _ = terminal::disable_raw_mode();
-        let mut out = stdout();
+        let mut out = stderr();
         let _ = queue!(out, Show);
         let _ = out.flush();
     }
@@ -220,7 +864,18 @@ impl Drop for RawTerminalGuard {
 
 #[cfg(test)]
 mod tests {
-    use super::{fit_for_terminal, preview_line};
+    use super::{
+        confirm_delete, fit_for_terminal, handle_key_event, load_query, save_query, search,
+        status_line, Action, Keybindings, LiveSearchOptions, Mode, Preview, Session,
+    };
+    use crate::db::Database;
+    use crate::output::OutputStyle;
+    use crate::store::tests::store_with;
+    use crate::store::{Entry, Store};
+    use crate::store::{SearchAlgorithm, SearchOptions, SearchScope, SearchWeights};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::time::Duration;
+    use tempfile::tempdir;
 
     #[test]
     fn fit_for_terminal_single_lines_and_truncates() {
@@ -231,7 +886,269 @@ mod tests {
     #[test]
     fn preview_line_truncates_long_key() {
         let key = "this_is_a_very_long_key_name_that_should_be_trimmed_for_terminal_display";
-        let line = preview_line(key, "value", &[]);
+        let line = Preview::new(key, "value", &[], None).plain();
         assert!(line.contains("... = value"));
     }
+
+    #[test]
+    fn arrow_keys_move_selection_within_result_count() {
+        let mut session = Session {
+            result_count: 3,
+            ..Session::default()
+        };
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        handle_key_event(press(KeyCode::Up), &mut session);
+        assert_eq!(session.selected, 0);
+        for _ in 0..5 {
+            handle_key_event(press(KeyCode::Down), &mut session);
+        }
+        assert_eq!(session.selected, 2);
+
+        session.result_count = 1;
+        session.clamp_selection();
+        assert_eq!(session.selected, 0);
+        assert_eq!(
+            handle_key_event(press(KeyCode::Enter), &mut session),
+            Action::Accept
+        );
+        assert_eq!(
+            handle_key_event(
+                KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL),
+                &mut session
+            ),
+            Action::Copy
+        );
+    }
+
+    #[test]
+    fn viewport_follows_selection() {
+        let mut session = Session {
+            result_count: 10,
+            ..Session::default()
+        };
+
+        session.selected = 6;
+        session.scroll_to_selection(4);
+        assert_eq!(session.scroll, 3);
+
+        session.selected = 1;
+        session.scroll_to_selection(4);
+        assert_eq!(session.scroll, 1);
+
+        session.result_count = 3;
+        session.clamp_selection();
+        session.scroll_to_selection(4);
+        assert_eq!(session.scroll, 0);
+    }
+
+    #[test]
+    fn cursor_editing_respects_char_boundaries() {
+        let mut session = Session::default();
+        for c in "héllo wörld".chars() {
+            session.insert_char(c);
+        }
+        session.move_cursor(-5);
+        session.delete_before_cursor();
+        assert_eq!(session.input, "héllowörld");
+
+        session.insert_char('_');
+        assert_eq!(session.input, "héllo_wörld");
+        session.cursor = 1;
+        session.delete_at_cursor();
+        assert_eq!(session.input, "hllo_wörld");
+
+        session.cursor = session.char_len();
+        session.insert_char(' ');
+        session.insert_char('x');
+        session.delete_word_before_cursor();
+        assert_eq!(session.input, "hllo_wörld ");
+        session.delete_word_before_cursor();
+        assert_eq!(session.input, "");
+
+        session.input = "abc def".to_string();
+        session.cursor = 4;
+        session.delete_to_start();
+        assert_eq!((session.input.as_str(), session.cursor), ("def", 0));
+    }
+
+    #[test]
+    fn tab_cycles_search_scope() {
+        let mut session = Session::default();
+        let tab = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+
+        handle_key_event(tab, &mut session);
+        assert!(matches!(session.scope, SearchScope::KeysOnly));
+        handle_key_event(tab, &mut session);
+        assert!(matches!(session.scope, SearchScope::TagsOnly));
+        handle_key_event(tab, &mut session);
+        assert!(matches!(session.scope, SearchScope::All));
+    }
+
+    #[test]
+    fn remembered_query_survives_an_empty_cancel() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state").join("last_query");
+        assert_eq!(load_query(&path), "");
+
+        save_query(&path, "proj/api", false).unwrap();
+        assert_eq!(load_query(&path), "proj/api");
+        save_query(&path, "", false).unwrap();
+        assert_eq!(load_query(&path), "proj/api");
+        save_query(&path, "", true).unwrap();
+        assert_eq!(load_query(&path), "");
+        save_query(&path, "", true).unwrap();
+    }
+
+    #[test]
+    fn growing_query_rescores_only_previous_matches() {
+        let store = store_with(&[
+            ("apple", "v"),
+            ("grape", "v"),
+            ("pineapple", "v"),
+            ("banana", "v"),
+            ("maple", "v"),
+        ]);
+        let options = LiveSearchOptions {
+            limit: 1,
+            scope: SearchScope::All,
+            style: OutputStyle::plain(),
+            highlight: false,
+            copy_on_enter: false,
+            show_status: false,
+            poll_interval: Duration::ZERO,
+            debounce: Duration::ZERO,
+            keybindings: Keybindings::Emacs,
+            weights: SearchWeights::default(),
+            min_score: 0,
+            algorithm: SearchAlgorithm::default(),
+            query_file: None,
+        };
+        let mut session = Session::default();
+        let keys = |session: &mut Session| -> Vec<String> {
+            search(&store, session, &options)
+                .iter()
+                .map(|result| result.key.to_string())
+                .collect()
+        };
+        let fresh = |query: &str| -> Vec<String> {
+            let search_options = SearchOptions::new(1, SearchScope::All);
+            store
+                .search_with(query, &search_options)
+                .iter()
+                .map(|result| result.key.to_string())
+                .collect()
+        };
+
+        session.input = "ap".into();
+        assert_eq!(keys(&mut session), fresh("ap"));
+        let narrowed = &session.narrowed.as_ref().unwrap().keys;
+        assert_eq!(narrowed, &["apple", "grape", "maple", "pineapple"]);
+
+        // The limit hides most matches, but the narrowed set keeps all of them.
+        session.input = "apl".into();
+        assert_eq!(keys(&mut session), fresh("apl"));
+        assert_eq!(
+            session.narrowed.as_ref().unwrap().keys,
+            ["apple", "maple", "pineapple"]
+        );
+
+        // Backspace past the narrowed query searches every key again.
+        session.input = "a".into();
+        assert_eq!(keys(&mut session), fresh("a"));
+        assert_eq!(session.narrowed.as_ref().unwrap().keys.len(), 5);
+    }
+
+    #[test]
+    fn status_line_reports_count_and_duration() {
+        assert_eq!(
+            status_line(1, Duration::from_micros(1500)),
+            "1 match (1.5 ms)"
+        );
+        assert_eq!(status_line(3, Duration::ZERO), "3 matches (0.0 ms)");
+    }
+
+    #[test]
+    fn confirm_delete_removes_entry_only_on_yes() {
+        let temp = tempdir().unwrap();
+        let mut database = Database::connect(temp.path().join("data.db")).unwrap();
+        let entry = Entry::new("value".to_string(), vec![]);
+        database.upsert_entry("alpha", &entry).unwrap();
+        let mut store = Store::from_entries(database.load_entries().unwrap());
+        let mut session = Session::default();
+        let press = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+
+        confirm_delete(
+            press('n'),
+            "alpha".into(),
+            &mut store,
+            &mut database,
+            &mut session,
+        )
+        .unwrap();
+        assert!(store.get("alpha").is_some());
+
+        confirm_delete(
+            press('y'),
+            "alpha".into(),
+            &mut store,
+            &mut database,
+            &mut session,
+        )
+        .unwrap();
+        assert!(store.get("alpha").is_none());
+        assert!(database.load_entries().unwrap().is_empty());
+        assert_eq!(session.message.as_deref(), Some("Deleted 'alpha'."));
+    }
+
+    #[test]
+    fn vim_bindings_switch_modes_and_delete_with_dd() {
+        let mut session = Session {
+            keybindings: Keybindings::Vim,
+            result_count: 3,
+            ..Session::default()
+        };
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        handle_key_event(press(KeyCode::Char('j')), &mut session);
+        assert_eq!(session.input, "j");
+
+        handle_key_event(press(KeyCode::Esc), &mut session);
+        assert_eq!(session.mode, Mode::Normal);
+        handle_key_event(press(KeyCode::Char('j')), &mut session);
+        assert_eq!((session.input.as_str(), session.selected), ("j", 1));
+        handle_key_event(press(KeyCode::Char('k')), &mut session);
+        assert_eq!(session.selected, 0);
+
+        assert_eq!(
+            handle_key_event(press(KeyCode::Char('d')), &mut session),
+            Action::Continue
+        );
+        assert_eq!(
+            handle_key_event(press(KeyCode::Char('d')), &mut session),
+            Action::Delete
+        );
+
+        handle_key_event(press(KeyCode::Char('A')), &mut session);
+        handle_key_event(press(KeyCode::Char('x')), &mut session);
+        assert_eq!((session.input.as_str(), session.mode), ("jx", Mode::Insert));
+    }
+
+    #[test]
+    fn question_mark_opens_help_only_on_empty_query() {
+        let mut session = Session::default();
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        handle_key_event(press(KeyCode::Char('?')), &mut session);
+        assert!(session.show_help);
+
+        session.show_help = false;
+        session.insert_char('a');
+        handle_key_event(press(KeyCode::Char('?')), &mut session);
+        assert!(!session.show_help);
+        assert_eq!(session.input, "a?");
+
+        handle_key_event(press(KeyCode::F(1)), &mut session);
+        assert!(session.show_help);
+    }
 }
@@ -1,33 +1,53 @@
+pub mod api;
 pub mod cli;
 pub mod db;
 pub mod interactive;
+pub mod output;
 pub mod settings;
 pub mod store;
 
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
+use std::fmt;
 use std::fs;
-use std::io::{BufRead, BufReader, Read, Write};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
 use std::net::{TcpListener, TcpStream};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use directories::BaseDirs;
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
-
-use cli::{Cli, Command};
-use db::Database;
-use interactive::live_search;
-use settings::AppSettings;
-use store::{Entry, RecentConfig, SearchScope, Store};
+use sha2::{Digest, Sha256};
+
+use api::{AddOptions, KvStore};
+use cli::{Cli, Command, ExportFormat, GetField, DEFAULT_INTERACTIVE_LIMIT};
+use db::{is_in_memory, Database};
+use interactive::{copy_to_clipboard, live_search, LiveSearchOptions};
+use output::{page_lines, table_lines, DisplayZone, OutputStyle};
+use settings::{AppSettings, LimitsSettings, ProfileSettings};
+use store::{
+    CreatedAt, Entry, EntryValue, FoldedKeys, KeyPolicy, RecentConfig, SearchOptions, SearchScope,
+    SearchWeights, SortOrder, Store, StoreDiff, TagPolicy,
+};
 use thiserror::Error;
 
 const APP_DIR: &str = ".kvstore";
+const XDG_APP_DIR: &str = "kvstore";
 const NAMESPACES_DIR: &str = "namespaces";
 const DEFAULT_DATA_FILE_NAME: &str = "data.db";
 const DEFAULT_RECENT_LOG_NAME: &str = "recent.log";
+/// Saved next to the recent log for `[interactive] remember_query`.
+const LAST_QUERY_FILE_NAME: &str = "last_query";
 const DEFAULT_NAMESPACE: &str = "default";
+const DATA_FILE_ENV: &str = "KVSTORE_DATA_FILE";
+/// Top-level JSON export key holding [`ExportMeta`]; a stored key with this name
+/// suppresses the metadata instead.
+const EXPORT_META_KEY: &str = "_kvstore_meta";
+const SNAPSHOTS_DIR: &str = "snapshots";
+const SNAPSHOT_INDEX_FILE: &str = "index.json";
 const MAX_HTTP_BODY_BYTES: usize = 128 * 1024;
 const TTL_CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
@@ -49,7 +69,10 @@ pub enum KvError {
     DataFormat(#[from] serde_json::Error),
     #[error("database error: {0}")]
     Db(#[from] rusqlite::Error),
-    #[error("database error while opening '{path}': {source}")]
+    #[error(
+        "database error while opening '{path}': {source} (data file precedence: --data-file, \
+         --profile, KVSTORE_DATA_FILE, [database] data_file, namespace default)"
+    )]
     DbPath {
         path: PathBuf,
         #[source]
@@ -67,262 +90,2005 @@ pub enum KvError {
     PayloadTooLarge(usize),
 }
 
-impl KvError {
-    pub(crate) fn io_path(
-        action: &'static str,
-        path: impl Into<PathBuf>,
-        source: std::io::Error,
-    ) -> Self {
-        Self::IoPath {
-            action,
-            path: path.into(),
-            source,
+impl KvError {
+    pub(crate) fn io_path(
+        action: &'static str,
+        path: impl Into<PathBuf>,
+        source: std::io::Error,
+    ) -> Self {
+        Self::IoPath {
+            action,
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Process exit status for this error: 2 for a missing key, 3 for invalid
+    /// input, 1 for everything else (I/O, database, parse failures).
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            KvError::NotFound(_) => 2,
+            KvError::InvalidInput(_) => 3,
+            _ => 1,
+        }
+    }
+}
+
+/// Formats `key` for a log message, e.g. `info!("stored key={}", log_key(key, redact))`.
+/// With `redact` (`[logging] redact_keys`) the key shows as a short SHA-256 digest.
+pub(crate) fn log_key(key: &str, redact: bool) -> LogKey<'_> {
+    LogKey { key, redact }
+}
+
+pub(crate) struct LogKey<'a> {
+    key: &'a str,
+    redact: bool,
+}
+
+impl fmt::Display for LogKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.key.is_empty() || !self.redact {
+            return f.write_str(self.key);
+        }
+        // Eight hex digits keep lines short while still telling keys apart.
+        let digest = Sha256::digest(self.key.as_bytes());
+        f.write_str("<sha256:")?;
+        for byte in &digest[..4] {
+            write!(f, "{byte:02x}")?;
+        }
+        f.write_str("…>")
+    }
+}
+
+/// Name of the installed executable, as opposed to the `kv` shown in help text.
+const BIN_NAME: &str = "kvstore";
+
+/// Executes the application logic for the provided CLI arguments.
+pub fn run(cli: Cli, settings: &AppSettings) -> KvResult<()> {
+    let mut timings = Timings::new(cli.timing);
+    if let Command::Completions { shell } = cli.command {
+        // Complete the installed binary; zsh and fish then also complete a `kv` alias.
+        let mut command = Cli::command().bin_name(BIN_NAME);
+        // Buffer first: `generate` panics if stdout closes early (e.g. `| head`).
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut command, BIN_NAME, &mut script);
+        io::stdout().write_all(&script)?;
+        return Ok(());
+    }
+
+    let key_policy = settings.keys().policy()?;
+    let mut cli = cli;
+    if let Command::Get {
+        keys,
+        stdin_keys: Some(separator),
+        ..
+    } = &mut cli.command
+    {
+        *keys = split_key_list(&io::read_to_string(io::stdin())?, *separator);
+    }
+    if key_policy.case_insensitive {
+        fold_key_case(&mut cli.command, &key_policy);
+    }
+
+    let namespace = resolve_namespace(cli.namespace.as_deref())?;
+    let style = OutputStyle::for_stdout(cli.color);
+    let profile = cli
+        .profile
+        .as_deref()
+        .map(|name| settings.profile(name))
+        .transpose()?;
+    let env_data_file = env::var_os(DATA_FILE_ENV)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from);
+    let db_path = resolve_data_file(cli.data_file, profile, env_data_file, settings, &namespace);
+    info!("opening store at {}", db_path.display());
+    let redact_log_keys = settings.logging().redact_keys();
+    let connect = |path: &Path| -> KvResult<Database> {
+        Ok(Database::connect(path)?.with_redacted_log_keys(redact_log_keys))
+    };
+    if cli.no_create {
+        // Checked once up front; every later open then finds the file.
+        Database::connect_existing(&db_path)?;
+    }
+    if is_in_memory(&db_path)
+        && matches!(
+            cli.command,
+            Command::Snapshot { .. } | Command::Restore { .. } | Command::Watch { .. }
+        )
+    {
+        return Err(KvError::InvalidInput(format!(
+            "snapshot, restore, and watch need a data file; '{}' is discarded on exit",
+            db_path.display()
+        )));
+    }
+
+    if let Command::Serve { host, port } = &cli.command {
+        let mut database = connect(&db_path)?;
+        let rules = ApiWriteRules {
+            max_value_bytes: settings.limits().max_value_bytes(),
+            key_policy: key_policy.clone(),
+            tag_policy: settings.tags().policy()?,
+        };
+        serve_viewer(&mut database, &rules, &db_path, &namespace, host, *port)?;
+        return Ok(());
+    }
+
+    if let Command::Watch {
+        key,
+        prefix,
+        interval,
+    } = &cli.command
+    {
+        let database = connect(&db_path)?;
+        return watch(
+            &database,
+            key.as_deref(),
+            prefix.as_deref(),
+            *interval,
+            &style,
+        );
+    }
+
+    if let Command::Snapshot { name, list } = &cli.command {
+        let dir = snapshot_dir(&db_path);
+        if *list {
+            let time_format = settings.display().time_format();
+            let zone = settings.display().timezone();
+            let snapshots = list_snapshots(&dir)?;
+            if snapshots.is_empty() {
+                println!("No snapshots in {}.", dir.display());
+            }
+            for snapshot in snapshots {
+                let taken = time_format.format(snapshot.created_at, zone);
+                println!("{}  {}", style.key(&snapshot.name), style.tags(&taken));
+            }
+        } else {
+            let database = connect(&db_path)?;
+            let snapshot = create_snapshot(&database, &dir, name.clone())?;
+            if !cli.quiet {
+                println!(
+                    "Saved snapshot '{}' to {}",
+                    snapshot.name,
+                    snapshot_path(&dir, &snapshot.name).display()
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    if let Command::Restore { name, yes } = &cli.command {
+        let dir = snapshot_dir(&db_path);
+        let snapshot = find_snapshot(&dir, name)?;
+        let question = format!(
+            "This will replace {} with snapshot '{name}' from {}. Continue?",
+            db_path.display(),
+            snapshot.created_at.to_rfc3339()
+        );
+        if !*yes && !confirm(&question)? {
+            println!("Aborted; nothing was restored.");
+            return Ok(());
+        }
+        let mut database = connect(&db_path)?;
+        database.restore_from(&snapshot_path(&dir, name))?;
+        if !cli.quiet {
+            println!("Restored snapshot '{name}'.");
+        }
+        return Ok(());
+    }
+
+    if let Command::Import {
+        path,
+        format,
+        yes,
+        prefix,
+        allow_empty,
+        no_verify,
+        stream: true,
+        ..
+    } = &cli.command
+    {
+        if !no_verify {
+            verify_checksum(path)?;
+        }
+        let mut database = connect(&db_path)?;
+        let (existing, question) = match prefix {
+            Some(prefix) => {
+                let existing = database.keys_with_prefix(prefix)?.len();
+                let question = format!(
+                    "Keys from {} may overwrite {existing} existing entries under '{prefix}'. Continue?",
+                    path.display()
+                );
+                (existing, question)
+            }
+            None => {
+                let existing = database.entry_count()?;
+                let question = format!(
+                    "This will replace {existing} existing entries with the contents of {}. Continue?",
+                    path.display()
+                );
+                (existing, question)
+            }
+        };
+        if existing > 0 && !yes && !confirm(&question)? {
+            println!("Aborted; nothing was imported.");
+            return Ok(());
+        }
+        let key_policy = settings.keys().policy()?;
+        let tag_policy = settings.tags().policy()?;
+        let limits = settings.limits();
+        // A merge keeps stored keys, so imported ones must not collide with them by case.
+        let stored = match prefix {
+            Some(_) if key_policy.case_insensitive => Some(
+                database
+                    .keys_with_prefix("")?
+                    .into_iter()
+                    .collect::<FoldedKeys>(),
+            ),
+            _ => None,
+        };
+        let mut folder = ImportKeyFolder::new(&key_policy, stored.as_ref());
+        let feed = |write: &mut dyn FnMut(String, Entry) -> KvResult<()>| {
+            stream_import(path, *format, &mut |key, item| {
+                let entry = import_entry(&key, item, limits)?;
+                let (key, entry) = match prefix {
+                    Some(prefix) => prefix_entry(prefix, key, entry),
+                    None => (key, entry),
+                };
+                let (key, entry) = folder.fold(key, entry)?;
+                let entry =
+                    check_import_entry(&key, entry, &key_policy, &tag_policy, *allow_empty)?;
+                write(key, entry)
+            })
+        };
+        // A prefixed import lands in its own namespace, so it adds keys instead of
+        // replacing the whole database.
+        let count = if prefix.is_some() {
+            database.upsert_streaming(feed)?
+        } else {
+            database.replace_all_streaming(feed)?
+        };
+        if count == 0 && prefix.is_none() {
+            warn!("import file {} is empty; cleared database", path.display());
+        }
+        if !cli.quiet {
+            println!("Imported {count} entries from {}", path.display());
+        }
+        return Ok(());
+    }
+
+    if let Command::Verify = &cli.command {
+        let database = connect(&db_path)?;
+        return handle_verify(&database);
+    }
+
+    if let Command::Checkpoint { mode } = &cli.command {
+        let database = connect(&db_path)?;
+        let report = database.checkpoint(*mode)?;
+        println!(
+            "Checkpoint ({}): busy={}, log={}, checkpointed={}",
+            mode.pragma_arg(),
+            report.busy,
+            report.log_frames,
+            report.checkpointed_frames
+        );
+        return Ok(());
+    }
+
+    if let Command::CompleteKeys { prefix } = &cli.command {
+        for key in complete_keys(&db_path, prefix)? {
+            println!("{key}");
+        }
+        return Ok(());
+    }
+
+    // Point lookups and edits of named keys skip loading the whole store.
+    let point_keys = match &cli.command {
+        Command::Get { keys, .. }
+        | Command::Remove {
+            keys, glob: None, ..
+        } => Some(keys.clone()),
+        Command::Touch { key } => Some(vec![key.clone()]),
+        _ => None,
+    };
+    let tag_filter = match &cli.command {
+        Command::List { tag: Some(tag), .. }
+        | Command::ByTag { tag }
+        | Command::Search {
+            in_tag: Some(tag), ..
+        }
+        | Command::Stats { tag: Some(tag) } => Some(tag.as_str()),
+        _ => None,
+    };
+    let load_started = Instant::now();
+    let kv = match (&point_keys, tag_filter) {
+        (Some(keys), _) => KvStore::open_keys(&db_path, keys)?,
+        (None, Some(tag)) => KvStore::open_tagged(&db_path, tag)?,
+        (None, None) if matches!(cli.command, Command::Import { dry_run: true, .. }) => {
+            KvStore::open_without_cleanup(&db_path)?
+        }
+        (None, None) => KvStore::open(&db_path)?,
+    };
+    timings.load = Some(load_started.elapsed());
+    let mut kv = kv
+        .with_value_limit(settings.limits().max_value_bytes())
+        .with_tag_policy(settings.tags().policy()?)
+        .with_key_policy(key_policy)
+        .with_verify_on_write(settings.database().verify_on_write())
+        .with_redacted_log_keys(redact_log_keys);
+
+    let history_settings = settings.history();
+    let recent_path = profile
+        .and_then(ProfileSettings::history_file)
+        .or_else(|| history_settings.file())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_recent_log_path(&namespace));
+    let query_path = recent_path.with_file_name(LAST_QUERY_FILE_NAME);
+    let recent_limit = history_settings.limit();
+    if recent_limit > 0 {
+        let config = RecentConfig::new(recent_path, recent_limit);
+        kv.enable_recent_history(config);
+    }
+
+    let quiet = cli.quiet;
+    timings.command_started = Some(Instant::now());
+    match cli.command {
+        Command::Add {
+            key,
+            value,
+            tags,
+            binary,
+            note,
+            trim,
+            allow_empty,
+            reset_created,
+            created_at,
+        } => {
+            let created = match created_at {
+                Some(text) => CreatedAt::At(parse_created_at(&text)?),
+                None if reset_created => CreatedAt::Reset,
+                None => CreatedAt::Keep,
+            };
+            let value = if binary {
+                read_binary_stdin(&value)?
+            } else {
+                EntryValue::Text(value)
+            };
+            let input = ValueInput {
+                trim: trim.unwrap_or(settings.input().trim()),
+                allow_empty,
+            };
+            let options = AddOptions {
+                notes: note,
+                created,
+            };
+            handle_add(&mut kv, &key, value, tags, options, input, quiet)?
+        }
+        Command::AddMany { pairs } => handle_add_many(&mut kv, pairs, quiet)?,
+        Command::Get {
+            keys,
+            ignore_missing,
+            stdin_keys: Some(_),
+            field,
+            print0,
+            ..
+        } => {
+            let output = GetOutput { field, print0 };
+            handle_get_tabbed(&mut kv, &keys, ignore_missing, output, &style)?
+        }
+        Command::Get {
+            keys,
+            binary,
+            ignore_missing,
+            stdin_keys: None,
+            field,
+            print0,
+        } => {
+            let output = GetOutput { field, print0 };
+            handle_get(&mut kv, keys, binary, ignore_missing, output, &style)?
+        }
+        Command::Remove {
+            glob: Some(pattern),
+            yes,
+            ..
+        } => {
+            let keys = kv.store().keys_matching_glob(&parse_glob(&pattern)?);
+            if keys.is_empty() {
+                println!("No keys match '{pattern}'.");
+            } else if !yes {
+                for key in &keys {
+                    println!("{key}");
+                }
+                return Err(KvError::InvalidInput(format!(
+                    "{} keys match '{pattern}'; pass --yes to remove them",
+                    keys.len()
+                )));
+            } else {
+                let removed = kv.remove_many(&keys, false)?;
+                if !quiet {
+                    println!("Removed {} keys matching '{pattern}'.", removed.len());
+                }
+            }
+        }
+        Command::Remove {
+            keys,
+            ignore_missing,
+            print_value,
+            ..
+        } => {
+            let removed = kv.remove_many(&keys, ignore_missing)?;
+            if print_value {
+                for value in removed_values(&keys, removed) {
+                    println!("{value}");
+                }
+            } else {
+                match (keys.len(), removed.as_slice()) {
+                    _ if quiet => {}
+                    (1, [(key, existing)]) => println!(
+                        "Removed '{}'. Stored value was {}.",
+                        key,
+                        describe_value(existing)
+                    ),
+                    _ => println!("Removed {} of {} keys.", removed.len(), keys.len()),
+                }
+            }
+        }
+        Command::List {
+            prefix,
+            tag,
+            glob,
+            order,
+            timestamps,
+            values_only,
+            table,
+            template,
+            pager,
+        } => {
+            let mut entries = kv.list(prefix.as_deref().unwrap_or(""));
+            if let Some(pattern) = &glob {
+                let pattern = parse_glob(pattern)?;
+                entries.retain(|(key, _)| pattern.matches(key));
+            }
+            order.sort(&mut entries);
+            let time_format = settings.display().time_format();
+            let zone = settings.display().timezone();
+            let mut lines = Vec::with_capacity(entries.len());
+            if values_only {
+                for (key, entry) in entries {
+                    let entry = kv.resolve(key).unwrap_or(entry);
+                    lines.push(entry.display_value().into_owned());
+                }
+            } else if entries.is_empty() {
+                match (&prefix, &tag, &glob) {
+                    (_, _, Some(pattern)) => println!("No keys match '{pattern}'."),
+                    (_, Some(tag), None) => println!("No entries tagged '{tag}'."),
+                    (Some(prefix), None, None) => println!("No keys start with '{prefix}'."),
+                    (None, None, None) => println!("No entries stored."),
+                }
+            } else if let Some(width) = table {
+                let rows: Vec<Vec<String>> = entries
+                    .into_iter()
+                    .map(|(key, entry)| {
+                        let value = kv.resolve(key).unwrap_or(entry).display_value();
+                        vec![key.clone(), value.into_owned(), entry.tags().join(", ")]
+                    })
+                    .collect();
+                lines = table_lines(&["KEY", "VALUE", "TAGS"], &rows, &[0, width]);
+            } else if let Some(template) = &template {
+                for (key, entry) in entries {
+                    let entry = kv.resolve(key).unwrap_or(entry);
+                    lines.push(render_template(template, key, entry, None));
+                }
+            } else {
+                for (key, entry) in entries {
+                    let summary = entry.summary(key, &style);
+                    if timestamps {
+                        let times = format!(
+                            "(created {}, updated {})",
+                            time_format.format(entry.created_at(), zone),
+                            time_format.format(entry.updated_at(), zone)
+                        );
+                        lines.push(format!("{summary} {}", style.tags(&times)));
+                    } else {
+                        lines.push(summary);
+                    }
+                }
+            }
+            page_lines(&lines, pager)?;
+        }
+        Command::Search {
+            pattern,
+            prefix,
+            in_tag,
+            limit,
+            offset,
+            min_score,
+            show_scores,
+            tags_only,
+            keys_only,
+            highlight,
+            explain,
+            key_weight,
+            tag_weight,
+            template,
+            pager,
+        } => {
+            let defaults = settings.search().weights();
+            let weights = SearchWeights {
+                key: key_weight.unwrap_or(defaults.key),
+                tag: tag_weight.unwrap_or(defaults.tag),
+            };
+            let scope = resolve_scope(tags_only, keys_only, settings.search().default_scope())?;
+            let limit = resolve_search_limit(limit, settings);
+            let options = SearchOptions {
+                prefix: prefix.as_deref().unwrap_or(""),
+                tag: in_tag.as_deref(),
+                with_indices: resolve_highlight(highlight, &style, io::stdout().is_terminal()),
+                weights,
+                offset,
+                min_score: min_score.unwrap_or(settings.search().min_score()),
+                algorithm: settings.search().algorithm(),
+                ..SearchOptions::new(limit, scope)
+            };
+            let matches = kv.search(&pattern, &options);
+            let mut lines = Vec::with_capacity(matches.len());
+            if matches.is_empty() {
+                println!("No matches found.");
+            } else {
+                for item in matches {
+                    if let Some(template) = &template {
+                        lines.push(render_template(
+                            template,
+                            item.key,
+                            item.entry,
+                            Some(item.score),
+                        ));
+                        continue;
+                    }
+                    let mut summary =
+                        item.entry
+                            .summary_with_matches(item.key, &style, item.matches.as_ref());
+                    if show_scores {
+                        summary = format!("{} {summary}", style.tags(&format!("[{}]", item.score)));
+                    }
+                    if explain {
+                        let reasons: Vec<_> = item
+                            .fields
+                            .iter()
+                            .map(|(field, score)| format!("{field}:{score}"))
+                            .collect();
+                        let reason = format!("({})", reasons.join(", "));
+                        lines.push(format!("{summary}  {}", style.tags(&reason)));
+                    } else {
+                        lines.push(summary);
+                    }
+                }
+            }
+            page_lines(&lines, pager)?;
+        }
+        Command::Export {
+            path,
+            order,
+            local_time,
+            compact,
+            format,
+            keys,
+            ignore_missing,
+            with_checksum,
+        } => {
+            let missing: Vec<&str> = keys
+                .iter()
+                .filter(|key| kv.get(key).is_err())
+                .map(String::as_str)
+                .collect();
+            if !missing.is_empty() {
+                if !ignore_missing {
+                    return Err(KvError::NotFound(missing.join(", ")));
+                }
+                eprintln!("Skipped missing keys: {}", missing.join(", "));
+                if missing.len() == keys.len() {
+                    return Err(KvError::NotFound(missing.join(", ")));
+                }
+            }
+            // Without a configured zone, --local-time means the system's local zone.
+            let zone = if local_time {
+                settings
+                    .display()
+                    .configured_timezone()
+                    .unwrap_or(DisplayZone::Local)
+            } else {
+                DisplayZone::Utc
+            };
+            let count = export_to_path(kv.store(), &keys, &path, order, zone, format, compact)?;
+            write_checksum(&path, with_checksum)?;
+            if !quiet {
+                println!("Exported {count} entries to {}", path.display());
+            }
+        }
+        Command::Import {
+            path,
+            format,
+            yes,
+            dry_run,
+            prefix,
+            allow_empty,
+            no_verify,
+            stream: _,
+        } => {
+            if !no_verify {
+                verify_checksum(&path)?;
+            }
+            let mut entries = read_import(&path, format, settings.limits())?;
+            if let Some(prefix) = &prefix {
+                entries = prefix_keys(entries, prefix);
+            }
+            let key_policy = settings.keys().policy()?;
+            let tag_policy = settings.tags().policy()?;
+            let merge = prefix.is_some();
+            let mut folder =
+                ImportKeyFolder::new(&key_policy, merge.then(|| kv.store().folded_keys()));
+            let entries = entries
+                .into_iter()
+                .map(|(key, entry)| {
+                    let (key, entry) = folder.fold(key, entry)?;
+                    let entry =
+                        check_import_entry(&key, entry, &key_policy, &tag_policy, allow_empty)?;
+                    Ok((key, entry))
+                })
+                .collect::<KvResult<Vec<_>>>()?;
+            check_import_aliases(&entries, merge.then(|| kv.store()))?;
+            let question = import_question(kv.store(), &entries, merge).filter(|_| !yes);
+            if dry_run {
+                let diff = ImportDiff::between(kv.store(), &entries, merge);
+                println!("{}", diff.summary());
+            } else if !question.map_or(Ok(true), |question| confirm(&question))? {
+                println!("Aborted; nothing was imported.");
+            } else {
+                if entries.is_empty() && !merge {
+                    warn!("import file {} is empty; clearing database", path.display());
+                }
+                apply_import(&mut kv, entries, merge)?;
+                if !quiet {
+                    println!("Imported entries from {}", path.display());
+                }
+            }
+        }
+        Command::Migrate { from } => {
+            let (database, store) = kv.parts_mut();
+            let tag_policy = settings.tags().policy()?;
+            let report = handle_migrate(database, store, &from, settings.limits(), &tag_policy)?;
+            for key in &report.skipped {
+                eprintln!("Skipped '{key}': it already exists in the database.");
+            }
+            if !quiet {
+                println!(
+                    "Migrated {} entries from {}",
+                    report.migrated,
+                    from.display()
+                );
+            }
+        }
+        Command::Html { path } => {
+            export_html_view(kv.store(), &path)?;
+            if !quiet {
+                println!(
+                    "Generated HTML view at {} (namespace: {}, data source: {})",
+                    path.display(),
+                    namespace,
+                    db_path.display()
+                );
+            }
+        }
+        Command::Serve { .. } => unreachable!("serve is handled before cache loading"),
+        Command::Verify => unreachable!("verify is handled before cache loading"),
+        Command::Snapshot { .. } | Command::Restore { .. } => {
+            unreachable!("snapshots are handled before cache loading")
+        }
+        Command::Watch { .. } => unreachable!("watch is handled before cache loading"),
+        Command::Checkpoint { .. } => {
+            unreachable!("checkpoint is handled before cache loading")
+        }
+        Command::Completions { .. } => {
+            unreachable!("completions are handled before opening the store")
+        }
+        Command::CompleteKeys { .. } => {
+            unreachable!("complete-keys is handled before cache loading")
+        }
+        Command::PutFile {
+            key,
+            path,
+            tags,
+            any_file,
+            binary,
+            trim,
+        } => {
+            let contents = read_source_file(&path, any_file || binary, binary)?;
+            // An empty file is stored as-is; it was clearly chosen on purpose.
+            let input = ValueInput {
+                trim: trim.unwrap_or(settings.input().trim()),
+                allow_empty: true,
+            };
+            let options = AddOptions::default();
+            handle_add(&mut kv, &key, contents, tags, options, input, quiet)?;
+        }
+        Command::GetFile {
+            key,
+            path,
+            any_file,
+        } => {
+            let key_for_message = key.clone();
+            handle_get_file(&mut kv, key, &path, any_file)?;
+            if !quiet {
+                println!("Wrote '{}' to {}", key_for_message, path.display());
+            }
+        }
+        Command::Interactive {
+            limit,
+            min_score,
+            tags_only,
+            keys_only,
+            highlight,
+            print_value,
+            copy,
+            no_status,
+        } => {
+            let style = OutputStyle::for_stderr(cli.color);
+            let options = LiveSearchOptions {
+                limit: resolve_search_limit(limit, settings),
+                scope: resolve_scope(tags_only, keys_only, settings.search().default_scope())?,
+                style,
+                highlight: resolve_highlight(highlight, &style, io::stderr().is_terminal()),
+                copy_on_enter: copy,
+                show_status: !no_status,
+                poll_interval: settings.interactive().poll_interval(),
+                debounce: settings.interactive().debounce(),
+                keybindings: settings.interactive().keybindings(),
+                weights: settings.search().weights(),
+                min_score: min_score.unwrap_or(settings.search().min_score()),
+                algorithm: settings.search().algorithm(),
+                query_file: settings
+                    .interactive()
+                    .remember_query()
+                    .then_some(query_path),
+            };
+            let (database, store) = kv.parts_mut();
+            if let Some(selection) = live_search(store, database, &options)? {
+                let key = selection.key;
+                if selection.copy {
+                    let value = text_value(kv.store(), &key)?;
+                    match copy_to_clipboard(value) {
+                        Ok(()) => eprintln!("Copied value of '{key}' to the clipboard."),
+                        Err(err) => {
+                            eprintln!("Clipboard unavailable ({err}); printing value instead.");
+                            println!("{value}");
+                        }
+                    }
+                } else if print_value {
+                    println!("{}", text_value(kv.store(), &key)?);
+                } else {
+                    println!("{key}");
+                }
+                kv.record_access(&key);
+            }
+        }
+        Command::Touch { key } => {
+            let now = kv.touch(&key)?;
+            let display = settings.display();
+            let updated_at = display.time_format().format(now, display.timezone());
+            if !quiet {
+                println!("Touched '{key}' (updated_at {updated_at}).");
+            }
+        }
+        Command::Clear { yes, dry_run } => {
+            let count = kv.len();
+            if dry_run {
+                let diff = ImportDiff {
+                    removed: count,
+                    ..ImportDiff::default()
+                };
+                println!("{}", diff.summary());
+            } else if count == 0 {
+                println!("No entries stored.");
+            } else if yes || confirm(&format!("Delete all {count} entries in '{namespace}'?"))? {
+                let removed = kv.clear()?;
+                if !quiet {
+                    println!("Removed {removed} entries.");
+                }
+            } else {
+                println!("Aborted; nothing was removed.");
+            }
+        }
+        Command::Random { count, tag } => {
+            let picked = kv.random(count, tag.as_deref());
+            if picked.is_empty() {
+                // On stderr, so scripts sampling an empty store read no output.
+                match &tag {
+                    Some(tag) => eprintln!("No entries tagged '{tag}'."),
+                    None => eprintln!("No entries stored."),
+                }
+            } else {
+                for (key, entry) in picked {
+                    println!("{}", entry.summary(key, &style));
+                }
+            }
+        }
+        Command::Grep {
+            pattern,
+            ignore_case,
+            invert,
+        } => {
+            let hits: Vec<_> = if invert {
+                kv.store()
+                    .ordered()
+                    .into_iter()
+                    .filter(|(_, entry)| !entry.contains_text(&pattern, ignore_case))
+                    .collect()
+            } else {
+                kv.store().grep(&pattern, ignore_case)
+            };
+            if hits.is_empty() {
+                println!("No matches found.");
+            }
+            for (key, entry) in hits {
+                let lines = entry.matching_lines(&pattern, ignore_case);
+                if invert || lines.is_empty() {
+                    println!("{}", entry.summary(key, &style));
+                } else if entry.value().lines().nth(1).is_none() {
+                    println!("{}: {}", style.key(key), style.value(entry.value()));
+                } else {
+                    for (number, line) in lines {
+                        println!("{}:{number}: {}", style.key(key), style.value(line));
+                    }
+                }
+            }
+        }
+        Command::Dump => {
+            let mut stdout = io::stdout();
+            stdout.write_all(kv.database().dump_sql()?.as_bytes())?;
+            stdout.flush()?;
+        }
+        Command::Diff {
+            other,
+            values,
+            json,
+        } => {
+            let theirs = load_diff_source(&other, settings.limits())?;
+            let diff = kv.store().diff(&theirs);
+            if json {
+                let report = diff_json(&diff, kv.store(), &theirs, values);
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else if diff.is_empty() {
+                println!("No differences.");
+            } else {
+                print_diff(&diff, kv.store(), &theirs, values);
+            }
+        }
+        Command::Alias { alias, target } => {
+            let outcome = kv.alias(&alias, &target)?;
+            if !quiet {
+                match outcome.previous.as_ref().and_then(Entry::alias_of) {
+                    Some(old) => {
+                        println!("Alias '{alias}' now points at '{target}' (was '{old}').")
+                    }
+                    None => println!("Alias '{alias}' points at '{target}'."),
+                }
+            }
+        }
+        Command::Tags { below } => {
+            let counts: Vec<_> = kv
+                .store()
+                .tag_counts()
+                .into_iter()
+                .filter(|(_, count)| below.is_none_or(|limit| *count < limit))
+                .collect();
+            if counts.is_empty() {
+                match below {
+                    Some(limit) => println!("No tags are used by fewer than {limit} entries."),
+                    None => println!("No tags in use."),
+                }
+            }
+            for (tag, count) in counts {
+                println!("{count:>5}  {tag}");
+            }
+        }
+        Command::Stats { tag } => {
+            let stats = kv.store().stats(tag.as_deref());
+            if stats.entries == 0 {
+                match &tag {
+                    Some(tag) => println!("No entries tagged '{tag}'."),
+                    None => println!("No entries stored."),
+                }
+                return Ok(());
+            }
+            if let Some(tag) = &tag {
+                println!("Stats for tag '{tag}':");
+            }
+            let time_format = settings.display().time_format();
+            let zone = settings.display().timezone();
+            let dated = |(key, entry): (&str, &Entry)| {
+                let created = time_format.format(entry.created_at(), zone);
+                format!(
+                    "{} {}",
+                    style.key(key),
+                    style.tags(&format!("(created {created})"))
+                )
+            };
+            println!("entries:      {}", stats.entries);
+            println!(
+                "value bytes:  {} total, {} average",
+                stats.value_bytes,
+                stats.value_bytes / stats.entries
+            );
+            if let Some((key, size)) = stats.largest {
+                println!("largest:      {} ({size} bytes)", style.key(key));
+            }
+            if let Some(oldest) = stats.oldest {
+                println!("oldest:       {}", dated(oldest));
+            }
+            if let Some(newest) = stats.newest {
+                println!("newest:       {}", dated(newest));
+            }
+        }
+        Command::ByTag { tag } => {
+            let entries = kv.list("");
+            if entries.is_empty() {
+                println!("No entries tagged '{tag}'.");
+            }
+            for (key, entry) in entries {
+                println!("{}", entry.summary(key, &style));
+            }
+        }
+        Command::Recent { limit } => {
+            let recent = kv.recent(limit);
+            if recent.is_empty() {
+                println!("No recent keys recorded.");
+            } else {
+                for (idx, key) in recent.iter().enumerate() {
+                    println!("{:>2}. {}", idx + 1, key);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `remove --print-value` output: one line per requested key, in order, so values
+/// stay aligned with the keys. Keys skipped by `--ignore-missing` (or repeated) get
+/// an empty line.
+fn removed_values(keys: &[String], removed: Vec<(String, Entry)>) -> Vec<String> {
+    let mut removed: BTreeMap<String, Entry> = removed.into_iter().collect();
+    keys.iter()
+        .map(|key| {
+            removed
+                .remove(key)
+                .map(|entry| entry.display_value().into_owned())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// What `get` prints for each entry.
+#[derive(Debug, Clone, Copy, Default)]
+struct GetOutput {
+    /// The `--field` choice; `None` prints the value.
+    field: Option<GetField>,
+    /// End records with NUL instead of a newline (`--print0`).
+    print0: bool,
+}
+
+impl GetOutput {
+    fn end(self) -> char {
+        if self.print0 {
+            '\0'
+        } else {
+            '\n'
+        }
+    }
+
+    fn text(self, entry: &Entry) -> Cow<'_, str> {
+        entry_field(entry, self.field, self.print0)
+    }
+}
+
+fn handle_get(
+    kv: &mut KvStore,
+    keys: Vec<String>,
+    binary: bool,
+    ignore_missing: bool,
+    output: GetOutput,
+    style: &OutputStyle,
+) -> KvResult<()> {
+    let end = output.end();
+    let missing: Vec<&str> = keys
+        .iter()
+        .filter(|key| kv.resolve(key).is_err())
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() && !ignore_missing {
+        return Err(KvError::NotFound(missing.join(", ")));
+    }
+
+    if let [key] = keys.as_slice() {
+        if missing.is_empty() {
+            let entry = kv.resolve(key)?;
+            match output.field {
+                Some(_) => print!("{}{end}", style.value(&output.text(entry))),
+                None => print_single_value(entry, key, binary, style)?,
+            }
+            kv.record_access(key);
+        }
+        return Ok(());
+    }
+
+    if binary {
+        return Err(KvError::InvalidInput(
+            "`get --binary` accepts exactly one key".to_string(),
+        ));
+    }
+    for key in &keys {
+        if let Ok(entry) = kv.resolve(key) {
+            print!(
+                "{}: {}{end}",
+                style.key(key),
+                style.value(&output.text(entry))
+            );
+            kv.record_access(key);
+        }
+    }
+    Ok(())
+}
+
+/// `get --stdin-keys`: one `key<TAB>value` line per found key, in input order.
+fn handle_get_tabbed(
+    kv: &mut KvStore,
+    keys: &[String],
+    ignore_missing: bool,
+    output: GetOutput,
+    style: &OutputStyle,
+) -> KvResult<()> {
+    let end = output.end();
+    let missing: Vec<&str> = keys
+        .iter()
+        .filter(|key| kv.resolve(key).is_err())
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() && !ignore_missing {
+        return Err(KvError::NotFound(missing.join(", ")));
+    }
+
+    for key in keys {
+        if let Ok(entry) = kv.resolve(key) {
+            print!(
+                "{}\t{}{end}",
+                style.key(key),
+                style.value(&output.text(entry))
+            );
+            kv.record_access(key);
+        }
+    }
+    Ok(())
+}
+
+/// The text `get` prints for `entry`: its display value, or the `--field` choice.
+/// Tags are joined with commas, or NULs under `--print0`.
+fn entry_field(entry: &Entry, field: Option<GetField>, print0: bool) -> Cow<'_, str> {
+    match field {
+        None | Some(GetField::Value) => entry.display_value(),
+        Some(GetField::Tags) => Cow::Owned(entry.tags().join(if print0 { "\0" } else { "," })),
+        Some(GetField::Created) => Cow::Owned(entry.created_at().to_rfc3339()),
+        Some(GetField::Updated) => Cow::Owned(entry.updated_at().to_rfc3339()),
+    }
+}
+
+/// Fills `{key}`, `{value}`, `{tags}`, `{created}`, `{updated}`, and (for search)
+/// `{score}` in a `--template`, with the same text `get --field` prints. `\t`, `\n`,
+/// and `\\` become tab, newline, and backslash; anything else, including unknown
+/// placeholders, is copied as written.
+fn render_template(template: &str, key: &str, entry: &Entry, score: Option<i64>) -> String {
+    let mut out = String::with_capacity(template.len() + key.len());
+    let mut rest = template;
+    while let Some(at) = rest.find(['{', '\\']) {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            let (text, len) = match escaped.chars().next() {
+                Some('t') => ("\t", 2),
+                Some('n') => ("\n", 2),
+                Some('\\') => ("\\", 2),
+                _ => ("\\", 1),
+            };
+            out.push_str(text);
+            rest = &rest[len..];
+            continue;
+        }
+        let name = rest[1..].find('}').map(|end| &rest[1..1 + end]);
+        let field = |field| Some(entry_field(entry, Some(field), false));
+        let text = match name {
+            Some("key") => Some(Cow::Borrowed(key)),
+            Some("value") => field(GetField::Value),
+            Some("tags") => field(GetField::Tags),
+            Some("created") => field(GetField::Created),
+            Some("updated") => field(GetField::Updated),
+            Some("score") => score.map(|score| Cow::Owned(score.to_string())),
+            _ => None,
+        };
+        match (text, name) {
+            (Some(text), Some(name)) => {
+                out.push_str(&text);
+                rest = &rest[name.len() + 2..];
+            }
+            _ => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Keys from `get --stdin-keys` input; blank items and a trailing `\r` are dropped.
+fn split_key_list(input: &str, separator: u8) -> Vec<String> {
+    input
+        .split(char::from(separator))
+        .map(|key| key.strip_suffix('\r').unwrap_or(key))
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn print_single_value(entry: &Entry, key: &str, binary: bool, style: &OutputStyle) -> KvResult<()> {
+    if binary {
+        let mut stdout = io::stdout();
+        stdout.write_all(entry.bytes())?;
+        stdout.flush()?;
+    } else if entry.is_binary() {
+        return Err(KvError::InvalidInput(format!(
+            "'{key}' holds a binary value ({} bytes); use `get --binary`",
+            entry.bytes().len()
+        )));
+    } else {
+        println!("{}", style.value(entry.value()));
+        if !entry.tags().is_empty() {
+            println!(
+                "{}",
+                style.tags(&format!("tags: {}", entry.tags().join(", ")))
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Lowercases every key a command names, so lookups and writes agree under
+/// `[keys] case_insensitive`. Prefixes and patterns are left alone.
+fn fold_key_case(command: &mut Command, policy: &KeyPolicy) {
+    match command {
+        Command::Add { key, .. }
+        | Command::PutFile { key, .. }
+        | Command::GetFile { key, .. }
+        | Command::Touch { key }
+        | Command::Watch { key: Some(key), .. } => policy.fold(key),
+        Command::Get { keys, .. } | Command::Remove { keys, .. } | Command::Export { keys, .. } => {
+            keys.iter_mut().for_each(|key| policy.fold(key))
+        }
+        Command::AddMany { pairs } => pairs.iter_mut().step_by(2).for_each(|key| policy.fold(key)),
+        Command::Alias { alias, target } => {
+            policy.fold(alias);
+            policy.fold(target);
+        }
+        _ => {}
+    }
+}
+
+/// How `handle_add` treats the value it was given.
+#[derive(Debug, Clone, Copy, Default)]
+struct ValueInput {
+    /// Strip surrounding whitespace from text values first.
+    trim: bool,
+    /// Store an empty value instead of rejecting it.
+    allow_empty: bool,
+}
+
+fn handle_add(
+    kv: &mut KvStore,
+    key: &str,
+    value: EntryValue,
+    tags: Vec<String>,
+    options: AddOptions,
+    input: ValueInput,
+    quiet: bool,
+) -> KvResult<()> {
+    let value = if input.trim { trim_value(value) } else { value };
+    // `kv foo @prod` tags a key without a value, so only a bare new key is refused.
+    if value.is_empty() && !input.allow_empty && tags.is_empty() && kv.store().get(key).is_none() {
+        kv.check_key(key)?;
+        return Err(KvError::InvalidInput(format!(
+            "value for '{key}' is empty; pass --allow-empty to store it"
+        )));
+    }
+    let outcome = kv.add_with(key, value, tags, options)?;
+    if quiet {
+        return Ok(());
+    }
+    match outcome.previous {
+        Some(old) => {
+            let tags = tag_changes(old.tags(), outcome.entry.tags())
+                .map(|changes| format!(" {changes}"))
+                .unwrap_or_default();
+            println!(
+                "Updated '{}'. Previous: {}; Now: {}{}",
+                key,
+                describe_value(&old),
+                describe_value(&outcome.entry),
+                tags
+            )
+        }
+        None => println!("Added '{}'. {}", key, describe_value(&outcome.entry)),
+    }
+
+    Ok(())
+}
+
+/// Parses `add --created-at`; a time in the future would make the entry look
+/// created after its own update.
+fn parse_created_at(text: &str) -> KvResult<DateTime<Utc>> {
+    let at = DateTime::parse_from_rfc3339(text.trim())
+        .map_err(|error| {
+            KvError::InvalidInput(format!("--created-at '{text}' is not RFC 3339: {error}"))
+        })?
+        .with_timezone(&Utc);
+    if at > Utc::now() {
+        return Err(KvError::InvalidInput(format!(
+            "--created-at '{text}' is in the future"
+        )));
+    }
+    Ok(at)
+}
+
+fn handle_add_many(kv: &mut KvStore, pairs: Vec<String>, quiet: bool) -> KvResult<()> {
+    if !pairs.len().is_multiple_of(2) {
+        return Err(KvError::InvalidInput(format!(
+            "add-many expects key/value pairs, got {} arguments",
+            pairs.len()
+        )));
+    }
+    let mut args = pairs.into_iter();
+    let mut batch = Vec::new();
+    while let (Some(key), Some(value)) = (args.next(), args.next()) {
+        batch.push((key, EntryValue::Text(value)));
+    }
+
+    let outcomes = kv.add_many(batch)?;
+    if !quiet {
+        let updated = outcomes
+            .iter()
+            .filter(|outcome| outcome.previous.is_some())
+            .count();
+        println!(
+            "Stored {} keys ({} added, {} updated).",
+            outcomes.len(),
+            outcomes.len() - updated,
+            updated
+        );
+    }
+    Ok(())
+}
+
+/// Wall-clock phases of one `run`, printed to stderr by `--timing` when dropped so
+/// commands that fail or return early still report what was measured.
+struct Timings {
+    enabled: bool,
+    started: Instant,
+    load: Option<Duration>,
+    command_started: Option<Instant>,
+}
+
+impl Timings {
+    fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            started: Instant::now(),
+            load: None,
+            command_started: None,
+        }
+    }
+
+    fn report(&self) -> String {
+        let mut phases = Vec::new();
+        if let Some(load) = self.load {
+            phases.push(format!("load {}", format_millis(load)));
+        }
+        if let Some(started) = self.command_started {
+            phases.push(format!("command {}", format_millis(started.elapsed())));
+        }
+        phases.push(format!("total {}", format_millis(self.started.elapsed())));
+        format!("timing: {}", phases.join(", "))
+    }
+}
+
+impl Drop for Timings {
+    fn drop(&mut self) {
+        if self.enabled {
+            eprintln!("{}", self.report());
+        }
+    }
+}
+
+fn format_millis(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// `tags +added -removed` between two tag lists, or `None` when they hold the same tags.
+fn tag_changes(old: &[String], new: &[String]) -> Option<String> {
+    let added = new.iter().filter(|tag| !old.contains(tag));
+    let removed = old.iter().filter(|tag| !new.contains(tag));
+    let changes: Vec<_> = added
+        .map(|tag| format!("+{tag}"))
+        .chain(removed.map(|tag| format!("-{tag}")))
+        .collect();
+    (!changes.is_empty()).then(|| format!("tags {}", changes.join(" ")))
+}
+
+/// Strips surrounding whitespace from text values; binary values are stored as read.
+fn trim_value(value: EntryValue) -> EntryValue {
+    match value {
+        EntryValue::Text(text) if text.trim().len() != text.len() => {
+            EntryValue::Text(text.trim().to_string())
+        }
+        value => value,
+    }
+}
+
+/// Asks a y/N question on stderr; refuses to guess when stdin is not a terminal.
+fn confirm(question: &str) -> KvResult<bool> {
+    if !io::stdin().is_terminal() {
+        return Err(KvError::InvalidInput(
+            "confirmation required but stdin is not a terminal; pass --yes to proceed".to_string(),
+        ));
+    }
+    eprint!("{question} [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Runs before the cache is loaded, since loading stops at the first corrupt row.
+fn handle_verify(database: &Database) -> KvResult<()> {
+    let report = database.verify()?;
+    for issue in &report.issues {
+        println!("{}: {}", issue.key, issue.problem);
+    }
+    println!(
+        "Checked {} entries; found {} problem(s).",
+        report.checked,
+        report.issues.len()
+    );
+    if report.issues.is_empty() {
+        Ok(())
+    } else {
+        Err(KvError::InvalidInput(format!(
+            "verification failed with {} problem(s)",
+            report.issues.len()
+        )))
+    }
+}
+
+/// Key counts an import would add, remove, or change relative to the current store.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ImportDiff {
+    added: usize,
+    removed: usize,
+    changed: usize,
+}
+
+impl ImportDiff {
+    /// Compares values, tags, notes, and expiry; timestamps alone do not count as a change.
+    /// A `merge` import never removes keys.
+    fn between(store: &Store, entries: &[(String, Entry)], merge: bool) -> Self {
+        let mut diff = Self::default();
+        let mut incoming = BTreeMap::new();
+        for (key, entry) in entries {
+            incoming.insert(key.as_str(), entry);
+        }
+        for (key, entry) in &incoming {
+            match store.get(key) {
+                None => diff.added += 1,
+                Some(current) if !current.same_content(entry) => diff.changed += 1,
+                Some(_) => {}
+            }
+        }
+        if !merge {
+            diff.removed = store
+                .with_prefix("")
+                .filter(|(key, _)| !incoming.contains_key(key.as_str()))
+                .count();
+        }
+        diff
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "+{} -{} ~{} (added/removed/changed)",
+            self.added, self.removed, self.changed
+        )
+    }
+}
+
+/// The confirmation an import needs before it touches stored entries, or `None` when
+/// nothing already stored would be replaced or overwritten.
+fn import_question(store: &Store, entries: &[(String, Entry)], merge: bool) -> Option<String> {
+    if merge {
+        let existing = entries
+            .iter()
+            .filter(|(key, _)| store.get(key).is_some())
+            .count();
+        (existing > 0).then(|| {
+            format!("This will overwrite {existing} existing entries with imported ones. Continue?")
+        })
+    } else {
+        let existing = store.len();
+        (existing > 0).then(|| {
+            format!(
+                "This will replace {existing} existing entries with {} imported entries. Continue?",
+                entries.len()
+            )
+        })
+    }
+}
+
+/// Writes imported entries: a `merge` import upserts them and leaves other keys alone,
+/// otherwise they replace the whole database.
+fn apply_import(kv: &mut KvStore, entries: Vec<(String, Entry)>, merge: bool) -> KvResult<()> {
+    let (database, store) = kv.parts_mut();
+    if merge {
+        database.upsert_entries(&entries)?;
+        for (key, entry) in entries {
+            store.insert(key, entry);
+        }
+    } else {
+        database.replace_all(&entries)?;
+        store.reset(entries);
+    }
+    Ok(())
+}
+
+/// Prepends `prefix` to every key, and to alias targets so aliases keep pointing at
+/// the imported entries.
+fn prefix_keys(entries: Vec<(String, Entry)>, prefix: &str) -> Vec<(String, Entry)> {
+    entries
+        .into_iter()
+        .map(|(key, entry)| prefix_entry(prefix, key, entry))
+        .collect()
+}
+
+fn prefix_entry(prefix: &str, key: String, entry: Entry) -> (String, Entry) {
+    let target = entry.alias_of().map(|target| format!("{prefix}{target}"));
+    let entry = if target.is_some() {
+        entry.with_alias_of(target)
+    } else {
+        entry
+    };
+    (format!("{prefix}{key}"), entry)
+}
+
+/// Applies `[keys]` and `[tags]` rules and the empty-value check to one imported
+/// entry, returning it with its tags normalized the way `add` would store them.
+fn check_import_entry(
+    key: &str,
+    entry: Entry,
+    key_policy: &KeyPolicy,
+    tag_policy: &TagPolicy,
+    allow_empty: bool,
+) -> KvResult<Entry> {
+    key_policy.check(key)?;
+    // Aliases carry no value of their own.
+    if !allow_empty && entry.alias_of().is_none() && entry.payload().is_empty() {
+        return Err(KvError::InvalidInput(format!(
+            "value for '{key}' is empty; pass --allow-empty to import it"
+        )));
+    }
+    let tags = Store::normalize_tags_with(entry.tags().to_vec(), tag_policy)?;
+    Ok(entry.with_tags(tags))
+}
+
+/// Lowercases imported keys and alias targets under `[keys] case_insensitive`.
+/// Imported keys differing only in case, or differing only in case from a key in
+/// `stored` (a merge import), are rejected instead of silently merged.
+struct ImportKeyFolder<'a> {
+    policy: &'a KeyPolicy,
+    stored: Option<&'a FoldedKeys>,
+    imported: FoldedKeys,
+}
+
+impl<'a> ImportKeyFolder<'a> {
+    fn new(policy: &'a KeyPolicy, stored: Option<&'a FoldedKeys>) -> Self {
+        Self {
+            policy,
+            stored,
+            imported: FoldedKeys::default(),
+        }
+    }
+
+    fn fold(&mut self, mut key: String, entry: Entry) -> KvResult<(String, Entry)> {
+        if !self.policy.case_insensitive {
+            return Ok((key, entry));
+        }
+        if let Some(other) = self.imported.collision(&key) {
+            return Err(KvError::InvalidInput(format!(
+                "imported keys '{other}' and '{key}' differ only in case"
+            )));
+        }
+        self.imported.insert(&key);
+        self.policy.fold(&mut key);
+        if let Some(stored) = self.stored {
+            stored.check(&key)?;
+        }
+        let target = entry.alias_of().map(str::to_lowercase);
+        Ok((key, entry.with_alias_of(target)))
+    }
+}
+
+/// Checks that every imported alias points at a key that is not itself an alias. With
+/// `existing`, targets may also be keys already stored there (an additive import).
+fn check_import_aliases(entries: &[(String, Entry)], existing: Option<&Store>) -> KvResult<()> {
+    let imported: BTreeMap<&str, &Entry> = entries
+        .iter()
+        .map(|(key, entry)| (key.as_str(), entry))
+        .collect();
+    for (key, entry) in entries {
+        let Some(target) = entry.alias_of() else {
+            continue;
+        };
+        let found = imported
+            .get(target)
+            .copied()
+            .or_else(|| existing.and_then(|store| store.get(target)));
+        match found {
+            Some(target_entry) if target_entry.alias_of().is_none() => {}
+            Some(_) => return Err(bad_alias_target(key, target, true)),
+            None => return Err(bad_alias_target(key, target, false)),
+        }
+    }
+    Ok(())
+}
+
+/// The error for an imported alias whose target is missing or is itself an alias.
+pub(crate) fn bad_alias_target(key: &str, target: &str, target_is_alias: bool) -> KvError {
+    let problem = if target_is_alias {
+        "which is itself an alias"
+    } else {
+        "which is neither imported nor stored"
+    };
+    KvError::InvalidInput(format!(
+        "alias '{key}' points at '{target}', {problem}; nothing was imported"
+    ))
+}
+
+fn parse_glob(pattern: &str) -> KvResult<glob::Pattern> {
+    glob::Pattern::new(pattern)
+        .map_err(|err| KvError::InvalidInput(format!("invalid glob '{pattern}': {err}")))
+}
+
+/// Polls the watched keys every `interval` and prints what changed, until the process
+/// is interrupted. The terminal is left in its normal mode, so Ctrl-C needs no cleanup.
+fn watch(
+    database: &Database,
+    key: Option<&str>,
+    prefix: Option<&str>,
+    interval: Duration,
+    style: &OutputStyle,
+) -> KvResult<()> {
+    let mut seen = read_watched(database, key, prefix)?;
+    let mut revision = database.current_revision()?;
+    info!("watching {} keys", seen.len());
+    loop {
+        std::thread::sleep(interval);
+        // Only re-read once another connection has committed something.
+        let current = database.current_revision()?;
+        if current == revision {
+            continue;
+        }
+        revision = current;
+        let next = read_watched(database, key, prefix)?;
+        for line in watch_changes(&seen, &next, style) {
+            println!("{line}");
+        }
+        seen = next;
+    }
+}
+
+fn read_watched(
+    database: &Database,
+    key: Option<&str>,
+    prefix: Option<&str>,
+) -> KvResult<BTreeMap<String, Entry>> {
+    let keys = match key {
+        Some(key) => vec![key.to_string()],
+        None => database.keys_with_prefix(prefix.unwrap_or(""))?,
+    };
+    Ok(database.load_entries_for(&keys)?.into_iter().collect())
+}
+
+/// `+ key: value` for new keys, `~ key: value` for keys whose `updated_at` moved, and
+/// `- key` for removed ones, in key order.
+fn watch_changes(
+    before: &BTreeMap<String, Entry>,
+    after: &BTreeMap<String, Entry>,
+    style: &OutputStyle,
+) -> Vec<String> {
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    let changed = |marker: &str, key: &str, entry: &Entry| {
+        let value = entry.display_value();
+        format!("{marker} {}: {}", style.key(key), style.value(&value))
+    };
+    keys.into_iter()
+        .filter_map(|key| match (before.get(key), after.get(key)) {
+            (None, Some(entry)) => Some(changed("+", key, entry)),
+            (Some(old), Some(entry)) if old.updated_at() != entry.updated_at() => {
+                Some(changed("~", key, entry))
+            }
+            (Some(_), None) => Some(format!("- {}", style.key(key))),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reads the other side of `diff`: an export file by extension, otherwise a database.
+fn load_diff_source(path: &Path, limits: &LimitsSettings) -> KvResult<Store> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    if matches!(extension.as_deref(), Some("json" | "jsonl" | "ndjson")) {
+        let format = ExportFormat::resolve(None, path);
+        return Ok(Store::from_entries(read_import(path, format, limits)?));
+    }
+    Ok(Store::from_entries(
+        Database::open_read_only(path)?.load_entries()?,
+    ))
+}
+
+/// Stored keys starting with `prefix`, for shell completion. The database is opened
+/// read-only, and a missing file completes to nothing instead of being created.
+fn complete_keys(db_path: &Path, prefix: &str) -> KvResult<Vec<String>> {
+    if is_in_memory(db_path) || !db_path.exists() {
+        return Ok(Vec::new());
+    }
+    Database::open_read_only(db_path)?.keys_with_prefix(prefix)
+}
+
+/// `-` for keys only here, `+` for keys only in the other store, `~` for changed ones.
+fn print_diff(diff: &StoreDiff, ours: &Store, theirs: &Store, values: bool) {
+    let show = |marker: char, key: &str, entry: Option<&Entry>| match entry {
+        Some(entry) if values => println!("{marker} {key}: {}", describe_value(entry)),
+        _ => println!("{marker} {key}"),
+    };
+    for key in &diff.only_left {
+        show('-', key, ours.get(key));
+    }
+    for key in &diff.only_right {
+        show('+', key, theirs.get(key));
+    }
+    for key in &diff.changed {
+        match (ours.get(key), theirs.get(key)) {
+            (Some(mine), Some(other)) if values => println!(
+                "~ {key}: {} -> {}",
+                describe_value(mine),
+                describe_value(other)
+            ),
+            _ => println!("~ {key}"),
         }
     }
 }
 
-/// Executes the application logic for the provided CLI arguments.
-pub fn run(cli: Cli, settings: &AppSettings) -> KvResult<()> {
-    let namespace = resolve_namespace(cli.namespace.as_deref())?;
-    let db_path = cli
-        .data_file
-        .unwrap_or_else(|| default_data_file_path(&namespace));
-    info!("opening store at {}", db_path.display());
+fn diff_json(diff: &StoreDiff, ours: &Store, theirs: &Store, values: bool) -> serde_json::Value {
+    let side = |keys: &[String], store: &Store| -> Vec<serde_json::Value> {
+        keys.iter()
+            .map(|key| match store.get(key) {
+                Some(entry) if values => serde_json::json!({
+                    "key": key,
+                    "value": entry.display_value(),
+                    "tags": entry.tags(),
+                }),
+                _ => serde_json::json!(key),
+            })
+            .collect()
+    };
+    let changed: Vec<_> = diff
+        .changed
+        .iter()
+        .map(|key| match (ours.get(key), theirs.get(key)) {
+            (Some(mine), Some(other)) if values => serde_json::json!({
+                "key": key,
+                "current": { "value": mine.display_value(), "tags": mine.tags() },
+                "other": { "value": other.display_value(), "tags": other.tags() },
+            }),
+            _ => serde_json::json!(key),
+        })
+        .collect();
+    serde_json::json!({
+        "only_in_current": side(&diff.only_left, ours),
+        "only_in_other": side(&diff.only_right, theirs),
+        "changed": changed,
+    })
+}
 
-    if let Command::Serve { host, port } = &cli.command {
-        let mut database = Database::connect(&db_path)?;
-        serve_viewer(&mut database, &db_path, &namespace, host, *port)?;
-        return Ok(());
+/// Parses and validates an export file; nothing is written until the caller
+/// replaces the store with the result.
+fn read_import(
+    path: &Path,
+    format: ExportFormat,
+    limits: &LimitsSettings,
+) -> KvResult<Vec<(String, Entry)>> {
+    let map = match format {
+        ExportFormat::Json => read_import_json(path)?,
+        ExportFormat::Jsonl => read_import_jsonl(path)?,
+    };
+    let problems: Vec<String> = map
+        .iter()
+        .flat_map(|(key, item)| import_timestamp_problems(key, item))
+        .collect();
+    if !problems.is_empty() {
+        return Err(KvError::InvalidInput(format!(
+            "invalid timestamps in {}; nothing was imported: {}",
+            path.display(),
+            problems.join("; ")
+        )));
     }
 
-    let mut database = Database::connect(&db_path)?;
-    let _ = database.cleanup_expired_entries()?;
-    let entries = database.load_entries()?;
-    let mut store = Store::from_entries(entries);
+    map.into_iter()
+        .map(|(key, item)| {
+            let entry = import_entry(&key, item, limits)?;
+            Ok((key, entry))
+        })
+        .collect()
+}
 
-    let history_settings = settings.history();
-    let recent_path = history_settings
-        .file()
-        .map(PathBuf::from)
-        .unwrap_or_else(|| default_recent_log_path(&namespace));
-    let recent_limit = history_settings.limit();
-    if recent_limit > 0 {
-        let config = RecentConfig::new(recent_path, recent_limit);
-        store.enable_recent_history(config);
+/// Each timestamp of `item` that is not RFC 3339, described with its key and field.
+fn import_timestamp_problems(key: &str, item: &ImportEntry) -> Vec<String> {
+    [
+        ("created_at", item.created_at.as_deref()),
+        ("updated_at", item.updated_at.as_deref()),
+        (
+            "expires_at",
+            item.expires_at
+                .as_deref()
+                .filter(|text| !text.trim().is_empty()),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(field, text)| {
+        let text = text?;
+        let error = DateTime::parse_from_rfc3339(text).err()?;
+        Some(format!("'{key}' has {field} '{text}' ({error})"))
+    })
+    .collect()
+}
+
+/// Decodes and validates one export record.
+fn import_entry(key: &str, item: ImportEntry, limits: &LimitsSettings) -> KvResult<Entry> {
+    if let Some(problem) = import_timestamp_problems(key, &item).into_iter().next() {
+        return Err(KvError::InvalidInput(format!(
+            "invalid timestamp: {problem}"
+        )));
     }
+    let value = decode_export_value(key, item.value, item.value_type.as_deref())?;
+    enforce_value_limit(key, value.len(), limits.max_value_bytes())?;
+    let tags = Store::normalize_tags(item.tags.unwrap_or_default());
+    let tags_json = serde_json::to_string(&tags)?;
+
+    let created_at = item.created_at.unwrap_or_else(|| Utc::now().to_rfc3339());
+    let updated_at = item.updated_at.unwrap_or_else(|| Utc::now().to_rfc3339());
+
+    Ok(Entry::from_persisted(
+        value,
+        &tags_json,
+        &created_at,
+        &updated_at,
+        item.expires_at.as_deref(),
+    )?
+    .with_notes(item.notes)
+    .with_alias_of(item.alias_of))
+}
 
-    match cli.command {
-        Command::Add { key, value, tags } => {
-            handle_add(&mut database, &mut store, key, value, tags)?
-        }
-        Command::Get { key } => {
-            let entry = store
-                .get(&key)
-                .ok_or_else(|| KvError::NotFound(key.clone()))?
-                .clone();
-            store.record_access(&key);
-            println!("{}", entry.value());
-            if !entry.tags().is_empty() {
-                println!("tags: {}", entry.tags().join(", "));
-            }
-        }
-        Command::Remove { key } => {
-            handle_remove(&mut database, &mut store, key)?;
-        }
-        Command::List => {
-            if store.is_empty() {
-                println!("No entries stored.");
-            } else {
-                for (key, entry) in store.ordered() {
-                    println!("{}", entry.summary(key));
+/// Parses an export file record by record, handing each to `each` without collecting
+/// them. Stops at the first error from the parser or from `each`.
+fn stream_import(
+    path: &Path,
+    format: ExportFormat,
+    each: &mut dyn FnMut(String, ImportEntry) -> KvResult<()>,
+) -> KvResult<()> {
+    let file = fs::File::open(path)
+        .map_err(|error| KvError::io_path("reading import file", path.to_path_buf(), error))?;
+    let reader = BufReader::new(file);
+    match format {
+        ExportFormat::Jsonl => {
+            for (index, line) in reader.lines().enumerate() {
+                let line = line.map_err(|error| {
+                    KvError::io_path("reading import file", path.to_path_buf(), error)
+                })?;
+                if line.trim().is_empty() {
+                    continue;
                 }
+                let record: ImportLine = serde_json::from_str(&line).map_err(|error| {
+                    KvError::InvalidInput(format!("{}:{}: {error}", path.display(), index + 1))
+                })?;
+                each(record.key, record.entry)?;
             }
+            Ok(())
         }
-        Command::Search {
-            pattern,
-            limit,
-            tags_only,
-            keys_only,
-        } => {
-            let scope = resolve_scope(tags_only, keys_only)?;
-            let matches = store.search(&pattern, limit, scope);
-            if matches.is_empty() {
-                println!("No matches found.");
-            } else {
-                for item in matches {
-                    println!("{}", item.entry.summary(item.key));
-                }
+        ExportFormat::Json => {
+            let mut deserializer = serde_json::Deserializer::from_reader(reader);
+            let mut visitor = ImportMapVisitor {
+                each,
+                failure: None,
+            };
+            let parsed = serde::Deserializer::deserialize_map(&mut deserializer, &mut visitor);
+            if let Some(error) = visitor.failure {
+                return Err(error);
             }
-        }
-        Command::Export { path } => {
-            export_to_path(&store, &path)?;
-            println!("Exported {} entries to {}", store.len(), path.display());
-        }
-        Command::Import { path } => {
-            handle_import(&mut database, &mut store, &path)?;
-            println!("Imported entries from {}", path.display());
-        }
-        Command::Html { path } => {
-            export_html_view(&store, &path)?;
-            println!(
-                "Generated HTML view at {} (namespace: {}, data source: {})",
-                path.display(),
-                namespace,
-                db_path.display()
-            );
-        }
-        Command::Serve { .. } => unreachable!("serve is handled before cache loading"),
-        Command::PutFile {
-            key,
-            path,
-            tags,
-            any_file,
-        } => {
-            handle_put_file(&mut database, &mut store, key, &path, tags, any_file)?;
-        }
-        Command::GetFile {
-            key,
-            path,
-            any_file,
-        } => {
-            let key_for_message = key.clone();
-            handle_get_file(&mut store, key, &path, any_file)?;
-            println!("Wrote '{}' to {}", key_for_message, path.display());
-        }
-        Command::Interactive {
-            limit,
-            tags_only,
-            keys_only,
-        } => {
-            let scope = resolve_scope(tags_only, keys_only)?;
-            live_search(&store, limit, scope)?;
-        }
-        Command::Recent { limit } => {
-            let recent = store.recent(limit);
-            if recent.is_empty() {
-                println!("No recent keys recorded.");
-            } else {
-                for (idx, key) in recent.iter().enumerate() {
-                    println!("{:>2}. {}", idx + 1, key);
-                }
+            match parsed {
+                Ok(()) => Ok(deserializer.end()?),
+                // An empty file imports nothing, as with the buffered path.
+                Err(error) if error.is_eof() && error.line() == 1 && error.column() == 0 => Ok(()),
+                Err(error) => Err(error.into()),
             }
         }
     }
-
-    Ok(())
 }
 
-fn handle_add(
-    database: &mut Database,
-    store: &mut Store,
-    key: String,
-    value: String,
-    tags: Vec<String>,
-) -> KvResult<()> {
-    let existing = store.get(&key).cloned();
-    let tags = if tags.is_empty() {
-        existing
-            .as_ref()
-            .map(|entry| entry.tags().to_vec())
-            .unwrap_or_default()
-    } else {
-        Store::normalize_tags(tags)
-    };
-    let entry = Entry::for_update(existing.as_ref(), value, tags);
+/// Feeds the entries of a top-level JSON export object to a callback as they are parsed.
+struct ImportMapVisitor<'f> {
+    each: &'f mut dyn FnMut(String, ImportEntry) -> KvResult<()>,
+    /// The callback's error, kept so it is reported instead of a generic parse error.
+    failure: Option<KvError>,
+}
 
-    database.upsert_entry(&key, &entry)?;
-    let previous = store.insert(key.clone(), entry.clone());
-    store.record_access(&key);
+impl<'de> serde::de::Visitor<'de> for &mut ImportMapVisitor<'_> {
+    type Value = ();
 
-    match previous {
-        Some(old) => println!(
-            "Updated '{}'. Previous: {}; Now: {}",
-            key,
-            describe_value(&old),
-            describe_value(&entry)
-        ),
-        None => println!("Added '{}'. {}", key, describe_value(&entry)),
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("an object of exported entries")
     }
 
-    Ok(())
-}
-
-fn handle_remove(database: &mut Database, store: &mut Store, key: String) -> KvResult<()> {
-    let existing = store
-        .get(&key)
-        .cloned()
-        .ok_or_else(|| KvError::NotFound(key.clone()))?;
-
-    database.delete_entry(&key)?;
-    store
-        .remove(&key)
-        .ok_or_else(|| KvError::NotFound(key.clone()))?;
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        use serde::de::Error as _;
 
-    println!(
-        "Removed '{}'. Stored value was {}.",
-        key,
-        describe_value(&existing)
-    );
-    Ok(())
+        while let Some(key) = map.next_key::<String>()? {
+            let item = if key == EXPORT_META_KEY {
+                let record: serde_json::Value = map.next_value()?;
+                if record.get("value").is_none() {
+                    continue;
+                }
+                serde_json::from_value(record).map_err(A::Error::custom)?
+            } else {
+                map.next_value::<ImportEntry>()?
+            };
+            if let Err(error) = (self.each)(key, item) {
+                self.failure = Some(error);
+                return Err(A::Error::custom("import aborted"));
+            }
+        }
+        Ok(())
+    }
 }
 
-fn handle_import(database: &mut Database, store: &mut Store, path: &Path) -> KvResult<()> {
+fn read_import_json(path: &Path) -> KvResult<BTreeMap<String, ImportEntry>> {
     let contents = fs::read_to_string(path)
         .map_err(|error| KvError::io_path("reading import file", path.to_path_buf(), error))?;
     if contents.trim().is_empty() {
-        warn!("import file {} is empty; clearing database", path.display());
+        return Ok(BTreeMap::new());
+    }
+    // Older exports have no metadata; a stored entry under the reserved name has a value.
+    let mut object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&contents)?;
+    let is_meta = |record: &serde_json::Value| record.get("value").is_none();
+    if object.get(EXPORT_META_KEY).is_some_and(is_meta) {
+        object.remove(EXPORT_META_KEY);
+        return Ok(serde_json::from_value(serde_json::Value::Object(object))?);
+    }
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Reads one record per line, skipping blank lines. A repeated key keeps its last record.
+fn read_import_jsonl(path: &Path) -> KvResult<BTreeMap<String, ImportEntry>> {
+    let file = fs::File::open(path)
+        .map_err(|error| KvError::io_path("reading import file", path.to_path_buf(), error))?;
+    let mut map = BTreeMap::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line
+            .map_err(|error| KvError::io_path("reading import file", path.to_path_buf(), error))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: ImportLine = serde_json::from_str(&line).map_err(|error| {
+            KvError::InvalidInput(format!("{}:{}: {error}", path.display(), index + 1))
+        })?;
+        map.insert(record.key, record.entry);
     }
+    Ok(map)
+}
+
+struct MigrateReport {
+    migrated: usize,
+    skipped: Vec<String>,
+}
 
-    let map: BTreeMap<String, ImportEntry> = if contents.trim().is_empty() {
+/// Adds legacy JSON entries alongside the existing ones in a single `replace_all`
+/// transaction. Existing keys win; colliding legacy keys are reported as skipped.
+fn handle_migrate(
+    database: &mut Database,
+    store: &mut Store,
+    path: &Path,
+    limits: &LimitsSettings,
+    tag_policy: &TagPolicy,
+) -> KvResult<MigrateReport> {
+    let contents = fs::read_to_string(path)
+        .map_err(|error| KvError::io_path("reading legacy store", path.to_path_buf(), error))?;
+    let legacy: BTreeMap<String, LegacyEntry> = if contents.trim().is_empty() {
         BTreeMap::new()
     } else {
         serde_json::from_str(&contents)?
     };
 
-    let mut entries = Vec::with_capacity(map.len());
-
-    for (key, item) in map {
-        let tags = Store::normalize_tags(item.tags.unwrap_or_default());
-        let tags_json = serde_json::to_string(&tags)?;
-
-        let created_at = item.created_at.unwrap_or_else(|| Utc::now().to_rfc3339());
-        let updated_at = item.updated_at.unwrap_or_else(|| Utc::now().to_rfc3339());
-
-        let entry = Entry::from_persisted(
-            item.value,
-            &tags_json,
-            &created_at,
-            &updated_at,
-            item.expires_at.as_deref(),
-        )?;
-        entries.push((key, entry));
+    let mut entries: Vec<(String, Entry)> = store
+        .ordered()
+        .into_iter()
+        .map(|(key, entry)| (key.clone(), entry.clone()))
+        .collect();
+    let mut report = MigrateReport {
+        migrated: 0,
+        skipped: Vec::new(),
+    };
+    for (key, item) in legacy {
+        if store.get(&key).is_some() {
+            report.skipped.push(key);
+            continue;
+        }
+        let (value, tags) = match item {
+            LegacyEntry::Plain(value) => (value, Vec::new()),
+            LegacyEntry::Record { value, tags } => {
+                (value, Store::normalize_tags_with(tags, tag_policy)?)
+            }
+        };
+        enforce_value_limit(&key, value.len(), limits.max_value_bytes())?;
+        entries.push((key, Entry::new(value, tags)));
+        report.migrated += 1;
     }
 
     database.replace_all(&entries)?;
     store.reset(entries);
-
-    Ok(())
+    Ok(report)
 }
 
-fn handle_put_file(
-    database: &mut Database,
-    store: &mut Store,
-    key: String,
-    path: &Path,
-    tags: Vec<String>,
-    any_file: bool,
-) -> KvResult<()> {
+fn read_source_file(path: &Path, any_file: bool, binary: bool) -> KvResult<EntryValue> {
     validate_markdown_path(path, any_file, "source file")?;
-    let contents = fs::read_to_string(path)
-        .map_err(|error| KvError::io_path("reading source file", path.to_path_buf(), error))?;
-    handle_add(database, store, key, contents, tags)
+    if binary {
+        fs::read(path).map(EntryValue::Blob)
+    } else {
+        fs::read_to_string(path).map(EntryValue::Text)
+    }
+    .map_err(|error| KvError::io_path("reading source file", path.to_path_buf(), error))
 }
 
-fn handle_get_file(store: &mut Store, key: String, path: &Path, any_file: bool) -> KvResult<()> {
+fn read_binary_stdin(inline_value: &str) -> KvResult<EntryValue> {
+    if !inline_value.is_empty() {
+        return Err(KvError::InvalidInput(
+            "binary values are read from stdin; omit the inline value when using --binary"
+                .to_string(),
+        ));
+    }
+    let mut bytes = Vec::new();
+    io::stdin().read_to_end(&mut bytes)?;
+    Ok(EntryValue::Blob(bytes))
+}
+
+fn handle_get_file(kv: &mut KvStore, key: String, path: &Path, any_file: bool) -> KvResult<()> {
     validate_markdown_path(path, any_file, "destination file")?;
-    let entry = store
-        .get(&key)
-        .ok_or_else(|| KvError::NotFound(key.clone()))?;
+    let entry = kv.get(&key)?;
 
     if let Some(parent) = path.parent() {
         if !parent.as_os_str().is_empty() {
@@ -336,13 +2102,22 @@ fn handle_get_file(store: &mut Store, key: String, path: &Path, any_file: bool)
         }
     }
 
-    fs::write(path, entry.value())
+    fs::write(path, entry.bytes())
         .map_err(|error| KvError::io_path("writing destination file", path.to_path_buf(), error))?;
-    store.record_access(&key);
+    kv.record_access(&key);
     Ok(())
 }
 
-fn export_to_path(store: &Store, path: &Path) -> KvResult<()> {
+/// Writes `keys` (every entry when empty) to `path` and returns how many were written.
+fn export_to_path(
+    store: &Store,
+    keys: &[String],
+    path: &Path,
+    order: SortOrder,
+    zone: DisplayZone,
+    format: ExportFormat,
+    compact: bool,
+) -> KvResult<usize> {
     if let Some(parent) = path.parent() {
         if !parent.as_os_str().is_empty() {
             fs::create_dir_all(parent).map_err(|error| {
@@ -351,24 +2126,172 @@ fn export_to_path(store: &Store, path: &Path) -> KvResult<()> {
         }
     }
 
-    let mut map = BTreeMap::new();
-    for (key, entry) in store.ordered() {
-        map.insert(
-            key.clone(),
-            ExportEntry {
-                value: entry.value().to_string(),
-                tags: entry.tags().to_vec(),
-                created_at: entry.created_at().to_rfc3339(),
-                updated_at: entry.updated_at().to_rfc3339(),
-                expires_at: entry.expires_at().map(|ts| ts.to_rfc3339()),
-            },
-        );
+    let selected = store
+        .ordered_by(order)
+        .into_iter()
+        .filter(|(key, _)| keys.is_empty() || keys.contains(key));
+    let export_entry = |entry: &Entry| ExportEntry {
+        value: encode_export_value(entry.payload()),
+        value_type: entry.is_binary().then_some(EntryValue::BLOB),
+        tags: entry.tags().to_vec(),
+        created_at: zone.rfc3339(entry.created_at()),
+        updated_at: zone.rfc3339(entry.updated_at()),
+        expires_at: entry.expires_at().map(|ts| zone.rfc3339(ts)),
+        notes: entry.notes().map(str::to_string),
+        alias_of: entry.alias_of().map(str::to_string),
+    };
+    let write_error = |error| KvError::io_path("writing export file", path.to_path_buf(), error);
+
+    if format == ExportFormat::Jsonl {
+        // One record per line, so each is serialized and written as it is built.
+        let mut count = 0;
+        write_atomically_with(path, |out| {
+            for (key, entry) in selected {
+                let line = ExportLine {
+                    key,
+                    entry: &export_entry(entry),
+                };
+                serde_json::to_writer(&mut *out, &line)?;
+                out.write_all(b"\n")?;
+                count += 1;
+            }
+            Ok(())
+        })
+        .map_err(write_error)?;
+        return Ok(count);
     }
 
-    let json = serde_json::to_string_pretty(&map)?;
-    fs::write(path, format!("{json}\n"))
-        .map_err(|error| KvError::io_path("writing export file", path.to_path_buf(), error))?;
-    Ok(())
+    let records: Vec<_> = selected
+        .map(|(key, entry)| (key.as_str(), export_entry(entry)))
+        .collect();
+    let count = records.len();
+    let meta = if records.iter().any(|(key, _)| *key == EXPORT_META_KEY) {
+        warn!("a stored key is named '{EXPORT_META_KEY}'; exporting without metadata");
+        None
+    } else {
+        Some(ExportMeta {
+            exported_at: zone.rfc3339(Utc::now()),
+            version: env!("CARGO_PKG_VERSION"),
+            entries: count,
+        })
+    };
+
+    let map = ExportMap { meta, records };
+    write_atomically_with(path, |out| {
+        if compact {
+            serde_json::to_writer(&mut *out, &map)?;
+        } else {
+            serde_json::to_writer_pretty(&mut *out, &map)?;
+        }
+        out.write_all(b"\n")
+    })
+    .map_err(write_error)?;
+    Ok(count)
+}
+
+/// `backup.json` -> `backup.json.sha256`.
+fn checksum_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+/// Hex SHA-256 of a file, read in chunks so large exports are not held in memory.
+fn sha256_file(path: &Path, action: &'static str) -> KvResult<String> {
+    let mut hasher = Sha256::new();
+    fs::File::open(path)
+        .and_then(|mut file| io::copy(&mut file, &mut hasher))
+        .map_err(|error| KvError::io_path(action, path.to_path_buf(), error))?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Hashes the export as written and stores it in `sha256sum` format next to it. Without
+/// `enabled`, a sidecar left by an earlier export is removed so it cannot go stale.
+fn write_checksum(path: &Path, enabled: bool) -> KvResult<()> {
+    let sidecar = checksum_path(path);
+    if !enabled {
+        return match fs::remove_file(&sidecar) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                Err(KvError::io_path("removing stale checksum", sidecar, error))
+            }
+            _ => Ok(()),
+        };
+    }
+    let digest = sha256_file(path, "reading export file")?;
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    let line = format!("{digest}  {name}\n");
+    write_atomically(&sidecar, line.as_bytes())
+        .map_err(|error| KvError::io_path("writing checksum file", sidecar, error))
+}
+
+/// Checks `path` against its `.sha256` sidecar, if there is one.
+fn verify_checksum(path: &Path) -> KvResult<()> {
+    let sidecar = checksum_path(path);
+    let expected = match fs::read_to_string(&sidecar) {
+        Ok(line) => line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase(),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(KvError::io_path("reading checksum file", sidecar, error)),
+    };
+    let actual = sha256_file(path, "reading import file")?;
+    if actual == expected {
+        info!("checksum verified for {}", path.display());
+        Ok(())
+    } else {
+        Err(KvError::InvalidInput(format!(
+            "{} does not match {} (expected {expected}, got {actual}); \
+             the file may be truncated or modified, pass --no-verify to import it anyway",
+            path.display(),
+            sidecar.display()
+        )))
+    }
+}
+
+/// Writes to a sibling temp file and renames it over `path`, so readers never see a
+/// partially written file. Falls back to copy+remove if the rename crosses devices.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    write_atomically_with(path, |out| out.write_all(contents))
+}
+
+/// Like [`write_atomically`], with `write` filling a buffered temporary file.
+fn write_atomically_with(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "destination has no file name")
+    })?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".tmp-{}", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let written = fs::File::create(&temp_path).and_then(|file| {
+        let mut out = io::BufWriter::new(file);
+        write(&mut out)?;
+        out.flush()
+    });
+    let result = written.and_then(|()| match fs::rename(&temp_path, path) {
+        Err(error) if error.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(&temp_path, path)?;
+            fs::remove_file(&temp_path)
+        }
+        other => other,
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
 }
 
 fn export_html_view(store: &Store, path: &Path) -> KvResult<()> {
@@ -2035,7 +3958,7 @@ fn serialize_html_records(store: &Store) -> KvResult<String> {
         .into_iter()
         .map(|(key, entry)| HtmlEntry {
             key: key.as_str(),
-            value: entry.value(),
+            value: entry.display_value(),
             tags: entry.tags(),
             created_at: entry.created_at().to_rfc3339(),
             updated_at: entry.updated_at().to_rfc3339(),
@@ -2045,20 +3968,54 @@ fn serialize_html_records(store: &Store) -> KvResult<String> {
     Ok(serde_json::to_string(&records)?)
 }
 
+/// `KVSTORE_HOME` when set; otherwise prefers an existing `~/.kvstore` so older
+/// installs keep their data, then `$XDG_DATA_HOME/kvstore` (usually `~/.local/share/kvstore`).
 fn default_storage_dir() -> PathBuf {
-    if let Some(home) = env::var_os("HOME").filter(|value| !value.is_empty()) {
-        return PathBuf::from(home).join(APP_DIR);
+    if let Some(home) = settings::home_dir() {
+        return home;
+    }
+    let data_dir = BaseDirs::new().map(|dirs| dirs.data_dir().to_path_buf());
+    pick_storage_dir(legacy_storage_dir(), data_dir)
+}
+
+fn pick_storage_dir(legacy: Option<PathBuf>, data_dir: Option<PathBuf>) -> PathBuf {
+    if let Some(dir) = legacy.as_ref().filter(|dir| dir.is_dir()) {
+        return dir.clone();
     }
-    if let Some(profile) = env::var_os("USERPROFILE").filter(|value| !value.is_empty()) {
-        return PathBuf::from(profile).join(APP_DIR);
+    if let Some(data_dir) = data_dir {
+        return data_dir.join(XDG_APP_DIR);
     }
-    PathBuf::from(APP_DIR)
+    legacy.unwrap_or_else(|| PathBuf::from(APP_DIR))
+}
+
+fn legacy_storage_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .filter(|value| !value.is_empty())
+        .or_else(|| env::var_os("USERPROFILE").filter(|value| !value.is_empty()))
+        .map(|home| PathBuf::from(home).join(APP_DIR))
+}
+
+/// Precedence: `--data-file`, then the `--profile` data file, then `KVSTORE_DATA_FILE`,
+/// then `[database] data_file`, then the namespace default.
+fn resolve_data_file(
+    explicit: Option<PathBuf>,
+    profile: Option<&ProfileSettings>,
+    env_data_file: Option<PathBuf>,
+    settings: &AppSettings,
+    namespace: &str,
+) -> PathBuf {
+    explicit
+        .or_else(|| {
+            profile
+                .and_then(ProfileSettings::data_file)
+                .map(PathBuf::from)
+        })
+        .or(env_data_file)
+        .or_else(|| settings.database().data_file().map(PathBuf::from))
+        .unwrap_or_else(|| default_data_file_path(namespace))
 }
 
 fn default_data_file_path(namespace: &str) -> PathBuf {
-    if let Some(explicit) = env::var_os("KVSTORE_DATA_FILE").filter(|value| !value.is_empty()) {
-        return PathBuf::from(explicit);
-    }
     namespace_dir(namespace).join(DEFAULT_DATA_FILE_NAME)
 }
 
@@ -2093,13 +4050,18 @@ fn resolve_namespace(raw: Option<&str>) -> KvResult<String> {
 }
 
 fn validate_namespace(namespace: &str) -> KvResult<()> {
-    if matches!(namespace, "." | "..") {
+    validate_file_name("namespace", namespace)
+}
+
+/// Accepts names that are safe to use as a single path component.
+fn validate_file_name(kind: &str, name: &str) -> KvResult<()> {
+    if matches!(name, "." | "..") {
         return Err(KvError::InvalidInput(format!(
-            "invalid namespace '{namespace}'; '.' and '..' are not allowed"
+            "invalid {kind} '{name}'; '.' and '..' are not allowed"
         )));
     }
 
-    let is_valid = namespace
+    let is_valid = name
         .chars()
         .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '.'));
 
@@ -2107,11 +4069,81 @@ fn validate_namespace(namespace: &str) -> KvResult<()> {
         Ok(())
     } else {
         Err(KvError::InvalidInput(format!(
-            "invalid namespace '{namespace}'; use letters, numbers, '_', '-', or '.'"
+            "invalid {kind} '{name}'; use letters, numbers, '_', '-', or '.'"
         )))
     }
 }
 
+/// One line of `snapshots/index.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotRecord {
+    name: String,
+    created_at: DateTime<Utc>,
+}
+
+/// Snapshots live next to the data file so each namespace keeps its own.
+fn snapshot_dir(db_path: &Path) -> PathBuf {
+    db_path
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(SNAPSHOTS_DIR)
+}
+
+fn snapshot_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.db"))
+}
+
+/// Indexed snapshots whose file still exists, oldest first.
+fn list_snapshots(dir: &Path) -> KvResult<Vec<SnapshotRecord>> {
+    let path = dir.join(SNAPSHOT_INDEX_FILE);
+    let mut records: Vec<SnapshotRecord> = match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)?,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(error) => return Err(KvError::io_path("reading snapshot index", path, error)),
+    };
+    records.retain(|record| snapshot_path(dir, &record.name).is_file());
+    records.sort_by_key(|record| record.created_at);
+    Ok(records)
+}
+
+fn find_snapshot(dir: &Path, name: &str) -> KvResult<SnapshotRecord> {
+    list_snapshots(dir)?
+        .into_iter()
+        .find(|record| record.name == name)
+        .ok_or_else(|| KvError::NotFound(format!("snapshot {name}")))
+}
+
+/// Backs the database up to `dir/<name>.db` and records it in the index. An existing
+/// snapshot is never overwritten.
+fn create_snapshot(
+    database: &Database,
+    dir: &Path,
+    name: Option<String>,
+) -> KvResult<SnapshotRecord> {
+    let created_at = Utc::now();
+    let name = name.unwrap_or_else(|| created_at.format("%Y%m%d-%H%M%S").to_string());
+    validate_file_name("snapshot name", &name)?;
+    let path = snapshot_path(dir, &name);
+    if path.exists() {
+        return Err(KvError::InvalidInput(format!(
+            "snapshot '{name}' already exists at {}",
+            path.display()
+        )));
+    }
+    fs::create_dir_all(dir).map_err(|error| {
+        KvError::io_path("creating snapshot directory", dir.to_path_buf(), error)
+    })?;
+    database.backup_to(&path)?;
+
+    let mut records = list_snapshots(dir)?;
+    records.push(SnapshotRecord { name, created_at });
+    let index = dir.join(SNAPSHOT_INDEX_FILE);
+    let contents = serde_json::to_string_pretty(&records)? + "\n";
+    write_atomically(&index, contents.as_bytes())
+        .map_err(|error| KvError::io_path("writing snapshot index", index, error))?;
+    Ok(records.pop().expect("record was just pushed"))
+}
+
 fn snapshot_store(database: &Database) -> KvResult<Store> {
     let entries = database.load_entries()?;
     Ok(Store::from_entries(entries))
@@ -2172,8 +4204,17 @@ struct TagDeletePayload {
     tag: String,
 }
 
+/// Rules the viewer's write endpoints apply, the same ones [`KvStore::add`] enforces.
+#[derive(Debug, Clone, Default)]
+struct ApiWriteRules {
+    max_value_bytes: usize,
+    key_policy: KeyPolicy,
+    tag_policy: TagPolicy,
+}
+
 fn serve_viewer(
     database: &mut Database,
+    rules: &ApiWriteRules,
     data_path: &Path,
     namespace: &str,
     host: &str,
@@ -2195,7 +4236,7 @@ fn serve_viewer(
 
         match stream {
             Ok(stream) => {
-                if let Err(error) = handle_http_connection(stream, database) {
+                if let Err(error) = handle_http_connection(stream, database, rules) {
                     warn!("viewer request failed: {}", error);
                 }
             }
@@ -2208,11 +4249,17 @@ fn serve_viewer(
     Ok(())
 }
 
-fn handle_http_connection(mut stream: TcpStream, database: &mut Database) -> KvResult<()> {
+fn handle_http_connection(
+    mut stream: TcpStream,
+    database: &mut Database,
+    rules: &ApiWriteRules,
+) -> KvResult<()> {
     let mut reader = BufReader::new(stream.try_clone()?);
     let Some(request) = read_http_request(&mut reader)? else {
         return Ok(());
     };
+    // Each request reads fresh state, so an earlier snapshot must not block its writes.
+    database.forget_loaded_revision();
 
     match (request.method.as_str(), request.path.as_str()) {
         ("GET", "/") => {
@@ -2240,13 +4287,15 @@ fn handle_http_connection(mut stream: TcpStream, database: &mut Database) -> KvR
             )?;
         }
         ("POST", "/api/records/upsert") => {
-            write_api_text_response(&mut stream, handle_api_record_upsert(database, &request))?;
+            let response = handle_api_record_upsert(database, rules, &request);
+            write_api_text_response(&mut stream, response)?;
         }
         ("POST", "/api/records/delete") => {
             write_api_text_response(&mut stream, handle_api_record_delete(database, &request))?;
         }
         ("POST", "/api/records/tags/add") => {
-            write_api_text_response(&mut stream, handle_api_record_tag_add(database, &request))?;
+            let response = handle_api_record_tag_add(database, rules, &request);
+            write_api_text_response(&mut stream, response)?;
         }
         ("POST", "/api/records/tags/remove") => {
             write_api_text_response(
@@ -2349,16 +4398,23 @@ fn require_positive_minutes(value: u64, field: &str) -> KvResult<u64> {
     }
 }
 
-fn handle_api_record_upsert(database: &mut Database, request: &HttpRequest) -> KvResult<String> {
+fn handle_api_record_upsert(
+    database: &mut Database,
+    rules: &ApiWriteRules,
+    request: &HttpRequest,
+) -> KvResult<String> {
     let payload: RecordUpsertPayload = parse_json_request(request)?;
-    let key = require_non_empty(&payload.key, "key")?;
-    let tags = Store::normalize_tags(payload.tags);
+    let mut key = require_non_empty(&payload.key, "key")?;
+    rules.key_policy.fold(&mut key);
+    let tags = Store::normalize_tags_with(payload.tags, &rules.tag_policy)?;
     let ttl_minutes = payload
         .ttl_minutes
         .map(|minutes| require_positive_minutes(minutes, "ttl_minutes"))
         .transpose()?;
 
     let store = snapshot_store(database)?;
+    store.check_key(&key, &rules.key_policy)?;
+    enforce_value_limit(&key, payload.value.len(), rules.max_value_bytes)?;
     let existing = store.get(&key).cloned();
     let mut entry = Entry::for_update(existing.as_ref(), payload.value, tags);
     if let Some(minutes) = ttl_minutes {
@@ -2380,7 +4436,11 @@ fn handle_api_record_delete(database: &mut Database, request: &HttpRequest) -> K
     Ok(format!("deleted '{key}'"))
 }
 
-fn handle_api_record_tag_add(database: &mut Database, request: &HttpRequest) -> KvResult<String> {
+fn handle_api_record_tag_add(
+    database: &mut Database,
+    rules: &ApiWriteRules,
+    request: &HttpRequest,
+) -> KvResult<String> {
     let payload: RecordTagPayload = parse_json_request(request)?;
     let key = require_non_empty(&payload.key, "key")?;
     let tag = require_non_empty(&payload.tag, "tag")?;
@@ -2392,13 +4452,13 @@ fn handle_api_record_tag_add(database: &mut Database, request: &HttpRequest) ->
         .ok_or_else(|| KvError::NotFound(key.clone()))?;
     let mut tags = existing.tags().to_vec();
     tags.push(tag.clone());
-    let tags = Store::normalize_tags(tags);
+    let tags = Store::normalize_tags_with(tags, &rules.tag_policy)?;
 
     if tags == existing.tags() {
         return Ok(format!("tag '{tag}' already exists on '{key}'"));
     }
 
-    let entry = Entry::for_update(Some(&existing), existing.value().to_string(), tags);
+    let entry = Entry::for_update(Some(&existing), existing.payload().clone(), tags);
     database.upsert_entry(&key, &entry)?;
     Ok(format!("added tag '{tag}' to '{key}'"))
 }
@@ -2426,7 +4486,7 @@ fn handle_api_record_tag_remove(
 
     let entry = Entry::for_update(
         Some(&existing),
-        existing.value().to_string(),
+        existing.payload().clone(),
         Store::normalize_tags(tags),
     );
     database.upsert_entry(&key, &entry)?;
@@ -2449,7 +4509,7 @@ fn handle_api_record_ttl_extend(
 
     let mut entry = Entry::for_update(
         Some(&existing),
-        existing.value().to_string(),
+        existing.payload().clone(),
         existing.tags().to_vec(),
     );
     entry.extend_ttl_minutes(minutes);
@@ -2484,7 +4544,7 @@ fn handle_api_tag_rename(database: &mut Database, request: &HttpRequest) -> KvRe
             .collect::<Vec<_>>();
         let next = Entry::for_update(
             Some(entry),
-            entry.value().to_string(),
+            entry.payload().clone(),
             Store::normalize_tags(next_tags),
         );
         *entry = next;
@@ -2520,7 +4580,7 @@ fn handle_api_tag_delete(database: &mut Database, request: &HttpRequest) -> KvRe
             .collect::<Vec<_>>();
         let next = Entry::for_update(
             Some(entry),
-            entry.value().to_string(),
+            entry.payload().clone(),
             Store::normalize_tags(next_tags),
         );
         *entry = next;
@@ -2597,7 +4657,80 @@ fn validate_markdown_path(path: &Path, any_file: bool, label: &str) -> KvResult<
     }
 }
 
-fn resolve_scope(tags_only: bool, keys_only: bool) -> KvResult<SearchScope> {
+/// Rejects values larger than the configured limit (`0` disables the check).
+fn enforce_value_limit(key: &str, value_len: usize, max_value_bytes: usize) -> KvResult<()> {
+    if max_value_bytes > 0 && value_len > max_value_bytes {
+        return Err(KvError::InvalidInput(format!(
+            "value for '{key}' is {value_len} bytes, exceeding the limit of {max_value_bytes} bytes"
+        )));
+    }
+    Ok(())
+}
+
+/// Text values export verbatim; binary values are hex-encoded.
+fn encode_export_value(value: &EntryValue) -> String {
+    match value {
+        EntryValue::Text(text) => text.clone(),
+        EntryValue::Blob(bytes) => bytes.iter().map(|byte| format!("{byte:02x}")).collect(),
+    }
+}
+
+fn decode_export_value(key: &str, raw: String, value_type: Option<&str>) -> KvResult<EntryValue> {
+    match value_type {
+        None | Some(EntryValue::TEXT) => Ok(EntryValue::Text(raw)),
+        Some(EntryValue::BLOB) => {
+            let invalid =
+                || KvError::InvalidInput(format!("invalid hex-encoded binary value for '{key}'"));
+            if !raw.len().is_multiple_of(2) {
+                return Err(invalid());
+            }
+            (0..raw.len())
+                .step_by(2)
+                .map(|idx| {
+                    raw.get(idx..idx + 2)
+                        .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                        .ok_or_else(invalid)
+                })
+                .collect::<KvResult<Vec<u8>>>()
+                .map(EntryValue::Blob)
+        }
+        Some(other) => Err(KvError::InvalidInput(format!(
+            "unknown value_type '{other}' for '{key}'"
+        ))),
+    }
+}
+
+/// `--limit` wins, then `[search] default_limit`, then the built-in default of 10.
+fn resolve_search_limit(explicit: Option<usize>, settings: &AppSettings) -> usize {
+    explicit
+        .or_else(|| settings.search().default_limit())
+        .unwrap_or(DEFAULT_INTERACTIVE_LIMIT)
+}
+
+/// Text value of `key`, refusing binary entries that cannot be printed or copied.
+fn text_value<'a>(store: &'a Store, key: &str) -> KvResult<&'a str> {
+    let entry = store
+        .get(key)
+        .ok_or_else(|| KvError::NotFound(key.to_string()))?;
+    if entry.is_binary() {
+        return Err(KvError::InvalidInput(format!(
+            "'{key}' holds a binary value ({} bytes); use `get --binary`",
+            entry.bytes().len()
+        )));
+    }
+    Ok(entry.value())
+}
+
+/// Highlighting needs color; without an explicit flag it follows TTY detection.
+fn resolve_highlight(explicit: Option<bool>, style: &OutputStyle, is_terminal: bool) -> bool {
+    style.is_colored() && explicit.unwrap_or(is_terminal)
+}
+
+fn resolve_scope(
+    tags_only: bool,
+    keys_only: bool,
+    default_scope: SearchScope,
+) -> KvResult<SearchScope> {
     if tags_only && keys_only {
         Err(KvError::InvalidInput(
             "Cannot search keys-only and tags-only at the same time.".into(),
@@ -2607,31 +4740,100 @@ fn resolve_scope(tags_only: bool, keys_only: bool) -> KvResult<SearchScope> {
     } else if keys_only {
         Ok(SearchScope::KeysOnly)
     } else {
-        Ok(SearchScope::All)
+        Ok(default_scope)
     }
 }
 
 fn describe_value(entry: &Entry) -> String {
     if entry.tags().is_empty() {
-        format!("'{}'", entry.value())
+        format!("'{}'", entry.display_value())
     } else {
-        format!("'{}' (tags: {})", entry.value(), entry.tags().join(", "))
+        format!(
+            "'{}' (tags: {})",
+            entry.display_value(),
+            entry.tags().join(", ")
+        )
+    }
+}
+
+/// Values in a legacy JSON store: bare strings in the oldest format, records later.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LegacyEntry {
+    Plain(String),
+    Record {
+        value: String,
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+}
+
+/// Serializes as a JSON object whose keys keep the order of `records`, after the
+/// optional metadata.
+struct ExportMap<'a> {
+    meta: Option<ExportMeta>,
+    records: Vec<(&'a str, ExportEntry)>,
+}
+
+impl Serialize for ExportMap<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(meta) = &self.meta {
+            map.serialize_entry(EXPORT_META_KEY, meta)?;
+        }
+        for (key, record) in &self.records {
+            map.serialize_entry(key, record)?;
+        }
+        map.end()
     }
 }
 
+/// Describes a JSON export so a stale backup can be recognized; skipped on import.
+#[derive(Serialize)]
+struct ExportMeta {
+    exported_at: String,
+    version: &'static str,
+    entries: usize,
+}
+
 #[derive(Serialize)]
 struct ExportEntry {
     value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value_type: Option<&'static str>,
     tags: Vec<String>,
     created_at: String,
     updated_at: String,
     expires_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alias_of: Option<String>,
+}
+
+/// A JSON Lines export record: the key alongside the usual entry fields.
+#[derive(Serialize)]
+struct ExportLine<'a> {
+    key: &'a str,
+    #[serde(flatten)]
+    entry: &'a ExportEntry,
+}
+
+#[derive(Deserialize)]
+struct ImportLine {
+    key: String,
+    #[serde(flatten)]
+    entry: ImportEntry,
 }
 
 #[derive(Deserialize)]
 struct ImportEntry {
     value: String,
     #[serde(default)]
+    value_type: Option<String>,
+    #[serde(default)]
     tags: Option<Vec<String>>,
     #[serde(default)]
     created_at: Option<String>,
@@ -2639,12 +4841,16 @@ struct ImportEntry {
     updated_at: Option<String>,
     #[serde(default)]
     expires_at: Option<String>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    alias_of: Option<String>,
 }
 
 #[derive(Serialize)]
 struct HtmlEntry<'a> {
     key: &'a str,
-    value: &'a str,
+    value: Cow<'a, str>,
     tags: &'a [String],
     created_at: String,
     updated_at: String,
@@ -2653,7 +4859,904 @@ struct HtmlEntry<'a> {
 
 #[cfg(test)]
 mod tests {
-    use super::{http_status_for_error, validate_namespace, KvError};
+    use super::{
+        apply_import, check_import_aliases, check_import_entry, checksum_path, complete_keys,
+        create_snapshot, decode_export_value, encode_export_value, enforce_value_limit,
+        entry_field, export_to_path, find_snapshot, handle_add, handle_api_record_upsert,
+        handle_migrate, http_status_for_error, import_entry, import_question, list_snapshots,
+        log_key, parse_created_at, pick_storage_dir, prefix_keys, read_import, removed_values,
+        render_template, resolve_data_file, snapshot_dir, snapshot_path, split_key_list,
+        stream_import, tag_changes, validate_namespace, verify_checksum, watch_changes,
+        write_checksum, AddOptions, ApiWriteRules, AppSettings, CreatedAt, Database, DisplayZone,
+        Entry, EntryValue, ExportFormat, GetField, HttpRequest, ImportDiff, ImportKeyFolder,
+        KeyPolicy, KvError, KvStore, LimitsSettings, OutputStyle, SearchWeights, SortOrder, Store,
+        TagPolicy, Timings, ValueInput,
+    };
+    use crate::store::tests::store_with;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn exit_codes_distinguish_missing_keys_and_bad_input() {
+        assert_eq!(KvError::NotFound("k".into()).exit_code(), 2);
+        assert_eq!(KvError::InvalidInput("bad".into()).exit_code(), 3);
+        let io = KvError::Io(std::io::Error::other("disk"));
+        assert_eq!(io.exit_code(), 1);
+    }
+
+    #[test]
+    fn explicit_config_must_exist_and_parse() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kvstore.toml");
+        assert!(AppSettings::load_explicit(&path).is_err());
+
+        fs::write(&path, "[search]\ndefault_limit = 3\n").unwrap();
+        let settings = AppSettings::load_explicit(&path).unwrap();
+        assert_eq!(settings.search().default_limit(), Some(3));
+
+        fs::write(&path, "[search\n").unwrap();
+        let error = AppSettings::load_explicit(&path).unwrap_err().to_string();
+        assert!(error.contains("kvstore.toml"), "{error}");
+
+        let fallback = AppSettings::load_from(&[dir.path().join("missing.toml")]);
+        assert_eq!(fallback.search().default_limit(), None);
+    }
+
+    #[test]
+    fn search_weights_must_be_finite_and_non_negative() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kvstore.toml");
+        for bad in ["key_weight = -0.5", "tag_weight = nan", "key_weight = inf"] {
+            fs::write(&path, format!("[search]\n{bad}\n")).unwrap();
+            let error = AppSettings::load_explicit(&path).unwrap_err().to_string();
+            assert!(error.contains("finite, non-negative"), "{bad}: {error}");
+            assert!(error.contains("kvstore.toml"), "{error}");
+            let fallback = AppSettings::load_from(std::slice::from_ref(&path));
+            assert_eq!(fallback.search().weights(), SearchWeights::default());
+        }
+
+        fs::write(&path, "[search]\nkey_weight = 0.0\ntag_weight = 2.5\n").unwrap();
+        let weights = AppSettings::load_explicit(&path)
+            .unwrap()
+            .search()
+            .weights();
+        assert_eq!((weights.key, weights.tag), (0.0, 2.5));
+    }
+
+    #[test]
+    fn kvstore_home_is_the_only_config_location() {
+        let home = PathBuf::from("/srv/kv");
+        assert_eq!(
+            AppSettings::candidate_paths(Some(&home)),
+            [home.join("kvstore.toml")]
+        );
+        assert!(AppSettings::candidate_paths(None).contains(&PathBuf::from("kvstore.toml")));
+    }
+
+    #[test]
+    fn storage_prefers_an_existing_legacy_dir_then_xdg_data() {
+        let dir = tempdir().unwrap();
+        let legacy = dir.path().join(".kvstore");
+        let data_dir = dir.path().join("share");
+
+        let xdg = pick_storage_dir(Some(legacy.clone()), Some(data_dir.clone()));
+        assert_eq!(xdg, data_dir.join("kvstore"));
+        assert_eq!(pick_storage_dir(Some(legacy.clone()), None), legacy);
+        assert_eq!(pick_storage_dir(None, None), PathBuf::from(".kvstore"));
+
+        fs::create_dir(&legacy).unwrap();
+        assert_eq!(
+            pick_storage_dir(Some(legacy.clone()), Some(data_dir)),
+            legacy
+        );
+    }
+
+    #[test]
+    fn profile_data_file_sits_between_flag_and_settings() {
+        let settings: AppSettings = toml::from_str(
+            r#"
+            [database]
+            data_file = "/settings.db"
+
+            [profiles.work]
+            data_file = "/work.db"
+            "#,
+        )
+        .unwrap();
+        let work = settings.profile("work").unwrap();
+
+        let flag = resolve_data_file(
+            Some("/flag.db".into()),
+            Some(work),
+            None,
+            &settings,
+            "default",
+        );
+        assert_eq!(flag, PathBuf::from("/flag.db"));
+        let profiled = resolve_data_file(None, Some(work), None, &settings, "default");
+        assert_eq!(profiled, PathBuf::from("/work.db"));
+
+        let error = settings.profile("home").unwrap_err().to_string();
+        assert!(error.contains("configured profiles: work"), "{error}");
+    }
+
+    #[test]
+    fn env_data_file_sits_between_flag_and_database_settings() {
+        let settings: AppSettings =
+            toml::from_str("[database]\ndata_file = \"/settings.db\"\n").unwrap();
+        assert_eq!(settings.database().data_file(), Some("/settings.db"));
+        let env = || Some(PathBuf::from("/env.db"));
+
+        let flag = resolve_data_file(Some("/flag.db".into()), None, env(), &settings, "default");
+        assert_eq!(flag, PathBuf::from("/flag.db"));
+        let from_env = resolve_data_file(None, None, env(), &settings, "default");
+        assert_eq!(from_env, PathBuf::from("/env.db"));
+        let configured = resolve_data_file(None, None, None, &settings, "default");
+        assert_eq!(configured, PathBuf::from("/settings.db"));
+
+        let blank: AppSettings = toml::from_str("[database]\ndata_file = \" \"\n").unwrap();
+        assert_eq!(blank.database().data_file(), None);
+    }
+
+    #[test]
+    fn export_replaces_the_target_without_leaving_temp_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("backup.json");
+        fs::write(&path, "old contents").unwrap();
+        let store =
+            Store::from_entries(vec![("k".to_string(), Entry::new("v".to_string(), vec![]))]);
+
+        export_to_path(
+            &store,
+            &[],
+            &path,
+            SortOrder::Key,
+            DisplayZone::Utc,
+            ExportFormat::Json,
+            false,
+        )
+        .unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.contains("\"k\""), "{written}");
+        let names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["backup.json"]);
+    }
+
+    #[test]
+    fn export_with_keys_writes_only_those_entries() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("subset.jsonl");
+        let store = store_with(&[("a", "a"), ("b", "b"), ("c", "c")]);
+
+        let count = export_to_path(
+            &store,
+            &["c".to_string(), "a".to_string()],
+            &path,
+            SortOrder::Key,
+            DisplayZone::Utc,
+            ExportFormat::Jsonl,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(count, 2);
+        let keys: Vec<String> = read_import(&path, ExportFormat::Jsonl, &Default::default())
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, ["a", "c"]);
+    }
+
+    #[test]
+    fn jsonl_export_writes_lines_in_the_requested_order() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("ordered.jsonl");
+        let store = Store::from_entries(
+            [("c", 1), ("a", 2), ("b", 3)]
+                .map(|(key, seq)| {
+                    let entry = Entry::new(key.to_string(), vec![]).with_seq(seq);
+                    (key.to_string(), entry)
+                })
+                .to_vec(),
+        );
+        let lines = |order| {
+            export_to_path(
+                &store,
+                &[],
+                &path,
+                order,
+                DisplayZone::Utc,
+                ExportFormat::Jsonl,
+                false,
+            )
+            .unwrap();
+            fs::read_to_string(&path)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["key"].clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(lines(SortOrder::Insertion), ["c", "a", "b"]);
+        assert_eq!(lines(SortOrder::Key), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn import_prefix_applies_to_keys_and_alias_targets() {
+        let entries = vec![
+            ("api".to_string(), Entry::new("v".to_string(), vec![])),
+            ("current".to_string(), Entry::alias(None, "api".to_string())),
+        ];
+        let prefixed = prefix_keys(entries, "teamB/");
+        assert_eq!(prefixed[0].0, "teamB/api");
+        assert_eq!(prefixed[1].0, "teamB/current");
+        assert_eq!(prefixed[1].1.alias_of(), Some("teamB/api"));
+    }
+
+    #[test]
+    fn case_insensitive_imports_fold_keys_and_reject_collisions() {
+        let plain = || Entry::new("v".to_string(), vec![]);
+        let policy = KeyPolicy {
+            case_insensitive: true,
+            ..KeyPolicy::default()
+        };
+        let mut folder = ImportKeyFolder::new(&policy, None);
+        let (key, _) = folder.fold("API".to_string(), plain()).unwrap();
+        assert_eq!(key, "api");
+        let (key, entry) = folder
+            .fold("Current".to_string(), Entry::alias(None, "API".to_string()))
+            .unwrap();
+        assert_eq!((key.as_str(), entry.alias_of()), ("current", Some("api")));
+        let error = folder.fold("Api".to_string(), plain()).unwrap_err();
+        assert!(error.to_string().contains("'API' and 'Api'"), "{error}");
+
+        let stored = Store::from_entries(vec![("Legacy".to_string(), plain())]);
+        let mut merge = ImportKeyFolder::new(&policy, Some(stored.folded_keys()));
+        let error = merge.fold("legacy".to_string(), plain()).unwrap_err();
+        assert!(error.to_string().contains("'Legacy'"), "{error}");
+
+        let exact = KeyPolicy::default();
+        let mut folder = ImportKeyFolder::new(&exact, Some(stored.folded_keys()));
+        assert_eq!(
+            folder.fold("Legacy".to_string(), plain()).unwrap().0,
+            "Legacy"
+        );
+    }
+
+    #[test]
+    fn imported_aliases_must_point_at_plain_keys() {
+        let plain = || Entry::new("v".to_string(), vec![]);
+        let alias = |target: &str| Entry::alias(None, target.to_string());
+        let entries = vec![
+            ("api".to_string(), plain()),
+            ("current".to_string(), alias("api")),
+        ];
+        check_import_aliases(&prefix_keys(entries, "teamB/"), None).unwrap();
+
+        let missing = vec![("current".to_string(), alias("api"))];
+        let error = check_import_aliases(&missing, None).unwrap_err();
+        assert!(matches!(error, KvError::InvalidInput(_)), "{error}");
+        let stored = Store::from_entries(vec![("api".to_string(), plain())]);
+        check_import_aliases(&missing, Some(&stored)).unwrap();
+
+        let chained = vec![
+            ("api".to_string(), plain()),
+            ("current".to_string(), alias("api")),
+            ("latest".to_string(), alias("current")),
+        ];
+        let error = check_import_aliases(&chained, None).unwrap_err();
+        assert!(error.to_string().contains("itself an alias"), "{error}");
+    }
+
+    #[test]
+    fn prefixed_import_keeps_keys_that_were_already_stored() {
+        let dir = tempdir().unwrap();
+        let mut kv = KvStore::open(dir.path().join("data.db")).unwrap();
+        kv.add("mine", "kept", Vec::new()).unwrap();
+        kv.add("teamB/api", "old", Vec::new()).unwrap();
+        let entries = prefix_keys(
+            vec![("api".to_string(), Entry::new("new".to_string(), vec![]))],
+            "teamB/",
+        );
+
+        let diff = ImportDiff::between(kv.store(), &entries, true);
+        assert_eq!(diff.removed, 0);
+        assert_eq!(diff.changed, 1);
+        apply_import(&mut kv, entries, true).unwrap();
+
+        let reopened = KvStore::open(dir.path().join("data.db")).unwrap();
+        assert_eq!(reopened.get("mine").unwrap().value(), "kept");
+        assert_eq!(reopened.get("teamB/api").unwrap().value(), "new");
+        assert_eq!(reopened.len(), 2);
+    }
+
+    #[test]
+    fn removed_values_keep_one_line_per_requested_key() {
+        let dir = tempdir().unwrap();
+        let mut kv = KvStore::open(dir.path().join("data.db")).unwrap();
+        kv.add("a", "first", Vec::new()).unwrap();
+        kv.add("c", "third", Vec::new()).unwrap();
+        let keys = ["a", "missing", "c", "a"].map(String::from);
+
+        let removed = kv.remove_many(&keys, true).unwrap();
+        assert_eq!(removed_values(&keys, removed), ["first", "", "third", ""]);
+    }
+
+    #[test]
+    fn import_asks_before_replacing_or_overwriting_stored_entries() {
+        let imported = vec![
+            ("a".to_string(), Entry::new("new".to_string(), vec![])),
+            ("b".to_string(), Entry::new("new".to_string(), vec![])),
+        ];
+        let empty = Store::from_entries(Vec::new());
+        assert_eq!(import_question(&empty, &imported, false), None);
+
+        let store = Store::from_entries(vec![
+            ("a".to_string(), Entry::new("old".to_string(), vec![])),
+            ("c".to_string(), Entry::new("old".to_string(), vec![])),
+            ("d".to_string(), Entry::new("old".to_string(), vec![])),
+        ]);
+        let replace = import_question(&store, &imported, false).unwrap();
+        assert!(
+            replace.contains("replace 3 existing entries with 2 imported"),
+            "{replace}"
+        );
+        let overwrite = import_question(&store, &imported, true).unwrap();
+        assert!(overwrite.contains("overwrite 1 existing"), "{overwrite}");
+        assert_eq!(import_question(&store, &imported[1..], true), None);
+    }
+
+    #[test]
+    fn json_export_metadata_is_skipped_on_import() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("backup.json");
+        let store =
+            Store::from_entries(vec![("k".to_string(), Entry::new("v".to_string(), vec![]))]);
+        export_to_path(
+            &store,
+            &[],
+            &path,
+            SortOrder::Key,
+            DisplayZone::Utc,
+            ExportFormat::Json,
+            false,
+        )
+        .unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["_kvstore_meta"]["entries"], 1);
+        assert_eq!(
+            written["_kvstore_meta"]["version"],
+            env!("CARGO_PKG_VERSION")
+        );
+        let limits = Default::default();
+        let imported = read_import(&path, ExportFormat::Json, &limits).unwrap();
+        assert_eq!(imported.len(), 1);
+
+        // A real entry under the reserved name still imports.
+        fs::write(&path, r#"{"_kvstore_meta": {"value": "mine"}}"#).unwrap();
+        let imported = read_import(&path, ExportFormat::Json, &limits).unwrap();
+        assert_eq!(imported[0].0, "_kvstore_meta");
+    }
+
+    #[test]
+    fn corrupted_export_fails_checksum_verification() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("backup.json");
+        assert!(
+            verify_checksum(&path).is_ok(),
+            "no sidecar means nothing to check"
+        );
+
+        fs::write(&path, "{\"k\": {\"value\": \"v\"}}\n").unwrap();
+        write_checksum(&path, true).unwrap();
+        let sidecar = fs::read_to_string(checksum_path(&path)).unwrap();
+        assert!(sidecar.ends_with("  backup.json\n"), "{sidecar}");
+        verify_checksum(&path).unwrap();
+
+        fs::write(&path, "{\"k\": {\"value\": \"tampered\"}}\n").unwrap();
+        let error = verify_checksum(&path).unwrap_err();
+        assert!(matches!(error, KvError::InvalidInput(_)), "{error}");
+
+        write_checksum(&path, false).unwrap();
+        assert!(!checksum_path(&path).exists());
+    }
+
+    #[test]
+    fn streaming_import_matches_the_buffered_path_and_rolls_back_on_error() {
+        let dir = tempdir().unwrap();
+        let export = dir.path().join("backup.json");
+        let store = Store::from_entries(vec![
+            (
+                "a".to_string(),
+                Entry::new("1".to_string(), vec!["t".into()]),
+            ),
+            ("b".to_string(), Entry::new(vec![0u8, 1], vec![])),
+        ]);
+        let export_as = |path: &PathBuf, format| {
+            export_to_path(
+                &store,
+                &[],
+                path,
+                SortOrder::Key,
+                DisplayZone::Utc,
+                format,
+                false,
+            )
+            .unwrap()
+        };
+        export_as(&export, ExportFormat::Json);
+
+        let limits = LimitsSettings::default();
+        let mut database = Database::connect(dir.path().join("data.db")).unwrap();
+        let count = database
+            .replace_all_streaming(|write| {
+                stream_import(&export, ExportFormat::Json, &mut |key, item| {
+                    let entry = import_entry(&key, item, &limits)?;
+                    write(key, entry)
+                })
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+        let buffered = read_import(&export, ExportFormat::Json, &limits).unwrap();
+        let streamed = database.load_entries().unwrap();
+        assert_eq!(
+            streamed.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            buffered.iter().map(|(key, _)| key).collect::<Vec<_>>()
+        );
+        assert_eq!(streamed[1].1.payload(), &EntryValue::Blob(vec![0, 1]));
+
+        let lines = dir.path().join("backup.jsonl");
+        export_as(&lines, ExportFormat::Jsonl);
+        let error = database
+            .replace_all_streaming(|write| {
+                stream_import(&lines, ExportFormat::Jsonl, &mut |key, item| {
+                    if key == "b" {
+                        return Err(KvError::InvalidInput("rejected b".into()));
+                    }
+                    let entry = import_entry(&key, item, &limits)?;
+                    write(format!("new-{key}"), entry)
+                })
+            })
+            .unwrap_err();
+        assert!(error.to_string().contains("rejected b"), "{error}");
+        let keys: Vec<String> = database
+            .load_entries()
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, ["a", "b"], "the failed import must roll back");
+    }
+
+    #[test]
+    fn compact_export_is_a_single_line_that_still_imports() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("backup.json");
+        let store =
+            Store::from_entries(vec![("k".to_string(), Entry::new("v".to_string(), vec![]))]);
+
+        export_to_path(
+            &store,
+            &[],
+            &path,
+            SortOrder::Key,
+            DisplayZone::Utc,
+            ExportFormat::Json,
+            true,
+        )
+        .unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(written.lines().count(), 1, "{written}");
+        assert!(written.ends_with("}\n"), "{written}");
+        let imported = read_import(&path, ExportFormat::Json, &Default::default()).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].0, "k");
+        assert!(imported[0].1.same_content(store.get("k").unwrap()));
+    }
+
+    #[test]
+    fn jsonl_export_writes_one_record_per_line_and_imports_back() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("backup.jsonl");
+        let store = Store::from_entries(vec![
+            (
+                "a".to_string(),
+                Entry::new("1".to_string(), vec!["t".into()]).with_notes(Some("n".into())),
+            ),
+            ("b".to_string(), Entry::new("2".to_string(), vec![])),
+        ]);
+
+        export_to_path(
+            &store,
+            &[],
+            &path,
+            SortOrder::Key,
+            DisplayZone::Utc,
+            ExportFormat::Jsonl,
+            false,
+        )
+        .unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = written.lines().collect();
+        assert_eq!(lines.len(), 2, "{written}");
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["key"], "a");
+        assert_eq!(first["tags"][0], "t");
+
+        fs::write(&path, format!("\n{}\n\n{}\n", lines[1], lines[0])).unwrap();
+        let limits = Default::default();
+        let imported =
+            Store::from_entries(read_import(&path, ExportFormat::Jsonl, &limits).unwrap());
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported.get("a").unwrap().tags(), ["t"]);
+        assert_eq!(imported.get("a").unwrap().notes(), Some("n"));
+        assert_eq!(imported.get("b").unwrap().notes(), None);
+
+        fs::write(&path, "{\"key\": \"a\", \"value\": \"1\"}\nnot json\n").unwrap();
+        let error = read_import(&path, ExportFormat::Jsonl, &limits)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("backup.jsonl:2:"), "{error}");
+    }
+
+    #[test]
+    fn import_names_every_key_with_a_bad_timestamp() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("backup.json");
+        fs::write(
+            &path,
+            r#"{
+                "fine": {"value": "1", "created_at": "2024-01-02T03:04:05Z"},
+                "broken": {"value": "2", "created_at": "last tuesday"},
+                "expiring": {"value": "3", "expires_at": "2024-13-01T00:00:00Z"}
+            }"#,
+        )
+        .unwrap();
+        let limits = Default::default();
+        let error = read_import(&path, ExportFormat::Json, &limits).unwrap_err();
+        let KvError::InvalidInput(message) = &error else {
+            panic!("expected invalid input, got {error}");
+        };
+        assert!(
+            message.contains("'broken' has created_at 'last tuesday'"),
+            "{message}"
+        );
+        assert!(message.contains("'expiring' has expires_at"), "{message}");
+        assert!(!message.contains("'fine'"), "{message}");
+    }
+
+    #[test]
+    fn import_diff_counts_added_removed_and_changed_keys() {
+        let store = Store::from_entries(vec![
+            ("same".to_string(), Entry::new("1".to_string(), vec![])),
+            ("edited".to_string(), Entry::new("2".to_string(), vec![])),
+            ("dropped".to_string(), Entry::new("3".to_string(), vec![])),
+        ]);
+        let incoming = vec![
+            ("same".to_string(), Entry::new("1".to_string(), vec![])),
+            (
+                "edited".to_string(),
+                Entry::new("2".to_string(), vec!["t".into()]),
+            ),
+            ("new".to_string(), Entry::new("4".to_string(), vec![])),
+        ];
+
+        let diff = ImportDiff::between(&store, &incoming, false);
+        assert_eq!(
+            diff,
+            ImportDiff {
+                added: 1,
+                removed: 1,
+                changed: 1
+            }
+        );
+        assert_eq!(diff.summary(), "+1 -1 ~1 (added/removed/changed)");
+    }
+
+    #[test]
+    fn migrate_merges_both_legacy_formats_and_keeps_existing_keys() {
+        let dir = tempdir().unwrap();
+        let legacy = dir.path().join("legacy.json");
+        fs::write(
+            &legacy,
+            r#"{"old": "plain", "kept": "legacy", "rec": {"value": "v", "tags": [" b ", "a"]}}"#,
+        )
+        .unwrap();
+        let mut database = Database::connect(dir.path().join("data.db")).unwrap();
+        database
+            .upsert_entry("kept", &Entry::new("sqlite".to_string(), vec![]))
+            .unwrap();
+        let mut store = Store::from_entries(database.load_entries().unwrap());
+
+        let report = handle_migrate(
+            &mut database,
+            &mut store,
+            &legacy,
+            &Default::default(),
+            &Default::default(),
+        )
+        .unwrap();
+
+        assert_eq!(report.migrated, 2);
+        assert_eq!(report.skipped, ["kept"]);
+        let stored = Store::from_entries(database.load_entries().unwrap());
+        assert_eq!(stored.get("kept").unwrap().value(), "sqlite");
+        assert_eq!(stored.get("old").unwrap().value(), "plain");
+        assert_eq!(stored.get("rec").unwrap().tags(), ["a", "b"]);
+    }
+
+    #[test]
+    fn add_with_trim_strips_the_trailing_newline() {
+        let dir = tempdir().unwrap();
+        let mut kv = KvStore::open(dir.path().join("data.db")).unwrap();
+        let value = EntryValue::Text("v\n".to_string());
+        let trim = ValueInput {
+            trim: true,
+            ..ValueInput::default()
+        };
+        handle_add(
+            &mut kv,
+            "k",
+            value.clone(),
+            Vec::new(),
+            AddOptions::default(),
+            trim,
+            true,
+        )
+        .unwrap();
+        assert_eq!(kv.get("k").unwrap().value(), "v");
+
+        let raw = ValueInput::default();
+        handle_add(
+            &mut kv,
+            "raw",
+            value,
+            Vec::new(),
+            AddOptions::default(),
+            raw,
+            true,
+        )
+        .unwrap();
+        assert_eq!(kv.get("raw").unwrap().value(), "v\n");
+    }
+
+    #[test]
+    fn add_can_reset_or_set_created_at() {
+        let dir = tempdir().unwrap();
+        let mut kv = KvStore::open(dir.path().join("data.db")).unwrap();
+        let input = ValueInput::default();
+        let add = |kv: &mut KvStore, created| {
+            let options = AddOptions {
+                created,
+                ..AddOptions::default()
+            };
+            let value = EntryValue::Text("v".to_string());
+            handle_add(kv, "k", value, Vec::new(), options, input, true).unwrap();
+            kv.get("k").unwrap().clone()
+        };
+
+        let at = parse_created_at("2020-01-02T03:04:05+02:00").unwrap();
+        assert_eq!(at.to_rfc3339(), "2020-01-02T01:04:05+00:00");
+        assert_eq!(add(&mut kv, CreatedAt::At(at)).created_at(), at);
+        assert_eq!(add(&mut kv, CreatedAt::Keep).created_at(), at);
+        let reset = add(&mut kv, CreatedAt::Reset);
+        assert_eq!(reset.created_at(), reset.updated_at());
+        let reopened = KvStore::open(dir.path().join("data.db")).unwrap();
+        assert_eq!(reopened.get("k").unwrap().created_at(), reset.created_at());
+
+        for bad in ["yesterday", "2999-01-01T00:00:00Z"] {
+            let error = parse_created_at(bad).unwrap_err();
+            assert!(matches!(error, KvError::InvalidInput(_)), "{error}");
+        }
+    }
+
+    #[test]
+    fn get_field_picks_one_entry_field() {
+        let entry = Entry::new("v".to_string(), vec!["a".into(), "b".into()]);
+        assert_eq!(entry_field(&entry, None, false), "v");
+        assert_eq!(entry_field(&entry, Some(GetField::Value), false), "v");
+        assert_eq!(entry_field(&entry, Some(GetField::Tags), false), "a,b");
+        assert_eq!(entry_field(&entry, Some(GetField::Tags), true), "a\0b");
+        assert_eq!(
+            entry_field(&entry, Some(GetField::Created), false),
+            entry.created_at().to_rfc3339()
+        );
+    }
+
+    #[test]
+    fn templates_fill_known_placeholders_and_keep_the_rest() {
+        let entry = Entry::new("v 1".to_string(), vec!["a".into(), "b".into()]);
+        assert_eq!(
+            render_template("{key}\\t{value} [{tags}]\\n", "k", &entry, None),
+            "k\tv 1 [a,b]\n"
+        );
+        assert_eq!(
+            render_template("{score} {nope} {key {} \\x \\\\ {", "k", &entry, None),
+            "{score} {nope} {key {} \\x \\ {"
+        );
+        assert_eq!(
+            render_template("{score}:{key}", "k", &entry, Some(42)),
+            "42:k"
+        );
+        assert_eq!(
+            render_template("{updated}", "k", &entry, None),
+            entry.updated_at().to_rfc3339()
+        );
+    }
+
+    #[test]
+    fn stdin_key_lists_split_on_newlines_or_nul() {
+        assert_eq!(split_key_list("a\r\nb c\n\n", b'\n'), ["a", "b c"]);
+        assert_eq!(split_key_list("a\nb\0c\0", b'\0'), ["a\nb", "c"]);
+        assert!(split_key_list("", b'\n').is_empty());
+    }
+
+    #[test]
+    fn empty_values_need_allow_empty() {
+        let dir = tempdir().unwrap();
+        let mut kv = KvStore::open(dir.path().join("data.db")).unwrap();
+        let blank = || EntryValue::Text(" \n".to_string());
+        let trim = ValueInput {
+            trim: true,
+            allow_empty: false,
+        };
+        let error = handle_add(
+            &mut kv,
+            "k",
+            blank(),
+            Vec::new(),
+            AddOptions::default(),
+            trim,
+            true,
+        )
+        .unwrap_err();
+        assert!(matches!(error, KvError::InvalidInput(_)), "{error}");
+        assert!(kv.is_empty());
+
+        let allowed = ValueInput {
+            allow_empty: true,
+            ..trim
+        };
+        handle_add(
+            &mut kv,
+            "k",
+            blank(),
+            Vec::new(),
+            AddOptions::default(),
+            allowed,
+            true,
+        )
+        .unwrap();
+        assert_eq!(kv.get("k").unwrap().value(), "");
+    }
+
+    #[test]
+    fn empty_value_with_tags_or_for_a_stored_key_is_accepted() {
+        let dir = tempdir().unwrap();
+        let mut kv = KvStore::open(dir.path().join("data.db")).unwrap();
+        let empty = || EntryValue::Text(String::new());
+        let add = |kv: &mut KvStore, key: &str, tags: &[&str]| {
+            let tags = tags.iter().map(|tag| tag.to_string()).collect();
+            let options = AddOptions::default();
+            handle_add(kv, key, empty(), tags, options, ValueInput::default(), true)
+        };
+
+        // `kv foo @prod` on a new key, then on a stored one.
+        add(&mut kv, "foo", &["prod"]).unwrap();
+        assert_eq!(kv.get("foo").unwrap().tags(), ["prod"]);
+        kv.add("bar", "v", Vec::new()).unwrap();
+        add(&mut kv, "bar", &["prod"]).unwrap();
+        assert_eq!(kv.get("bar").unwrap().tags(), ["prod"]);
+        add(&mut kv, "bar", &[]).unwrap();
+
+        assert!(add(&mut kv, "new", &[]).is_err());
+        assert!(kv.get("new").is_err());
+    }
+
+    #[test]
+    fn tag_changes_lists_added_then_removed_tags() {
+        let tags = |list: &[&str]| list.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            tag_changes(&tags(&["python", "cli"]), &tags(&["cli", "rust"])).as_deref(),
+            Some("tags +rust -python")
+        );
+        assert_eq!(tag_changes(&tags(&["a", "b"]), &tags(&["b", "a"])), None);
+    }
+
+    #[test]
+    fn watch_changes_report_new_updated_and_removed_keys() {
+        let entry = |value: &str, updated: &str| {
+            Entry::from_persisted(
+                value.to_string(),
+                "[]",
+                "2024-01-01T00:00:00Z",
+                updated,
+                None,
+            )
+            .unwrap()
+        };
+        let before = BTreeMap::from([
+            ("gone".to_string(), entry("x", "2024-01-01T00:00:00Z")),
+            ("same".to_string(), entry("s", "2024-01-01T00:00:00Z")),
+            ("edited".to_string(), entry("old", "2024-01-01T00:00:00Z")),
+        ]);
+        let after = BTreeMap::from([
+            ("same".to_string(), entry("s", "2024-01-01T00:00:00Z")),
+            ("edited".to_string(), entry("new", "2024-01-02T00:00:00Z")),
+            ("added".to_string(), entry("a", "2024-01-02T00:00:00Z")),
+        ]);
+        assert_eq!(
+            watch_changes(&before, &after, &OutputStyle::plain()),
+            ["+ added: a", "~ edited: new", "- gone"]
+        );
+    }
+
+    #[test]
+    fn redacted_log_keys_show_a_short_digest() {
+        assert_eq!(log_key("secret", false).to_string(), "secret");
+        assert_eq!(log_key("secret", true).to_string(), "<sha256:2bb80d53…>");
+        assert_eq!(log_key("", true).to_string(), "");
+    }
+
+    #[test]
+    fn timing_report_lists_only_measured_phases() {
+        let mut timings = Timings::new(false);
+        assert!(timings.report().starts_with("timing: total "));
+        timings.load = Some(std::time::Duration::from_micros(2500));
+        timings.command_started = Some(std::time::Instant::now());
+        let report = timings.report();
+        assert!(
+            report.starts_with("timing: load 2.5ms, command "),
+            "{report}"
+        );
+        assert!(report.contains(", total "), "{report}");
+    }
+
+    #[test]
+    fn snapshots_are_indexed_and_never_overwritten() {
+        let dir = tempdir().unwrap();
+        let mut database = Database::connect(dir.path().join("data.db")).unwrap();
+        database
+            .upsert_entry("k", &Entry::new("before".to_string(), vec![]))
+            .unwrap();
+        let snapshots = snapshot_dir(&dir.path().join("data.db"));
+
+        create_snapshot(&database, &snapshots, Some("first".into())).unwrap();
+        let again = create_snapshot(&database, &snapshots, Some("first".into()));
+        assert!(matches!(again, Err(KvError::InvalidInput(_))));
+        assert!(create_snapshot(&database, &snapshots, Some("../x".into())).is_err());
+        let named = create_snapshot(&database, &snapshots, None).unwrap();
+
+        let names: Vec<String> = list_snapshots(&snapshots)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.name)
+            .collect();
+        assert_eq!(names, ["first".to_string(), named.name]);
+        assert!(matches!(
+            find_snapshot(&snapshots, "missing"),
+            Err(KvError::NotFound(_))
+        ));
+
+        database
+            .upsert_entry("k", &Entry::new("after".to_string(), vec![]))
+            .unwrap();
+        database
+            .restore_from(&snapshot_path(&snapshots, "first"))
+            .unwrap();
+        assert_eq!(database.get_entry("k").unwrap().unwrap().value(), "before");
+    }
 
     #[test]
     fn validate_namespace_rejects_dot_segments() {
@@ -2668,6 +5771,123 @@ mod tests {
         assert!(validate_namespace("team.alpha_1").is_ok());
     }
 
+    #[test]
+    fn value_limit_allows_values_at_the_boundary() {
+        assert!(enforce_value_limit("key", 4, 4).is_ok());
+        assert!(enforce_value_limit("key", 0, 4).is_ok());
+    }
+
+    #[test]
+    fn value_limit_rejects_values_past_the_boundary() {
+        let error = enforce_value_limit("big", 5, 4).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("'big'"), "unexpected message: {message}");
+        assert!(message.contains("5 bytes"), "unexpected message: {message}");
+    }
+
+    #[test]
+    fn value_limit_of_zero_is_unlimited() {
+        assert!(enforce_value_limit("key", 1 << 20, 0).is_ok());
+    }
+
+    #[test]
+    fn complete_keys_never_creates_the_database() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        assert!(complete_keys(&path, "").unwrap().is_empty());
+        assert!(!path.exists());
+
+        let mut db = Database::connect(&path).unwrap();
+        for key in ["app", "apple", "zeta"] {
+            db.upsert_entry(key, &Entry::new("v".to_string(), vec![]))
+                .unwrap();
+        }
+        drop(db);
+        assert_eq!(complete_keys(&path, "app").unwrap(), ["app", "apple"]);
+    }
+
+    #[test]
+    fn api_upsert_applies_the_value_limit_and_key_policy() {
+        let dir = tempdir().unwrap();
+        let mut database = Database::connect(dir.path().join("data.db")).unwrap();
+        let rules = ApiWriteRules {
+            max_value_bytes: 4,
+            key_policy: KeyPolicy {
+                max_length: 5,
+                ..KeyPolicy::default()
+            },
+            ..ApiWriteRules::default()
+        };
+        let upsert = |database: &mut Database, body: &str| {
+            let request = HttpRequest {
+                method: "POST".to_string(),
+                path: "/api/records/upsert".to_string(),
+                body: body.to_string(),
+            };
+            handle_api_record_upsert(database, &rules, &request)
+        };
+
+        let error = upsert(&mut database, r#"{"key": "k", "value": "too long"}"#).unwrap_err();
+        assert!(matches!(error, KvError::InvalidInput(_)), "{error}");
+        let error = upsert(&mut database, r#"{"key": "longkey", "value": "v"}"#).unwrap_err();
+        assert!(matches!(error, KvError::InvalidInput(_)), "{error}");
+        assert_eq!(database.entry_count().unwrap(), 0);
+        upsert(&mut database, r#"{"key": "k", "value": "ok"}"#).unwrap();
+        assert_eq!(database.entry_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn tag_policy_applies_to_imports_migrations_and_the_api() {
+        let policy = TagPolicy {
+            pattern: Some(regex::Regex::new("^[a-z]+$").unwrap()),
+            lowercase: true,
+            ..TagPolicy::default()
+        };
+        let keys = KeyPolicy::default();
+        let entry = Entry::new("v".to_string(), vec!["Docs".into()]);
+        let checked = check_import_entry("k", entry, &keys, &policy, false).unwrap();
+        assert_eq!(checked.tags(), ["docs"]);
+        let entry = Entry::new("v".to_string(), vec!["two words".into()]);
+        assert!(check_import_entry("k", entry, &keys, &policy, false).is_err());
+
+        let dir = tempdir().unwrap();
+        let legacy = dir.path().join("legacy.json");
+        fs::write(&legacy, r#"{"rec": {"value": "v", "tags": ["not ok"]}}"#).unwrap();
+        let mut database = Database::connect(dir.path().join("data.db")).unwrap();
+        let mut store = Store::from_entries(Vec::new());
+        let limits = LimitsSettings::default();
+        assert!(handle_migrate(&mut database, &mut store, &legacy, &limits, &policy).is_err());
+        assert_eq!(database.entry_count().unwrap(), 0);
+
+        let rules = ApiWriteRules {
+            tag_policy: policy,
+            ..ApiWriteRules::default()
+        };
+        let request = HttpRequest {
+            method: "POST".to_string(),
+            path: "/api/records/upsert".to_string(),
+            body: r#"{"key": "k", "value": "v", "tags": ["Web"]}"#.to_string(),
+        };
+        handle_api_record_upsert(&mut database, &rules, &request).unwrap();
+        let stored = Store::from_entries(database.load_entries().unwrap());
+        assert_eq!(stored.get("k").unwrap().tags(), ["web"]);
+    }
+
+    #[test]
+    fn binary_export_values_round_trip_as_hex() {
+        let value = EntryValue::Blob(vec![0x00, 0x7f, 0xff]);
+        let encoded = encode_export_value(&value);
+        assert_eq!(encoded, "007fff");
+        let decoded = decode_export_value("k", encoded, Some("blob")).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn malformed_hex_is_rejected_on_import() {
+        assert!(decode_export_value("k", "0g".to_string(), Some("blob")).is_err());
+        assert!(decode_export_value("k", "abc".to_string(), Some("blob")).is_err());
+    }
+
     #[test]
     fn http_status_maps_payload_too_large() {
         assert_eq!(
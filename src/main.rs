@@ -1,26 +1,97 @@
+use std::io::Write;
+use std::sync::Mutex;
+
 use kvstore::cli::Cli;
-use kvstore::settings::AppSettings;
+use kvstore::settings::{self, AppSettings, LogFormat};
+use log::{LevelFilter, Log, Metadata, Record};
 
 fn main() {
-    let settings = AppSettings::load();
-    init_logging(&settings);
-
     let cli = Cli::parse();
+    let settings = match AppSettings::resolve(cli.config.as_deref()) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            std::process::exit(error.exit_code());
+        }
+    };
+    init_logging(&settings, cli.verbose);
+
     if let Err(error) = kvstore::run(cli, &settings) {
         eprintln!("Error: {error}");
-        std::process::exit(1);
+        std::process::exit(error.exit_code());
     }
 }
 
-fn init_logging(settings: &AppSettings) {
-    use simplelog::{ConfigBuilder, LevelFilter, WriteLogger};
+/// Installs the file logger and, with `-v`, a stderr logger at Info (`-v`), Debug
+/// (`-vv`), or Trace (`-vvv`).
+fn init_logging(settings: &AppSettings, verbose: u8) {
+    use simplelog::{
+        ColorChoice, CombinedLogger, ConfigBuilder, LevelFilter, SharedLogger, TermLogger,
+        TerminalMode, WriteLogger,
+    };
     use std::env;
+
+    const LOG_LEVEL_ENV: &str = "KVSTORE_LOG_LEVEL";
+
+    let mut config_builder = ConfigBuilder::new();
+    let _ = config_builder.set_time_offset_to_local();
+    let config = config_builder.build();
+
+    let level = env::var(LOG_LEVEL_ENV)
+        .ok()
+        .and_then(|value| match value.to_uppercase().as_str() {
+            "TRACE" => Some(LevelFilter::Trace),
+            "DEBUG" => Some(LevelFilter::Debug),
+            "INFO" => Some(LevelFilter::Info),
+            "WARN" | "WARNING" => Some(LevelFilter::Warn),
+            "ERROR" => Some(LevelFilter::Error),
+            _ => None,
+        })
+        .or_else(|| settings.logging().level_filter())
+        .unwrap_or(LevelFilter::Warn);
+
+    let json = settings.logging().format() == LogFormat::Json;
+    let mut loggers: Vec<Box<dyn SharedLogger>> = Vec::new();
+    if let Some(file) = open_log_file(settings) {
+        if json {
+            loggers.push(JsonLogger::new(level, file));
+        } else {
+            loggers.push(WriteLogger::new(level, config.clone(), file));
+        }
+    }
+    let console_level = match verbose {
+        0 => None,
+        1 => Some(LevelFilter::Info),
+        2 => Some(LevelFilter::Debug),
+        _ => Some(LevelFilter::Trace),
+    };
+    match console_level {
+        Some(console_level) if json => {
+            loggers.push(JsonLogger::new(console_level, std::io::stderr()));
+        }
+        Some(console_level) => loggers.push(TermLogger::new(
+            console_level,
+            config,
+            TerminalMode::Stderr,
+            ColorChoice::Auto,
+        )),
+        None => {}
+    }
+
+    if loggers.is_empty() {
+        return;
+    }
+    if let Err(error) = CombinedLogger::init(loggers) {
+        eprintln!("Failed to initialise logger: {error}");
+    }
+}
+
+fn open_log_file(settings: &AppSettings) -> Option<std::fs::File> {
     use std::fs::{create_dir_all, OpenOptions};
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
 
     const LOG_DIR: &str = "logs";
     const LOG_FILE: &str = "kvstore.log";
-    const LOG_LEVEL_ENV: &str = "KVSTORE_LOG_LEVEL";
 
     let configured_path = settings
         .logging()
@@ -32,11 +103,16 @@ fn init_logging(settings: &AppSettings) {
     let log_path = if Path::new(configured_path).is_absolute() {
         configured_path.to_string()
     } else {
-        if let Err(error) = create_dir_all(LOG_DIR) {
-            eprintln!("Failed to create log directory '{LOG_DIR}': {error}");
-            return;
+        let log_dir =
+            settings::home_dir().map_or_else(|| PathBuf::from(LOG_DIR), |home| home.join(LOG_DIR));
+        if let Err(error) = create_dir_all(&log_dir) {
+            eprintln!(
+                "Failed to create log directory '{}': {error}",
+                log_dir.display()
+            );
+            return None;
         }
-        format!("{LOG_DIR}/{configured_path}")
+        log_dir.join(configured_path).to_string_lossy().into_owned()
     };
 
     if let Some(parent) = Path::new(&log_path).parent() {
@@ -45,36 +121,141 @@ fn init_logging(settings: &AppSettings) {
                 "Failed to create log directory '{}': {error}",
                 parent.display()
             );
-            return;
+            return None;
         }
     }
 
-    let file = match OpenOptions::new().create(true).append(true).open(&log_path) {
-        Ok(file) => file,
+    if let Some(max_bytes) = settings.logging().max_size_bytes() {
+        let keep = settings.logging().keep_files();
+        if let Err(error) = rotate_log(Path::new(&log_path), max_bytes, keep) {
+            eprintln!("Failed to rotate log file '{log_path}': {error}");
+        }
+    }
+
+    match OpenOptions::new().create(true).append(true).open(&log_path) {
+        Ok(file) => Some(file),
         Err(error) => {
             eprintln!("Failed to open log file '{log_path}': {error}");
-            return;
+            None
         }
+    }
+}
+
+/// Shifts `path` to `path.1`, `path.1` to `path.2`, ... once it reaches `max_bytes`,
+/// deleting rotated files numbered above `keep`.
+fn rotate_log(path: &std::path::Path, max_bytes: u64, keep: usize) -> std::io::Result<()> {
+    use std::fs;
+    use std::io::ErrorKind;
+
+    let rotated = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{n}"));
+        std::path::PathBuf::from(name)
     };
 
-    let mut config_builder = ConfigBuilder::new();
-    let _ = config_builder.set_time_offset_to_local();
-    let config = config_builder.build();
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.len() >= max_bytes => {}
+        Ok(_) => return Ok(()),
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    }
 
-    let level = env::var(LOG_LEVEL_ENV)
-        .ok()
-        .and_then(|value| match value.to_uppercase().as_str() {
-            "TRACE" => Some(LevelFilter::Trace),
-            "DEBUG" => Some(LevelFilter::Debug),
-            "INFO" => Some(LevelFilter::Info),
-            "WARN" | "WARNING" => Some(LevelFilter::Warn),
-            "ERROR" => Some(LevelFilter::Error),
-            _ => None,
+    // Also clears files left over from a larger `keep_files`.
+    let mut n = keep.max(1);
+    while rotated(n).exists() {
+        fs::remove_file(rotated(n))?;
+        n += 1;
+    }
+    if keep == 0 {
+        return fs::remove_file(path);
+    }
+    for n in (1..keep).rev() {
+        if rotated(n).exists() {
+            fs::rename(rotated(n), rotated(n + 1))?;
+        }
+    }
+    fs::rename(path, rotated(1))
+}
+
+/// Writes each record as one line of JSON for `[logging] format = "json"`.
+struct JsonLogger<W: Write + Send> {
+    level: LevelFilter,
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send + 'static> JsonLogger<W> {
+    fn new(level: LevelFilter, writer: W) -> Box<Self> {
+        Box::new(Self {
+            level,
+            writer: Mutex::new(writer),
         })
-        .or_else(|| settings.logging().level_filter())
-        .unwrap_or(LevelFilter::Warn);
+    }
+}
+
+impl<W: Write + Send> Log for JsonLogger<W> {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = serde_json::json!({
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "level": record.level().as_str(),
+            "message": record.args().to_string(),
+            "module": record.module_path(),
+        });
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writeln!(writer, "{line}");
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writer.flush();
+        }
+    }
+}
+
+impl<W: Write + Send + 'static> simplelog::SharedLogger for JsonLogger<W> {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&simplelog::Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rotate_log;
+    use std::fs;
+    use tempfile::tempdir;
 
-    if let Err(error) = WriteLogger::init(level, config, file) {
-        eprintln!("Failed to initialise file logger: {error}");
+    #[test]
+    fn rotation_shifts_files_and_drops_the_oldest() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("kvstore.log");
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).ok();
+
+        fs::write(&log, "small").unwrap();
+        rotate_log(&log, 10, 2).unwrap();
+        assert_eq!(read("kvstore.log").as_deref(), Some("small"));
+
+        for generation in ["first log", "second log", "third log"] {
+            fs::write(&log, generation).unwrap();
+            rotate_log(&log, 5, 2).unwrap();
+        }
+        assert_eq!(read("kvstore.log"), None);
+        assert_eq!(read("kvstore.log.1").as_deref(), Some("third log"));
+        assert_eq!(read("kvstore.log.2").as_deref(), Some("second log"));
+        assert_eq!(read("kvstore.log.3"), None);
     }
 }
@@ -0,0 +1,493 @@
+use std::borrow::Cow;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use crossterm::terminal;
+use log::{debug, warn};
+
+// Each attribute is switched off individually rather than with a full reset so
+// styles can nest (e.g. a bold key inside a reverse-video selected row).
+const BOLD: (&str, &str) = ("\x1b[1m", "\x1b[22m");
+const DIM: (&str, &str) = ("\x1b[2m", "\x1b[22m");
+const GREEN: (&str, &str) = ("\x1b[32m", "\x1b[39m");
+const REVERSE: (&str, &str) = ("\x1b[7m", "\x1b[27m");
+const UNDERLINE: &str = "\x1b[4m";
+const NO_UNDERLINE: &str = "\x1b[24m";
+const NO_COLOR_ENV: &str = "NO_COLOR";
+const PAGER_ENV: &str = "PAGER";
+const DEFAULT_PAGER: &str = "less";
+/// `less` options; `R` passes our color escapes through instead of showing them raw.
+const LESS_ENV: &str = "LESS";
+const DEFAULT_LESS: &str = "FRX";
+
+/// User preference for colored terminal output (`--color`).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color only when writing to a terminal and `NO_COLOR` is unset.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Decides whether and how CLI output is decorated with ANSI escapes.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct OutputStyle {
+    color: bool,
+}
+
+impl OutputStyle {
+    /// Style that never emits escape codes.
+    pub fn plain() -> Self {
+        Self { color: false }
+    }
+
+    /// Resolves the style for stdout based on the user's choice.
+    pub fn for_stdout(choice: ColorChoice) -> Self {
+        Self::resolve(choice, io::stdout().is_terminal())
+    }
+
+    /// Resolves the style for stderr based on the user's choice.
+    pub fn for_stderr(choice: ColorChoice) -> Self {
+        Self::resolve(choice, io::stderr().is_terminal())
+    }
+
+    fn resolve(choice: ColorChoice, is_terminal: bool) -> Self {
+        let color = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal && !no_color_requested(),
+        };
+        Self { color }
+    }
+
+    pub fn is_colored(&self) -> bool {
+        self.color
+    }
+
+    pub fn key<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.paint(BOLD, text)
+    }
+
+    pub fn value<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.paint(GREEN, text)
+    }
+
+    pub fn tags<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.paint(DIM, text)
+    }
+
+    /// Marks the currently selected row in interactive mode.
+    pub fn selected<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.paint(REVERSE, text)
+    }
+
+    /// Underlines the characters at the given (ascending) char positions.
+    pub fn highlight<'a>(&self, text: &'a str, indices: &[usize]) -> Cow<'a, str> {
+        if !self.color || indices.is_empty() {
+            return Cow::Borrowed(text);
+        }
+
+        let mut positions = indices.iter().peekable();
+        let mut output = String::with_capacity(text.len() + indices.len() * 9);
+        for (idx, c) in text.chars().enumerate() {
+            if positions.next_if(|&&position| position == idx).is_some() {
+                output.push_str(UNDERLINE);
+                output.push(c);
+                output.push_str(NO_UNDERLINE);
+            } else {
+                output.push(c);
+            }
+        }
+        Cow::Owned(output)
+    }
+
+    fn paint<'a>(&self, (on, off): (&str, &str), text: &'a str) -> Cow<'a, str> {
+        if self.color && !text.is_empty() {
+            Cow::Owned(format!("{on}{text}{off}"))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+}
+
+/// How timestamps are printed (`[display] time_format`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    #[default]
+    Rfc3339,
+    /// Humanized distance from now, e.g. "3 hours ago".
+    Relative,
+    /// A validated strftime pattern.
+    Strftime(String),
+}
+
+impl TimeFormat {
+    /// Parses `rfc3339`, `relative`, or a strftime pattern. Invalid patterns fall
+    /// back to RFC 3339 with a logged warning.
+    pub fn parse(raw: &str) -> Self {
+        match raw.trim() {
+            "" | "rfc3339" => TimeFormat::Rfc3339,
+            "relative" => TimeFormat::Relative,
+            pattern if StrftimeItems::new(pattern).any(|item| item == Item::Error) => {
+                warn!("invalid time_format '{pattern}'; using rfc3339");
+                TimeFormat::Rfc3339
+            }
+            pattern => TimeFormat::Strftime(pattern.to_string()),
+        }
+    }
+
+    pub fn format(&self, at: DateTime<Utc>, zone: DisplayZone) -> String {
+        self.format_at(at, zone, Utc::now())
+    }
+
+    fn format_at(&self, at: DateTime<Utc>, zone: DisplayZone, now: DateTime<Utc>) -> String {
+        match self {
+            TimeFormat::Rfc3339 => zone.rfc3339(at),
+            TimeFormat::Relative => humanize(now.signed_duration_since(at).num_seconds()),
+            TimeFormat::Strftime(pattern) => zone.convert(at).format(pattern).to_string(),
+        }
+    }
+}
+
+/// Zone timestamps are shown in (`[display] timezone`); storage is always UTC.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum DisplayZone {
+    #[default]
+    Utc,
+    /// The system's local zone.
+    Local,
+    /// An IANA zone such as `Europe/Berlin`; requires the `timezones` feature.
+    #[cfg(feature = "timezones")]
+    Named(chrono_tz::Tz),
+}
+
+impl DisplayZone {
+    /// Parses `utc`, `local`, or an IANA name. Unknown names fall back to UTC with a
+    /// logged warning.
+    pub fn parse(raw: &str) -> Self {
+        match raw.trim() {
+            "" => DisplayZone::Utc,
+            name if name.eq_ignore_ascii_case("utc") => DisplayZone::Utc,
+            name if name.eq_ignore_ascii_case("local") => DisplayZone::Local,
+            #[cfg(feature = "timezones")]
+            name => match name.parse::<chrono_tz::Tz>() {
+                Ok(tz) => DisplayZone::Named(tz),
+                Err(_) => {
+                    warn!("unknown timezone '{name}'; using utc");
+                    DisplayZone::Utc
+                }
+            },
+            #[cfg(not(feature = "timezones"))]
+            name => {
+                warn!("timezone '{name}' needs the `timezones` feature; using utc");
+                DisplayZone::Utc
+            }
+        }
+    }
+
+    /// RFC 3339 rendering in this zone, e.g. for exports.
+    pub fn rfc3339(self, at: DateTime<Utc>) -> String {
+        self.convert(at).to_rfc3339()
+    }
+
+    fn convert(self, at: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            DisplayZone::Utc => at.fixed_offset(),
+            DisplayZone::Local => at.with_timezone(&Local).fixed_offset(),
+            #[cfg(feature = "timezones")]
+            DisplayZone::Named(tz) => at.with_timezone(&tz).fixed_offset(),
+        }
+    }
+}
+
+/// "5 minutes ago" for positive `seconds`, "in 5 minutes" for negative ones.
+fn humanize(seconds: i64) -> String {
+    const UNITS: [(i64, &str); 6] = [
+        (365 * 24 * 3600, "year"),
+        (30 * 24 * 3600, "month"),
+        (24 * 3600, "day"),
+        (3600, "hour"),
+        (60, "minute"),
+        (1, "second"),
+    ];
+    let magnitude = seconds.abs();
+    if magnitude < 1 {
+        return "just now".to_string();
+    }
+    let (size, unit) = UNITS
+        .iter()
+        .copied()
+        .find(|(size, _)| magnitude >= *size)
+        .unwrap_or((1, "second"));
+    let count = magnitude / size;
+    let plural = if count == 1 { "" } else { "s" };
+    if seconds > 0 {
+        format!("{count} {unit}{plural} ago")
+    } else {
+        format!("in {count} {unit}{plural}")
+    }
+}
+
+/// Lays out `rows` under `headers` as an ASCII table with `+---+` rules, sizing each
+/// column to its widest cell. Whitespace runs collapse to one space so every row stays
+/// on one line, and a cell longer than its column's entry in `max_widths` chars is cut
+/// with an ellipsis (`0`, or no entry, keeps it whole).
+pub fn table_lines(headers: &[&str], rows: &[Vec<String>], max_widths: &[usize]) -> Vec<String> {
+    let cell = |column: usize, text: &str| {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        match max_widths.get(column) {
+            Some(&max) if max > 0 => truncate_chars(&text, max),
+            _ => text,
+        }
+    };
+    let rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(column, text)| cell(column, text))
+                .collect()
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect();
+    for row in &rows {
+        for (width, text) in widths.iter_mut().zip(row) {
+            *width = (*width).max(text.chars().count());
+        }
+    }
+
+    let rule = widths.iter().fold(String::from("+"), |mut rule, width| {
+        rule.push_str(&"-".repeat(width + 2));
+        rule.push('+');
+        rule
+    });
+    let line = |cells: &mut dyn Iterator<Item = &str>| {
+        widths
+            .iter()
+            .zip(cells)
+            .fold(String::from("|"), |mut line, (width, text)| {
+                let pad = width - text.chars().count();
+                line.push_str(&format!(" {text}{} |", " ".repeat(pad)));
+                line
+            })
+    };
+
+    let mut lines = Vec::with_capacity(rows.len() + 4);
+    lines.push(rule.clone());
+    lines.push(line(&mut headers.iter().copied()));
+    lines.push(rule.clone());
+    for row in &rows {
+        lines.push(line(&mut row.iter().map(String::as_str)));
+    }
+    lines.push(rule);
+    lines
+}
+
+/// Keeps the first `max` chars of `text`, replacing the last with `…` when it is cut.
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        None => text.to_string(),
+        Some(_) => {
+            let keep = max.saturating_sub(1);
+            let end = text
+                .char_indices()
+                .nth(keep)
+                .map_or(text.len(), |(at, _)| at);
+            format!("{}…", &text[..end])
+        }
+    }
+}
+
+/// Prints `lines` to stdout, through a pager when stdout is a terminal and either
+/// `explicit` is `Some(true)` or, by default, the lines do not fit on the screen.
+/// A pager that cannot be started is skipped and the lines are printed directly.
+pub fn page_lines(lines: &[String], explicit: Option<bool>) -> io::Result<()> {
+    let rows = terminal::size().map_or(usize::MAX, |(_, height)| height as usize);
+    if !should_page(explicit, io::stdout().is_terminal(), lines.len(), rows) {
+        return write_lines(&mut io::stdout().lock(), lines);
+    }
+
+    let configured = env::var(PAGER_ENV).ok();
+    let Some((program, args)) = pager_command(configured.as_deref()) else {
+        return write_lines(&mut io::stdout().lock(), lines);
+    };
+    let mut command = Command::new(program);
+    command.args(args).stdin(Stdio::piped());
+    if env::var_os(LESS_ENV).is_none() {
+        command.env(LESS_ENV, DEFAULT_LESS);
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(error) => {
+            debug!("pager '{program}' unavailable ({error}); writing directly");
+            return write_lines(&mut io::stdout().lock(), lines);
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        write_lines(&mut stdin, lines)?;
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Whether `lines` lines go through the pager given the `--pager`/`--no-pager` choice.
+/// One row is left for the shell prompt when deciding whether the output fits.
+fn should_page(explicit: Option<bool>, is_terminal: bool, lines: usize, rows: usize) -> bool {
+    is_terminal
+        && match explicit {
+            Some(choice) => choice,
+            None => lines >= rows,
+        }
+}
+
+/// Program and arguments from `$PAGER` (split on whitespace), or `less` when unset.
+/// An empty `$PAGER` or `cat` disables paging.
+fn pager_command(configured: Option<&str>) -> Option<(&str, Vec<&str>)> {
+    let mut words = configured.unwrap_or(DEFAULT_PAGER).split_whitespace();
+    let program = words.next()?;
+    (program != "cat").then(|| (program, words.collect()))
+}
+
+/// Writes `lines`, stopping quietly once the reader (a pager the user quit, or
+/// `head`) closes the pipe.
+fn write_lines(out: &mut impl Write, lines: &[String]) -> io::Result<()> {
+    let result = lines
+        .iter()
+        .try_for_each(|line| writeln!(out, "{line}"))
+        .and_then(|()| out.flush());
+    match result {
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => other,
+    }
+}
+
+/// Honors https://no-color.org: any non-empty value disables color.
+fn no_color_requested() -> bool {
+    env::var_os(NO_COLOR_ENV).is_some_and(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        pager_command, should_page, table_lines, ColorChoice, DisplayZone, OutputStyle, TimeFormat,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn never_and_non_terminal_output_contain_no_escapes() {
+        let never = OutputStyle::resolve(ColorChoice::Never, true);
+        let piped = OutputStyle::resolve(ColorChoice::Auto, false);
+        for style in [never, piped] {
+            assert!(!style.is_colored());
+            assert_eq!(style.key("foo"), "foo");
+            assert_eq!(style.tags("[tags: a]"), "[tags: a]");
+        }
+    }
+
+    #[test]
+    fn always_wraps_parts_in_escapes() {
+        let style = OutputStyle::resolve(ColorChoice::Always, false);
+        assert_eq!(style.key("foo"), "\x1b[1mfoo\x1b[22m");
+        assert_eq!(style.value(""), "");
+    }
+
+    #[test]
+    fn highlight_underlines_matched_chars_only_when_colored() {
+        let colored = OutputStyle::resolve(ColorChoice::Always, false);
+        assert_eq!(
+            colored.highlight("héllo", &[1, 3]),
+            "h\x1b[4mé\x1b[24ml\x1b[4ml\x1b[24mo"
+        );
+        assert_eq!(OutputStyle::plain().highlight("hello", &[1, 3]), "hello");
+    }
+
+    #[test]
+    fn table_aligns_columns_and_cuts_long_values_on_char_boundaries() {
+        let rows = vec![
+            vec![
+                "café".to_string(),
+                "naïve\nvalue".to_string(),
+                "ab".to_string(),
+            ],
+            vec!["k".to_string(), "ok".to_string(), String::new()],
+        ];
+        let lines = table_lines(&["KEY", "VALUE", "TAGS"], &rows, &[0, 5]);
+        assert_eq!(
+            lines,
+            [
+                "+------+-------+------+",
+                "| KEY  | VALUE | TAGS |",
+                "+------+-------+------+",
+                "| café | naïv… | ab   |",
+                "| k    | ok    |      |",
+                "+------+-------+------+",
+            ]
+        );
+        let whole = table_lines(&["KEY", "VALUE", "TAGS"], &rows, &[]);
+        assert_eq!(whole[3], "| café | naïve value | ab   |");
+    }
+
+    #[test]
+    fn pager_runs_only_for_terminals_and_overflowing_output() {
+        assert!(should_page(None, true, 40, 24));
+        assert!(!should_page(None, true, 10, 24));
+        assert!(should_page(Some(true), true, 1, 24));
+        assert!(!should_page(Some(false), true, 100, 24));
+        assert!(!should_page(Some(true), false, 100, 24));
+
+        assert_eq!(pager_command(None), Some(("less", vec![])));
+        assert_eq!(pager_command(Some("most -s")), Some(("most", vec!["-s"])));
+        assert_eq!(pager_command(Some("  ")), None);
+        assert_eq!(pager_command(Some("cat")), None);
+    }
+
+    #[test]
+    fn time_formats_parse_and_fall_back_to_rfc3339() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 5, 14, 7, 0).unwrap();
+        let utc = DisplayZone::Utc;
+        assert_eq!(
+            TimeFormat::parse("").format(at, utc),
+            "2024-03-05T14:07:00+00:00"
+        );
+        assert_eq!(
+            TimeFormat::parse("%d.%m.%Y %H:%M").format(at, utc),
+            "05.03.2024 14:07"
+        );
+        assert_eq!(TimeFormat::parse("%Q broken"), TimeFormat::Rfc3339);
+
+        let relative = TimeFormat::parse("relative");
+        assert_eq!(
+            relative.format_at(at, utc, at + Duration::hours(3)),
+            "3 hours ago"
+        );
+        assert_eq!(
+            relative.format_at(at, utc, at - Duration::minutes(1)),
+            "in 1 minute"
+        );
+        assert_eq!(relative.format_at(at, utc, at), "just now");
+    }
+
+    #[test]
+    fn display_zone_defaults_to_utc_for_unknown_names() {
+        assert_eq!(DisplayZone::parse("UTC"), DisplayZone::Utc);
+        assert_eq!(DisplayZone::parse("local"), DisplayZone::Local);
+        assert_eq!(DisplayZone::parse("Not/AZone"), DisplayZone::Utc);
+    }
+
+    #[cfg(feature = "timezones")]
+    #[test]
+    fn named_zones_shift_the_rendered_offset() {
+        let at = Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap();
+        let berlin = DisplayZone::parse("Europe/Berlin");
+        assert_eq!(berlin.rfc3339(at), "2024-07-01T14:00:00+02:00");
+    }
+}
@@ -1,11 +1,30 @@
+use std::collections::BTreeMap;
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use directories::BaseDirs;
 use log::LevelFilter;
+use regex::Regex;
 use serde::Deserialize;
 
+use crate::interactive::Keybindings;
+use crate::output::{DisplayZone, TimeFormat};
+use crate::store::{KeyPolicy, SearchAlgorithm, SearchScope, SearchWeights, TagPolicy};
 use crate::{KvError, KvResult};
 
+/// Names a directory that holds all kvstore state: the default data files and recent
+/// logs, the `logs/` directory, and the only config file looked up by default.
+pub const HOME_ENV: &str = "KVSTORE_HOME";
+
+/// The directory from `KVSTORE_HOME`, when it is set and non-empty.
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os(HOME_ENV)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
 /// Represents the application configuration loaded from disk.
 #[derive(Debug, Default, Deserialize)]
 pub struct AppSettings {
@@ -13,19 +32,62 @@ pub struct AppSettings {
     logging: LoggingSettings,
     #[serde(default)]
     history: HistorySettings,
+    #[serde(default)]
+    limits: LimitsSettings,
+    #[serde(default)]
+    database: DatabaseSettings,
+    #[serde(default)]
+    search: SearchSettings,
+    #[serde(default)]
+    interactive: InteractiveSettings,
+    #[serde(default)]
+    profiles: BTreeMap<String, ProfileSettings>,
+    #[serde(default)]
+    display: DisplaySettings,
+    #[serde(default)]
+    tags: TagSettings,
+    #[serde(default)]
+    input: InputSettings,
+    #[serde(default)]
+    keys: KeySettings,
 }
 
 impl AppSettings {
+    /// Working-directory locations, kept as a fallback after the XDG config path.
     const DEFAULT_PATHS: [&'static str; 2] = ["kvstore.toml", "config/kvstore.toml"];
+    const CONFIG_DIR_NAME: &'static str = "kvstore";
+    const CONFIG_FILE_NAME: &'static str = "kvstore.toml";
+    const CONFIG_ENV: &'static str = "KVSTORE_CONFIG";
+
+    /// Loads settings from `--config`, else `KVSTORE_CONFIG`, else the default
+    /// locations. An explicitly named file must exist and parse.
+    pub fn resolve(explicit: Option<&Path>) -> KvResult<Self> {
+        if let Some(path) = explicit {
+            return Self::load_explicit(path);
+        }
+        match env::var_os(Self::CONFIG_ENV).filter(|value| !value.is_empty()) {
+            Some(path) => Self::load_explicit(Path::new(&path)),
+            None => Ok(Self::load()),
+        }
+    }
 
     /// Attempts to load settings from the default locations, falling back to defaults.
     pub fn load() -> Self {
-        for path in Self::DEFAULT_PATHS {
-            if Path::new(path).exists() {
+        Self::load_from(&Self::candidate_paths(home_dir().as_deref()))
+    }
+
+    /// Loads the first of `paths` that exists; unparsable files are reported and
+    /// skipped, and defaults are used when none load.
+    pub fn load_from(paths: &[PathBuf]) -> Self {
+        for path in paths {
+            if path.exists() {
                 match Self::load_from_path(path) {
                     Ok(settings) => return settings,
                     Err(error) => {
-                        eprintln!("Failed to parse settings from '{path}': {error}");
+                        eprintln!(
+                            "Failed to parse settings from '{}': {error}",
+                            path.display()
+                        );
                     }
                 }
             }
@@ -33,10 +95,44 @@ impl AppSettings {
         AppSettings::default()
     }
 
-    fn load_from_path(path: &str) -> KvResult<Self> {
+    /// `$XDG_CONFIG_HOME/kvstore/kvstore.toml` first, then the working-directory paths.
+    /// Under `KVSTORE_HOME`, only `$KVSTORE_HOME/kvstore.toml`.
+    pub(crate) fn candidate_paths(home: Option<&Path>) -> Vec<PathBuf> {
+        if let Some(home) = home {
+            return vec![home.join(Self::CONFIG_FILE_NAME)];
+        }
+        let mut paths = Vec::with_capacity(Self::DEFAULT_PATHS.len() + 1);
+        if let Some(dirs) = BaseDirs::new() {
+            paths.push(
+                dirs.config_dir()
+                    .join(Self::CONFIG_DIR_NAME)
+                    .join(Self::CONFIG_FILE_NAME),
+            );
+        }
+        paths.extend(Self::DEFAULT_PATHS.iter().map(PathBuf::from));
+        paths
+    }
+
+    /// Loads `path` only, with no fallback: a missing or malformed file is an error.
+    pub fn load_explicit(path: &Path) -> KvResult<Self> {
+        Self::load_from_path(path).map_err(|error| match error {
+            KvError::ConfigFormat(error) => KvError::InvalidInput(format!(
+                "invalid settings file '{}': {error}",
+                path.display()
+            )),
+            KvError::InvalidInput(message) => KvError::InvalidInput(format!(
+                "invalid settings file '{}': {message}",
+                path.display()
+            )),
+            other => other,
+        })
+    }
+
+    fn load_from_path(path: &Path) -> KvResult<Self> {
         let data = fs::read_to_string(path)
             .map_err(|error| KvError::io_path("reading settings file", path, error))?;
         let settings = toml::from_str::<AppSettings>(&data)?;
+        settings.search.check_weights()?;
         Ok(settings)
     }
 
@@ -49,13 +145,87 @@ impl AppSettings {
     pub fn history(&self) -> &HistorySettings {
         &self.history
     }
+
+    /// Returns an immutable reference to the size limit configuration.
+    pub fn limits(&self) -> &LimitsSettings {
+        &self.limits
+    }
+
+    /// Returns an immutable reference to the database configuration.
+    pub fn database(&self) -> &DatabaseSettings {
+        &self.database
+    }
+
+    /// Returns an immutable reference to the search configuration.
+    pub fn search(&self) -> &SearchSettings {
+        &self.search
+    }
+
+    /// Returns an immutable reference to the interactive mode configuration.
+    pub fn interactive(&self) -> &InteractiveSettings {
+        &self.interactive
+    }
+
+    /// Returns an immutable reference to the display configuration.
+    pub fn display(&self) -> &DisplaySettings {
+        &self.display
+    }
+
+    /// Returns an immutable reference to the tag rules.
+    pub fn tags(&self) -> &TagSettings {
+        &self.tags
+    }
+
+    /// Returns an immutable reference to the value input rules.
+    pub fn input(&self) -> &InputSettings {
+        &self.input
+    }
+
+    /// Returns an immutable reference to the key naming rules.
+    pub fn keys(&self) -> &KeySettings {
+        &self.keys
+    }
+
+    /// Looks up a named profile from the `[profiles.<name>]` tables.
+    pub fn profile(&self, name: &str) -> KvResult<&ProfileSettings> {
+        self.profiles.get(name).ok_or_else(|| {
+            let known = self.profiles.keys().cloned().collect::<Vec<_>>();
+            let hint = if known.is_empty() {
+                "no profiles are configured".to_string()
+            } else {
+                format!("configured profiles: {}", known.join(", "))
+            };
+            KvError::InvalidInput(format!("unknown profile '{name}' ({hint})"))
+        })
+    }
 }
 
 /// Logging related settings parsed from the configuration file.
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Deserialize)]
 pub struct LoggingSettings {
     pub level: Option<String>,
     pub file: Option<String>,
+    #[serde(default)]
+    format: LogFormat,
+    #[serde(default)]
+    max_size_mb: u64,
+    #[serde(default = "LoggingSettings::default_keep_files")]
+    keep_files: usize,
+    #[serde(default)]
+    redact_keys: bool,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: None,
+            file: None,
+            format: LogFormat::default(),
+            max_size_mb: 0,
+            keep_files: Self::default_keep_files(),
+            redact_keys: false,
+        }
+    }
 }
 
 impl LoggingSettings {
@@ -63,6 +233,41 @@ impl LoggingSettings {
     pub fn level_filter(&self) -> Option<LevelFilter> {
         self.level.as_ref().and_then(|raw| parse_level(raw))
     }
+
+    /// Record layout for the log file and the `-v` stderr mirror.
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    const fn default_keep_files() -> usize {
+        5
+    }
+
+    /// Size in bytes past which the log file is rotated at startup; `None` never rotates.
+    pub fn max_size_bytes(&self) -> Option<u64> {
+        (self.max_size_mb > 0).then(|| self.max_size_mb.saturating_mul(1024 * 1024))
+    }
+
+    /// Rotated files (`kvstore.log.1` ...) kept alongside the live log.
+    pub fn keep_files(&self) -> usize {
+        self.keep_files
+    }
+
+    /// Whether keys in log messages are replaced by digests.
+    pub fn redact_keys(&self) -> bool {
+        self.redact_keys
+    }
+}
+
+/// Log record layout (`[logging] format`).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// simplelog's human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line with `timestamp`, `level`, `message`, and `module`.
+    Json,
 }
 
 /// Controls how the recent activity log behaves.
@@ -98,6 +303,281 @@ impl HistorySettings {
     }
 }
 
+/// Guards against accidentally storing oversized values.
+#[derive(Debug, Default, Deserialize)]
+pub struct LimitsSettings {
+    #[serde(default)]
+    max_value_bytes: usize,
+}
+
+impl LimitsSettings {
+    /// Maximum value size in bytes; `0` means unlimited.
+    pub fn max_value_bytes(&self) -> usize {
+        self.max_value_bytes
+    }
+}
+
+/// How values given on `add` are cleaned up before storing.
+#[derive(Debug, Default, Deserialize)]
+pub struct InputSettings {
+    #[serde(default)]
+    trim: bool,
+}
+
+impl InputSettings {
+    /// Whether `add` and `put-file` trim surrounding whitespace when neither `--trim` nor
+    /// `--no-trim` is passed.
+    pub fn trim(&self) -> bool {
+        self.trim
+    }
+}
+
+/// Database location overrides and write checks.
+#[derive(Debug, Default, Deserialize)]
+pub struct DatabaseSettings {
+    data_file: Option<String>,
+    #[serde(default)]
+    verify_on_write: bool,
+}
+
+impl DatabaseSettings {
+    /// Optional data file path used when neither `--data-file` nor `KVSTORE_DATA_FILE` is set.
+    pub fn data_file(&self) -> Option<&str> {
+        self.data_file
+            .as_deref()
+            .filter(|path| !path.trim().is_empty())
+    }
+
+    /// Whether writes compare each affected row with the cached entry instead of
+    /// failing whenever the file changed since it was loaded.
+    pub fn verify_on_write(&self) -> bool {
+        self.verify_on_write
+    }
+}
+
+/// Consistency rules for tags given on `add`.
+#[derive(Debug, Default, Deserialize)]
+pub struct TagSettings {
+    pattern: Option<String>,
+    #[serde(default)]
+    lowercase: bool,
+    #[serde(default)]
+    fold_case: bool,
+}
+
+impl TagSettings {
+    /// Compiles the rules; an invalid `pattern` is a configuration error.
+    pub fn policy(&self) -> KvResult<TagPolicy> {
+        let pattern = self
+            .pattern
+            .as_deref()
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|error| {
+                    KvError::InvalidInput(format!("invalid [tags] pattern '{pattern}': {error}"))
+                })
+            })
+            .transpose()?;
+        Ok(TagPolicy {
+            pattern,
+            lowercase: self.lowercase,
+            fold_case: self.fold_case,
+        })
+    }
+}
+
+/// Naming rules for keys written by `add` and `import`.
+#[derive(Debug, Default, Deserialize)]
+pub struct KeySettings {
+    pattern: Option<String>,
+    #[serde(default)]
+    max_length: usize,
+    #[serde(default)]
+    case_insensitive: bool,
+}
+
+impl KeySettings {
+    /// Compiles the rules; an invalid `pattern` is a configuration error.
+    pub fn policy(&self) -> KvResult<KeyPolicy> {
+        let pattern = self
+            .pattern
+            .as_deref()
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|error| {
+                    KvError::InvalidInput(format!("invalid [keys] pattern '{pattern}': {error}"))
+                })
+            })
+            .transpose()?;
+        Ok(KeyPolicy {
+            pattern,
+            max_length: self.max_length,
+            case_insensitive: self.case_insensitive,
+        })
+    }
+}
+
+/// How values such as timestamps are rendered.
+#[derive(Debug, Default, Deserialize)]
+pub struct DisplaySettings {
+    time_format: Option<String>,
+    timezone: Option<String>,
+}
+
+impl DisplaySettings {
+    /// Timestamp format: `rfc3339` (default), `relative`, or a strftime pattern.
+    pub fn time_format(&self) -> TimeFormat {
+        self.time_format
+            .as_deref()
+            .map(TimeFormat::parse)
+            .unwrap_or_default()
+    }
+
+    /// Zone for printed timestamps: `utc` (default), `local`, or an IANA name.
+    pub fn timezone(&self) -> DisplayZone {
+        self.configured_timezone().unwrap_or_default()
+    }
+
+    /// The `timezone` value if one is set, so callers can pick their own default.
+    pub fn configured_timezone(&self) -> Option<DisplayZone> {
+        self.timezone.as_deref().map(DisplayZone::parse)
+    }
+}
+
+/// A named store selected with `--profile`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ProfileSettings {
+    data_file: Option<String>,
+    history_file: Option<String>,
+}
+
+impl ProfileSettings {
+    /// Data file for this profile, if configured.
+    pub fn data_file(&self) -> Option<&str> {
+        self.data_file
+            .as_deref()
+            .filter(|path| !path.trim().is_empty())
+    }
+
+    /// Recent-history log for this profile, if configured.
+    pub fn history_file(&self) -> Option<&str> {
+        self.history_file
+            .as_deref()
+            .filter(|path| !path.trim().is_empty())
+    }
+}
+
+/// Defaults applied to `search` and interactive mode when flags are omitted.
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchSettings {
+    default_limit: Option<usize>,
+    #[serde(default)]
+    default_scope: SearchScope,
+    key_weight: Option<f64>,
+    tag_weight: Option<f64>,
+    #[serde(default)]
+    min_score: i64,
+    #[serde(default)]
+    algorithm: SearchAlgorithm,
+}
+
+impl SearchSettings {
+    /// Result limit used when `--limit` is not passed.
+    pub fn default_limit(&self) -> Option<usize> {
+        self.default_limit
+    }
+
+    /// Scope used when neither `--keys` nor `--tags` is passed.
+    pub fn default_scope(&self) -> SearchScope {
+        self.default_scope
+    }
+
+    /// Score multipliers from `key_weight`/`tag_weight`, falling back to the defaults.
+    pub fn weights(&self) -> SearchWeights {
+        let defaults = SearchWeights::default();
+        SearchWeights {
+            key: self.key_weight.unwrap_or(defaults.key),
+            tag: self.tag_weight.unwrap_or(defaults.tag),
+        }
+    }
+
+    /// Rejects a negative or non-finite `key_weight`/`tag_weight`, which would
+    /// invert or erase the ranking instead of scaling it.
+    fn check_weights(&self) -> KvResult<()> {
+        for (name, weight) in [
+            ("key_weight", self.key_weight),
+            ("tag_weight", self.tag_weight),
+        ] {
+            if let Some(weight) = weight.filter(|weight| !weight.is_finite() || *weight < 0.0) {
+                return Err(KvError::InvalidInput(format!(
+                    "[search] {name} must be a finite, non-negative number, got {weight}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Score below which matches are dropped when `--min-score` is not passed.
+    pub fn min_score(&self) -> i64 {
+        self.min_score
+    }
+
+    /// Fuzzy matcher used by `search` and interactive mode.
+    pub fn algorithm(&self) -> SearchAlgorithm {
+        self.algorithm
+    }
+}
+
+/// Input handling for interactive mode.
+#[derive(Debug, Deserialize)]
+pub struct InteractiveSettings {
+    #[serde(default = "InteractiveSettings::default_poll_ms")]
+    poll_ms: u64,
+    #[serde(default)]
+    debounce_ms: u64,
+    #[serde(default)]
+    keybindings: Keybindings,
+    #[serde(default)]
+    remember_query: bool,
+}
+
+impl Default for InteractiveSettings {
+    fn default() -> Self {
+        Self {
+            poll_ms: Self::default_poll_ms(),
+            debounce_ms: 0,
+            keybindings: Keybindings::default(),
+            remember_query: false,
+        }
+    }
+}
+
+impl InteractiveSettings {
+    const fn default_poll_ms() -> u64 {
+        120
+    }
+
+    /// How long to wait for terminal events per loop iteration (at least 1ms).
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_ms.max(1))
+    }
+
+    /// Idle time required after typing before a search runs; zero searches on every key.
+    pub fn debounce(&self) -> Duration {
+        Duration::from_millis(self.debounce_ms)
+    }
+
+    /// Key map for interactive mode: `emacs` (default) or `vim`.
+    pub fn keybindings(&self) -> Keybindings {
+        self.keybindings
+    }
+
+    /// Whether interactive mode starts from the query the last session ended with.
+    pub fn remember_query(&self) -> bool {
+        self.remember_query
+    }
+}
+
 fn parse_level(raw: &str) -> Option<LevelFilter> {
     match raw.trim().to_uppercase().as_str() {
         "TRACE" => Some(LevelFilter::Trace),
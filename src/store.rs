@@ -1,56 +1,149 @@
-use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::fs;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, Utc};
+use fuzzy_matcher::clangd::ClangdMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
 use log::{debug, info, warn};
+use rand::seq::{IteratorRandom, SliceRandom};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::KvResult;
+use crate::output::OutputStyle;
+use crate::{log_key, KvError, KvResult};
+
+const TAG_SEPARATOR: &str = ", ";
+/// Candidate count from which `search_with` scores on the rayon pool. Scoring a key
+/// takes about a microsecond, so below this the per-job matcher setup and thread
+/// hand-off eat the gain.
+#[cfg(feature = "parallel")]
+const PARALLEL_SEARCH_THRESHOLD: usize = 10_000;
+
+/// Payload stored under a key; text is the common case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntryValue {
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl EntryValue {
+    pub const TEXT: &'static str = "text";
+    pub const BLOB: &'static str = "blob";
+
+    /// Name persisted in the `value_type` column.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            EntryValue::Text(_) => Self::TEXT,
+            EntryValue::Blob(_) => Self::BLOB,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            EntryValue::Text(text) => text.as_bytes(),
+            EntryValue::Blob(bytes) => bytes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<String> for EntryValue {
+    fn from(text: String) -> Self {
+        EntryValue::Text(text)
+    }
+}
+
+impl From<&str> for EntryValue {
+    fn from(text: &str) -> Self {
+        EntryValue::Text(text.to_string())
+    }
+}
+
+impl From<Vec<u8>> for EntryValue {
+    fn from(bytes: Vec<u8>) -> Self {
+        EntryValue::Blob(bytes)
+    }
+}
 
 /// In-memory representation of a single entry loaded from SQLite.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
-    value: String,
+    value: EntryValue,
     tags: Vec<String>,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
     expires_at: Option<DateTime<Utc>>,
+    /// Insertion sequence from the `seq` column; `None` until the entry is persisted.
+    #[serde(skip)]
+    seq: Option<i64>,
+    /// Free-form annotation kept apart from the value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    /// Key this entry points at; aliases carry no value of their own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alias_of: Option<String>,
+}
+
+/// How an update sets `created_at` (`add --reset-created` / `--created-at`).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum CreatedAt {
+    /// Keep the replaced entry's creation time; a new entry is created now.
+    #[default]
+    Keep,
+    /// Re-date the entry to the time of this update.
+    Reset,
+    At(DateTime<Utc>),
 }
 
 impl Entry {
-    pub fn new(value: String, tags: Vec<String>) -> Self {
+    pub fn new(value: impl Into<EntryValue>, tags: Vec<String>) -> Self {
         let now = Utc::now();
         Self {
-            value,
+            value: value.into(),
             tags,
             created_at: now,
             updated_at: now,
             expires_at: None,
+            seq: None,
+            notes: None,
+            alias_of: None,
         }
     }
 
     pub fn with_timestamps(
-        value: String,
+        value: impl Into<EntryValue>,
         tags: Vec<String>,
         created_at: DateTime<Utc>,
         updated_at: DateTime<Utc>,
         expires_at: Option<DateTime<Utc>>,
     ) -> Self {
         Self {
-            value,
+            value: value.into(),
             tags,
             created_at,
             updated_at,
             expires_at,
+            seq: None,
+            notes: None,
+            alias_of: None,
         }
     }
 
     pub fn from_persisted(
-        value: String,
+        value: impl Into<EntryValue>,
         tags_json: &str,
         created_at: &str,
         updated_at: &str,
@@ -70,50 +163,216 @@ impl Entry {
             .transpose()?;
 
         Ok(Self {
-            value,
+            value: value.into(),
             tags,
             created_at,
             updated_at,
             expires_at,
+            seq: None,
+            notes: None,
+            alias_of: None,
         })
     }
 
-    pub fn for_update(existing: Option<&Entry>, value: String, tags: Vec<String>) -> Self {
+    pub fn for_update(
+        existing: Option<&Entry>,
+        value: impl Into<EntryValue>,
+        tags: Vec<String>,
+    ) -> Self {
         let now = Utc::now();
         let created_at = existing
             .map(|entry| entry.created_at)
             .unwrap_or_else(|| now);
         let expires_at = existing.and_then(|entry| entry.expires_at);
         Self {
-            value,
+            value: value.into(),
             tags,
             created_at,
             updated_at: now,
             expires_at,
+            seq: existing.and_then(|entry| entry.seq),
+            notes: existing.and_then(|entry| entry.notes.clone()),
+            alias_of: None,
+        }
+    }
+
+    /// An alias pointing at `target`, replacing `existing` if the key is already used.
+    pub fn alias(existing: Option<&Entry>, target: String) -> Self {
+        Self::for_update(existing, String::new(), Vec::new()).with_alias_of(Some(target))
+    }
+
+    pub fn with_alias_of(mut self, target: Option<String>) -> Self {
+        self.alias_of = target;
+        self
+    }
+
+    pub fn alias_of(&self) -> Option<&str> {
+        self.alias_of.as_deref()
+    }
+
+    /// Whether both entries hold the same value, tags, notes, expiry, and alias target;
+    /// timestamps and the insertion sequence are ignored.
+    pub fn same_content(&self, other: &Entry) -> bool {
+        self.value == other.value
+            && self.tags == other.tags
+            && self.notes == other.notes
+            && self.expires_at == other.expires_at
+            && self.alias_of == other.alias_of
+    }
+
+    /// Attaches the insertion sequence read from the database.
+    pub fn with_seq(mut self, seq: i64) -> Self {
+        self.seq = Some(seq);
+        self
+    }
+
+    pub fn seq(&self) -> Option<i64> {
+        self.seq
+    }
+
+    /// Applies `created` on top of the times set by [`Entry::for_update`].
+    pub fn with_created_at(mut self, created: CreatedAt) -> Self {
+        match created {
+            CreatedAt::Keep => {}
+            CreatedAt::Reset => self.created_at = self.updated_at,
+            CreatedAt::At(at) => self.created_at = at,
         }
+        self
+    }
+
+    /// Replaces the notes; blank text clears them.
+    pub fn with_notes(mut self, notes: Option<String>) -> Self {
+        self.notes = notes.filter(|text| !text.trim().is_empty());
+        self
+    }
+
+    pub fn notes(&self) -> Option<&str> {
+        self.notes.as_deref()
     }
 
     pub fn tags_json(&self) -> KvResult<String> {
         Ok(serde_json::to_string(&self.tags)?)
     }
 
-    pub fn summary(&self, key: &str) -> String {
-        let suffix = if self.tags.is_empty() {
+    pub fn summary(&self, key: &str, style: &OutputStyle) -> String {
+        self.summary_with_matches(key, style, None)
+    }
+
+    /// Like [`Entry::summary`], highlighting the characters a search matched.
+    pub fn summary_with_matches(
+        &self,
+        key: &str,
+        style: &OutputStyle,
+        matches: Option<&MatchIndices>,
+    ) -> String {
+        let (key_indices, tag_indices) = match matches {
+            Some(matches) => (
+                matches.key.as_slice(),
+                matches.joined_tags(&self.tags, TAG_SEPARATOR),
+            ),
+            None => (&[][..], Vec::new()),
+        };
+        let mut suffix = if self.tags.is_empty() {
             String::new()
         } else {
-            format!(" [tags: {}]", self.tags.join(", "))
+            let joined = self.tags.join(TAG_SEPARATOR);
+            format!(
+                " {}",
+                style.tags(&format!(
+                    "[tags: {}]",
+                    style.highlight(&joined, &tag_indices)
+                ))
+            )
         };
-        format!("{key} = {}{}", self.value, suffix)
+        if let Some(notes) = &self.notes {
+            suffix.push_str(&format!(" {}", style.tags(&format!("[note: {notes}]"))));
+        }
+        if let Some(target) = &self.alias_of {
+            return format!(
+                "{} -> {}{}",
+                style.key(&style.highlight(key, key_indices)),
+                style.key(target),
+                suffix
+            );
+        }
+        format!(
+            "{} = {}{}",
+            style.key(&style.highlight(key, key_indices)),
+            style.value(&self.display_value()),
+            suffix
+        )
     }
 
+    /// Text payload of the entry; binary entries yield an empty string.
     pub fn value(&self) -> &str {
+        match &self.value {
+            EntryValue::Text(text) => text,
+            EntryValue::Blob(_) => "",
+        }
+    }
+
+    pub fn payload(&self) -> &EntryValue {
         &self.value
     }
 
+    pub fn bytes(&self) -> &[u8] {
+        self.value.as_bytes()
+    }
+
+    pub fn is_binary(&self) -> bool {
+        matches!(self.value, EntryValue::Blob(_))
+    }
+
+    /// Human-readable form of the value, with a placeholder for binary payloads.
+    pub fn display_value(&self) -> Cow<'_, str> {
+        match &self.value {
+            EntryValue::Text(text) => Cow::Borrowed(text),
+            EntryValue::Blob(bytes) => Cow::Owned(format!("<binary: {} bytes>", bytes.len())),
+        }
+    }
+
+    /// Whether the text value contains `pattern` literally; binary values never match.
+    pub fn contains_text(&self, pattern: &str, ignore_case: bool) -> bool {
+        match &self.value {
+            EntryValue::Text(text) if ignore_case => {
+                text.to_lowercase().contains(&pattern.to_lowercase())
+            }
+            EntryValue::Text(text) => text.contains(pattern),
+            EntryValue::Blob(_) => false,
+        }
+    }
+
+    /// 1-based line numbers and lines of the text value that contain `pattern`.
+    pub fn matching_lines(&self, pattern: &str, ignore_case: bool) -> Vec<(usize, &str)> {
+        let needle = if ignore_case {
+            pattern.to_lowercase()
+        } else {
+            pattern.to_string()
+        };
+        self.value()
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| {
+                if ignore_case {
+                    line.to_lowercase().contains(&needle)
+                } else {
+                    line.contains(&needle)
+                }
+            })
+            .map(|(idx, line)| (idx + 1, line))
+            .collect()
+    }
+
     pub fn tags(&self) -> &[String] {
         &self.tags
     }
 
+    /// Replaces the tags as given; callers normalize them first.
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
     pub fn created_at(&self) -> DateTime<Utc> {
         self.created_at
     }
@@ -126,6 +385,11 @@ impl Entry {
         self.expires_at
     }
 
+    /// Moves `updated_at` to `at` without touching the value, tags, or `created_at`.
+    pub fn touch(&mut self, at: DateTime<Utc>) {
+        self.updated_at = at;
+    }
+
     pub fn set_ttl_minutes(&mut self, ttl_minutes: Option<u64>) {
         self.expires_at =
             ttl_minutes.map(|minutes| Utc::now() + chrono::Duration::minutes(minutes as i64));
@@ -147,20 +411,51 @@ impl Entry {
 }
 
 /// Determines how fuzzy searches evaluate stored data.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SearchScope {
+    #[default]
     All,
+    #[serde(rename = "keys")]
     KeysOnly,
+    #[serde(rename = "tags")]
     TagsOnly,
 }
 
+/// Fuzzy matcher used to score search candidates (`[search] algorithm`).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchAlgorithm {
+    /// fzf-style scoring that rewards consecutive runs and word starts.
+    #[default]
+    Skim,
+    /// clangd's code-completion scoring, stricter about segment boundaries.
+    Clangd,
+}
+
+impl SearchAlgorithm {
+    /// Builds the matcher. Both use smart case: an uppercase letter in the pattern
+    /// makes the match case-sensitive.
+    pub fn matcher(self) -> Box<dyn FuzzyMatcher> {
+        match self {
+            SearchAlgorithm::Skim => Box::new(SkimMatcherV2::default().smart_case()),
+            SearchAlgorithm::Clangd => Box::new(ClangdMatcher::default().smart_case()),
+        }
+    }
+}
+
 /// Cached entries plus pre-computed key ordering for fast fuzzy searching.
 pub struct Store {
     entries: HashMap<String, Entry>,
     search_keys: Vec<String>,
+    folded_keys: FoldedKeys,
     recent: VecDeque<String>,
     recent_capacity: usize,
     recent_file: Option<PathBuf>,
+    /// Only some keys are cached, so recent history must not be pruned against them.
+    partial: bool,
+    /// Log keys as short digests (`[logging] redact_keys`).
+    redact_log_keys: bool,
 }
 
 impl Store {
@@ -169,9 +464,11 @@ impl Store {
     pub fn from_entries(entries: Vec<(String, Entry)>) -> Self {
         let mut map = HashMap::new();
         let mut search_keys = Vec::with_capacity(entries.len());
+        let mut folded_keys = FoldedKeys::default();
 
         for (key, entry) in entries {
             search_keys.push(key.clone());
+            folded_keys.insert(&key);
             map.insert(key, entry);
         }
 
@@ -180,9 +477,27 @@ impl Store {
         Self {
             entries: map,
             search_keys,
+            folded_keys,
             recent: VecDeque::with_capacity(Self::RECENT_CAPACITY),
             recent_capacity: Self::RECENT_CAPACITY,
             recent_file: None,
+            partial: false,
+            redact_log_keys: false,
+        }
+    }
+
+    /// Logs keys and search patterns as short SHA-256 digests instead of verbatim.
+    pub fn set_redact_log_keys(&mut self, redact: bool) {
+        self.redact_log_keys = redact;
+    }
+
+    /// A cache holding only the entries a command touches (see
+    /// [`Database::load_entries_for`](crate::db::Database::load_entries_for)).
+    /// Recent history keeps keys that are not loaded.
+    pub fn from_subset(entries: Vec<(String, Entry)>) -> Self {
+        Self {
+            partial: true,
+            ..Self::from_entries(entries)
         }
     }
 
@@ -203,6 +518,7 @@ impl Store {
             if let Err(position) = self.search_keys.binary_search(&key) {
                 self.search_keys.insert(position, key.clone());
             }
+            self.folded_keys.insert(&key);
         }
         let previous = self.entries.insert(key, entry);
         info!("cache updated; total_entries={}", self.entries.len());
@@ -221,13 +537,13 @@ impl Store {
                 // Keep index recoverable even if the key list ever gets out of sync.
                 self.search_keys.retain(|candidate| candidate != key);
             }
-            self.recent.retain(|candidate| candidate != key);
+            self.folded_keys.remove(key);
             info!(
                 "cache removed key={}; total_entries={}",
-                key,
+                log_key(key, self.redact_log_keys),
                 self.entries.len()
             );
-            self.prune_recent();
+            self.forget_recent(key);
         }
         removed
     }
@@ -236,9 +552,11 @@ impl Store {
     pub fn reset(&mut self, entries: Vec<(String, Entry)>) {
         self.entries.clear();
         self.search_keys.clear();
+        self.folded_keys = FoldedKeys::default();
         self.recent.clear();
         for (key, entry) in entries {
             self.search_keys.push(key.clone());
+            self.folded_keys.insert(&key);
             self.entries.insert(key, entry);
         }
         self.search_keys.sort();
@@ -253,74 +571,254 @@ impl Store {
             .collect()
     }
 
+    /// All entries sorted by `order`.
+    pub fn ordered_by(&self, order: SortOrder) -> Vec<(&String, &Entry)> {
+        let mut entries = self.ordered();
+        order.sort(&mut entries);
+        entries
+    }
+
+    /// Checks `key` against `policy`. With case-insensitive keys, a stored key
+    /// differing only in case is a collision.
+    pub fn check_key(&self, key: &str, policy: &KeyPolicy) -> KvResult<()> {
+        policy.check(key)?;
+        if !policy.case_insensitive {
+            return Ok(());
+        }
+        self.folded_keys.check(key)
+    }
+
+    /// Stored keys grouped by their lowercase form.
+    pub fn folded_keys(&self) -> &FoldedKeys {
+        &self.folded_keys
+    }
+
+    /// Entries whose key starts with `prefix`, in key order.
+    pub fn with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a String, &'a Entry)> + 'a {
+        self.keys_with_prefix(prefix)
+            .iter()
+            .filter_map(|key| self.entries.get_key_value(key))
+    }
+
+    /// Slice of the sorted key index covering every key that starts with `prefix`.
+    fn keys_with_prefix(&self, prefix: &str) -> &[String] {
+        let start = self
+            .search_keys
+            .partition_point(|key| key.as_str() < prefix);
+        let len = self.search_keys[start..]
+            .iter()
+            .take_while(|key| key.starts_with(prefix))
+            .count();
+        &self.search_keys[start..start + len]
+    }
+
     pub fn search<'a>(
         &'a self,
         pattern: &str,
         limit: usize,
         scope: SearchScope,
     ) -> Vec<SearchResult<'a>> {
-        if pattern.is_empty() || limit == 0 {
+        self.search_with(pattern, &SearchOptions::new(limit, scope))
+    }
+
+    /// Like [`Store::search`], also recording which characters matched in each result.
+    pub fn search_with_indices<'a>(
+        &'a self,
+        pattern: &str,
+        limit: usize,
+        scope: SearchScope,
+    ) -> Vec<SearchResult<'a>> {
+        let options = SearchOptions {
+            with_indices: true,
+            ..SearchOptions::new(limit, scope)
+        };
+        self.search_with(pattern, &options)
+    }
+
+    /// Fuzzy search honoring every [`SearchOptions`] field. With a prefix, only keys
+    /// under it are candidates and the pattern is matched against the rest of the key.
+    pub fn search_with<'a>(
+        &'a self,
+        pattern: &str,
+        options: &SearchOptions<'_>,
+    ) -> Vec<SearchResult<'a>> {
+        self.search_among(pattern, options, None, None)
+    }
+
+    /// Like [`Store::search_with`], but scores only `candidates` (`None` for every key
+    /// under the prefix) and replaces `matched` with each candidate that matched in any
+    /// field, before `min_score`, `offset`, and `limit` apply. A fuzzy match can only
+    /// disappear as the pattern grows, so `matched` holds every candidate for a later
+    /// pattern that extends this one.
+    pub fn search_narrowing<'a>(
+        &'a self,
+        pattern: &str,
+        options: &SearchOptions<'_>,
+        candidates: Option<&[String]>,
+        matched: &mut Vec<String>,
+    ) -> Vec<SearchResult<'a>> {
+        matched.clear();
+        self.search_among(pattern, options, candidates, Some(matched))
+    }
+
+    fn search_among<'a>(
+        &'a self,
+        pattern: &str,
+        options: &SearchOptions<'_>,
+        candidates: Option<&[String]>,
+        matched: Option<&mut Vec<String>>,
+    ) -> Vec<SearchResult<'a>> {
+        if pattern.is_empty() {
             return Vec::new();
         }
 
-        let matcher = SkimMatcherV2::default();
-        let mut scored = Vec::new();
-
-        for key in &self.search_keys {
-            if let Some(entry) = self.entries.get(key) {
-                let key_score = if matches_keys(scope) {
-                    matcher.fuzzy_match(key, pattern)
-                } else {
-                    None
-                };
-
-                let tag_score = if matches_tags(scope) {
-                    entry
-                        .tags
-                        .iter()
-                        .filter_map(|tag| matcher.fuzzy_match(tag, pattern))
-                        .max()
-                } else {
-                    None
-                };
-
-                let best_score = match scope {
-                    SearchScope::All => key_score.max(tag_score),
-                    SearchScope::KeysOnly => key_score,
-                    SearchScope::TagsOnly => tag_score,
-                };
-
-                if let Some(score) = best_score {
-                    scored.push(Scored {
-                        score,
-                        key: key.as_str(),
-                        entry,
-                    });
+        let candidates = candidates.unwrap_or_else(|| self.keys_with_prefix(options.prefix));
+        let score = |matcher: &dyn FuzzyMatcher, key: &String| {
+            let (key, entry) = self.entries.get_key_value(key)?;
+            if let Some(tag) = options.tag {
+                if !entry.tags.iter().any(|candidate| candidate == tag) {
+                    return None;
                 }
             }
+            score_candidate(matcher, key, entry, pattern, options)
+        };
+        let matcher = options.algorithm.matcher();
+
+        #[cfg(feature = "parallel")]
+        if candidates.len() >= PARALLEL_SEARCH_THRESHOLD && rayon::current_num_threads() > 1 {
+            use rayon::prelude::*;
+            // `Box<dyn FuzzyMatcher>` is not `Sync`, so each rayon job builds its own.
+            let scored: Vec<_> = candidates
+                .par_iter()
+                .map_init(
+                    || options.algorithm.matcher(),
+                    |matcher, key| score(matcher.as_ref(), key),
+                )
+                .flatten()
+                .collect();
+            let redact = self.redact_log_keys;
+            return rank_results(scored, matcher.as_ref(), pattern, options, matched, redact);
         }
 
-        scored.sort_by(|a, b| b.score.cmp(&a.score));
-        if scored.len() > limit {
-            scored.truncate(limit);
+        let scored = candidates
+            .iter()
+            .filter_map(|key| score(matcher.as_ref(), key));
+        let redact = self.redact_log_keys;
+        rank_results(scored, matcher.as_ref(), pattern, options, matched, redact)
+    }
+
+    /// Entries whose value contains `pattern` as a literal substring, in key order.
+    pub fn grep(&self, pattern: &str, ignore_case: bool) -> Vec<(&String, &Entry)> {
+        self.ordered()
+            .into_iter()
+            .filter(|(_, entry)| entry.contains_text(pattern, ignore_case))
+            .collect()
+    }
+
+    /// Like [`Store::get`], but follows an alias to its target. `None` if either is missing.
+    pub fn resolve(&self, key: &str) -> Option<&Entry> {
+        let entry = self.get(key)?;
+        match &entry.alias_of {
+            Some(target) => self.get(target),
+            None => Some(entry),
         }
+    }
 
-        let results: Vec<_> = scored
+    /// Keys of the aliases pointing at `target`, in key order.
+    pub fn aliases_of(&self, target: &str) -> Vec<String> {
+        self.ordered()
             .into_iter()
-            .map(|scored| SearchResult {
-                key: scored.key,
-                entry: scored.entry,
-            })
-            .collect();
+            .filter(|(_, entry)| entry.alias_of.as_deref() == Some(target))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
 
-        debug!(
-            "fuzzy search pattern='{}' scope={:?} results={}",
-            pattern,
-            scope,
-            results.len()
-        );
+    /// Keys matching a shell-style glob, in key order.
+    pub fn keys_matching_glob(&self, pattern: &glob::Pattern) -> Vec<String> {
+        self.search_keys
+            .iter()
+            .filter(|key| pattern.matches(key))
+            .cloned()
+            .collect()
+    }
+
+    /// Number of entries carrying each tag, in tag order.
+    pub fn tag_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for entry in self.entries.values() {
+            for tag in &entry.tags {
+                *counts.entry(tag.as_str()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Counts, value sizes, and the oldest and newest entries, optionally limited to
+    /// entries carrying `tag`. Ties on age go to the smaller key.
+    pub fn stats(&self, tag: Option<&str>) -> StoreStats<'_> {
+        let mut stats = StoreStats::default();
+        for (key, entry) in self.ordered() {
+            if tag.is_some_and(|tag| !entry.tags.iter().any(|t| t == tag)) {
+                continue;
+            }
+            let size = entry.value.len();
+            stats.entries += 1;
+            stats.value_bytes += size;
+            if stats.largest.is_none_or(|(_, largest)| size > largest) {
+                stats.largest = Some((key, size));
+            }
+            if stats
+                .oldest
+                .is_none_or(|(_, oldest)| entry.created_at < oldest.created_at)
+            {
+                stats.oldest = Some((key, entry));
+            }
+            if stats
+                .newest
+                .is_none_or(|(_, newest)| entry.created_at > newest.created_at)
+            {
+                stats.newest = Some((key, entry));
+            }
+        }
+        stats
+    }
+
+    /// Keys only here, keys only in `other`, and shared keys whose content differs
+    /// (see [`Entry::same_content`]), each in key order.
+    pub fn diff(&self, other: &Store) -> StoreDiff {
+        let mut diff = StoreDiff::default();
+        for (key, entry) in self.ordered() {
+            match other.get(key) {
+                None => diff.only_left.push(key.clone()),
+                Some(theirs) if !theirs.same_content(entry) => {
+                    diff.changed.push(key.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        diff.only_right = other
+            .ordered()
+            .into_iter()
+            .filter(|(key, _)| !self.entries.contains_key(*key))
+            .map(|(key, _)| key.clone())
+            .collect();
+        diff
+    }
 
-        results
+    /// Samples up to `count` distinct entries in random order, optionally limited to
+    /// entries carrying `tag`.
+    pub fn random(&self, count: usize, tag: Option<&str>) -> Vec<(&String, &Entry)> {
+        let mut rng = rand::rng();
+        let mut picked = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| tag.is_none_or(|tag| entry.tags.iter().any(|t| t == tag)))
+            .sample(&mut rng, count);
+        picked.shuffle(&mut rng);
+        picked
     }
 
     pub fn normalize_tags(raw: Vec<String>) -> Vec<String> {
@@ -334,6 +832,41 @@ impl Store {
         set.into_iter().collect()
     }
 
+    /// Like [`Store::normalize_tags`], but tags differing only in case collapse to
+    /// the first-seen spelling.
+    pub fn normalize_tags_folded(raw: Vec<String>) -> Vec<String> {
+        let mut folded = BTreeMap::new();
+        for tag in raw {
+            let trimmed = tag.trim();
+            if !trimmed.is_empty() {
+                folded
+                    .entry(trimmed.to_lowercase())
+                    .or_insert_with(|| trimmed.to_string());
+            }
+        }
+        folded.into_values().collect()
+    }
+
+    /// Normalizes tags, then applies the configured [`TagPolicy`].
+    pub fn normalize_tags_with(raw: Vec<String>, policy: &TagPolicy) -> KvResult<Vec<String>> {
+        let tags = if policy.lowercase {
+            Self::normalize_tags(raw.into_iter().map(|tag| tag.to_lowercase()).collect())
+        } else if policy.fold_case {
+            Self::normalize_tags_folded(raw)
+        } else {
+            Self::normalize_tags(raw)
+        };
+        if let Some(pattern) = &policy.pattern {
+            if let Some(bad) = tags.iter().find(|tag| !pattern.is_match(tag)) {
+                return Err(KvError::InvalidInput(format!(
+                    "tag '{bad}' does not match the required pattern '{}'",
+                    pattern.as_str()
+                )));
+            }
+        }
+        Ok(tags)
+    }
+
     pub fn record_access(&mut self, key: &str) {
         if !self.entries.contains_key(key) {
             return;
@@ -345,6 +878,15 @@ impl Store {
         self.persist_recent();
     }
 
+    /// Drops `key` from the recent history, rewriting the log file if it was listed.
+    pub fn forget_recent(&mut self, key: &str) {
+        let before = self.recent.len();
+        self.recent.retain(|candidate| candidate != key);
+        if self.recent.len() != before {
+            self.persist_recent();
+        }
+    }
+
     pub fn recent(&self, limit: usize) -> Vec<String> {
         self.recent.iter().take(limit).cloned().collect()
     }
@@ -359,11 +901,21 @@ impl Store {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use std::fs;
     use tempfile::tempdir;
 
+    /// A store holding untagged `(key, value)` pairs.
+    pub(crate) fn store_with(pairs: &[(&str, &str)]) -> Store {
+        Store::from_entries(
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), Entry::new(value.to_string(), vec![])))
+                .collect(),
+        )
+    }
+
     fn sample_entries() -> Vec<(String, Entry)> {
         vec![
             ("alpha".to_string(), Entry::new("A".to_string(), vec![])),
@@ -372,6 +924,598 @@ mod tests {
         ]
     }
 
+    #[test]
+    fn search_with_indices_reports_matched_key_and_tag_chars() {
+        let store = Store::from_entries(vec![(
+            "deploy".to_string(),
+            Entry::new("x".to_string(), vec!["prod".to_string()]),
+        )]);
+
+        let plain = store.search("dp", 5, SearchScope::All);
+        assert!(plain[0].matches.is_none());
+
+        let results = store.search_with_indices("dp", 5, SearchScope::KeysOnly);
+        let matches = results[0].matches.as_ref().unwrap();
+        assert_eq!(matches.key, vec![0, 2]);
+        assert_eq!(matches.tags, vec![Vec::<usize>::new()]);
+
+        let tagged = store.search_with_indices("pd", 5, SearchScope::TagsOnly);
+        let matches = tagged[0].matches.as_ref().unwrap();
+        assert_eq!(matches.joined_tags(&["prod".to_string()], ", "), vec![0, 3]);
+    }
+
+    #[test]
+    fn search_breaks_score_ties_by_key() {
+        let store = store_with(&[("svc/c", "v"), ("svc/a", "v"), ("svc/b", "v")]);
+        let keys: Vec<_> = store
+            .search("svc", 0, SearchScope::KeysOnly)
+            .into_iter()
+            .map(|result| result.key)
+            .collect();
+        assert_eq!(keys, ["svc/a", "svc/b", "svc/c"]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_search_matches_the_serial_ranking() {
+        let entries = (0..PARALLEL_SEARCH_THRESHOLD + 500)
+            .map(|i| {
+                let entry = Entry::new("v".to_string(), vec![format!("t{}", i % 7)]);
+                (format!("k{}/cfg{i}", i % 13), entry)
+            })
+            .collect();
+        let store = Store::from_entries(entries);
+        let options = SearchOptions::new(0, SearchScope::All);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let parallel: Vec<_> = pool
+            .install(|| store.search_with("k3cfg", &options))
+            .into_iter()
+            .map(|result| (result.score, result.key))
+            .collect();
+
+        let matcher = options.algorithm.matcher();
+        let mut serial: Vec<_> = store
+            .entries
+            .iter()
+            .filter_map(|(key, entry)| {
+                score_candidate(matcher.as_ref(), key, entry, "k3cfg", &options)
+            })
+            .map(|scored| (scored.score, scored.key))
+            .collect();
+        serial.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        assert!(!serial.is_empty());
+        assert_eq!(parallel, serial);
+    }
+
+    #[test]
+    fn top_ranked_heap_matches_a_full_sort() {
+        let entry = Entry::new("v".to_string(), vec![]);
+        let keys: Vec<_> = (0..200)
+            .map(|i| format!("k{:03}", (i * 37) % 200))
+            .collect();
+        let scored = || {
+            keys.iter().enumerate().map(|(i, key)| Scored {
+                // Few distinct scores, so most of the ordering comes from key ties.
+                score: (i % 5) as i64,
+                fields: vec![(None, (i % 5) as i64)],
+                key,
+                entry: &entry,
+            })
+        };
+        fn ranked<'a>(scored: Vec<Scored<'a>>) -> Vec<(i64, &'a str)> {
+            scored.iter().map(|s| (s.score, s.key)).collect()
+        }
+
+        let mut sorted: Vec<_> = scored().collect();
+        sorted.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.key.cmp(b.key)));
+        let sorted = ranked(sorted);
+        assert_eq!(ranked(top_ranked(scored(), None)), sorted);
+        for keep in [0, 1, 7, 40, 199, 200, 500] {
+            let expected = &sorted[..keep.min(sorted.len())];
+            assert_eq!(ranked(top_ranked(scored(), Some(keep))), expected);
+        }
+    }
+
+    #[test]
+    fn search_can_be_limited_to_one_tag() {
+        let entries = vec![
+            (
+                "deploy/api".to_string(),
+                Entry::new("a", vec!["prod".into()]),
+            ),
+            (
+                "deploy/web".to_string(),
+                Entry::new("b", vec!["staging".into()]),
+            ),
+            (
+                "dpl".to_string(),
+                Entry::new("c", vec!["prod".into(), "x".into()]),
+            ),
+        ];
+        let store = Store::from_entries(entries);
+        let search = |tag| {
+            let options = SearchOptions {
+                tag,
+                ..SearchOptions::new(0, SearchScope::All)
+            };
+            let mut keys: Vec<_> = store
+                .search_with("dpl", &options)
+                .into_iter()
+                .map(|result| result.key)
+                .collect();
+            keys.sort_unstable();
+            keys
+        };
+        assert_eq!(search(None), ["deploy/api", "deploy/web", "dpl"]);
+        assert_eq!(search(Some("prod")), ["deploy/api", "dpl"]);
+        assert!(search(Some("pro")).is_empty());
+    }
+
+    #[test]
+    fn stats_can_be_scoped_to_a_tag() {
+        let at = |secs| DateTime::from_timestamp(secs, 0).unwrap();
+        let entry = |value: &str, tag: &str, created| {
+            Entry::with_timestamps(value, vec![tag.to_string()], at(created), at(created), None)
+        };
+        let store = Store::from_entries(vec![
+            ("a".to_string(), entry("1234", "work", 20)),
+            ("b".to_string(), entry("12", "work", 10)),
+            ("c".to_string(), entry("123456789", "home", 30)),
+        ]);
+
+        let all = store.stats(None);
+        assert_eq!((all.entries, all.value_bytes), (3, 15));
+        assert_eq!(all.largest, Some(("c", 9)));
+        assert_eq!(all.oldest.unwrap().0, "b");
+        assert_eq!(all.newest.unwrap().0, "c");
+
+        let work = store.stats(Some("work"));
+        assert_eq!((work.entries, work.value_bytes), (2, 6));
+        assert_eq!(work.largest, Some(("a", 4)));
+        assert_eq!(work.newest.unwrap().0, "a");
+        assert_eq!(store.stats(Some("none")).entries, 0);
+    }
+
+    #[test]
+    fn search_reports_which_field_matched() {
+        let store = Store::from_entries(vec![
+            ("rustup".to_string(), Entry::new("a".to_string(), vec![])),
+            (
+                "notes".to_string(),
+                Entry::new("b".to_string(), vec!["rust".to_string()]),
+            ),
+        ]);
+
+        let results = store.search("rust", 10, SearchScope::All);
+        let field_of = |key: &str| {
+            results
+                .iter()
+                .find(|result| result.key == key)
+                .map(|result| result.field.clone())
+        };
+        assert_eq!(field_of("rustup"), Some(MatchField::Key));
+        assert_eq!(field_of("notes"), Some(MatchField::Tag("rust".to_string())));
+        assert_eq!(
+            MatchField::Tag("rust".to_string()).to_string(),
+            "tag 'rust'"
+        );
+    }
+
+    #[test]
+    fn search_collects_every_matching_field_best_first() {
+        let store = Store::from_entries(vec![(
+            "rust".to_string(),
+            Entry::new(
+                "a".to_string(),
+                vec!["go".to_string(), "r-u-s-t".to_string(), "rust".to_string()],
+            ),
+        )]);
+
+        let results = store.search("rust", 10, SearchScope::All);
+        let fields: Vec<_> = results[0]
+            .fields
+            .iter()
+            .map(|(field, _)| field.to_string())
+            .collect();
+        assert_eq!(fields, ["key", "tag 'rust'", "tag 'r-u-s-t'"]);
+        assert_eq!(results[0].field, MatchField::Key);
+        assert_eq!(results[0].fields[0].1, results[0].score);
+        assert!(results[0]
+            .fields
+            .windows(2)
+            .all(|pair| pair[0].1 >= pair[1].1));
+    }
+
+    #[test]
+    fn grep_matches_literal_substrings_in_values() {
+        let store = Store::from_entries(vec![
+            (
+                "notes".to_string(),
+                Entry::new("first line\nTODO: fix it\n".to_string(), vec![]),
+            ),
+            ("plain".to_string(), Entry::new("todo".to_string(), vec![])),
+            ("blob".to_string(), Entry::new(b"TODO".to_vec(), vec![])),
+        ]);
+
+        let keys = |hits: Vec<(&String, &Entry)>| -> Vec<String> {
+            hits.into_iter().map(|(key, _)| key.clone()).collect()
+        };
+        assert_eq!(keys(store.grep("TODO", false)), ["notes"]);
+        assert_eq!(keys(store.grep("TODO", true)), ["notes", "plain"]);
+        assert!(store.grep("tdo", true).is_empty());
+
+        let notes = store.get("notes").unwrap();
+        assert_eq!(notes.matching_lines("todo", true), [(2, "TODO: fix it")]);
+    }
+
+    #[test]
+    fn key_match_wins_a_tie_under_default_weights() {
+        // "a-notes" sorts first, so only the weights can put "deploy" ahead.
+        let store = Store::from_entries(vec![
+            (
+                "a-notes".to_string(),
+                Entry::new("x".to_string(), vec!["deploy".to_string()]),
+            ),
+            ("deploy".to_string(), Entry::new("y".to_string(), vec![])),
+        ]);
+
+        let ranked: Vec<_> = store
+            .search("deploy", 10, SearchScope::All)
+            .into_iter()
+            .map(|result| result.key)
+            .collect();
+        assert_eq!(ranked, ["deploy", "a-notes"]);
+
+        let options = SearchOptions {
+            weights: SearchWeights { key: 0.5, tag: 1.0 },
+            ..SearchOptions::new(10, SearchScope::All)
+        };
+        let ranked: Vec<_> = store
+            .search_with("deploy", &options)
+            .into_iter()
+            .map(|result| result.key)
+            .collect();
+        assert_eq!(ranked, ["a-notes", "deploy"]);
+    }
+
+    #[test]
+    fn min_score_drops_weak_matches() {
+        let store = Store::from_entries(vec![
+            ("deploy".to_string(), Entry::new("x".to_string(), vec![])),
+            (
+                "d-e-p-l-o-y".to_string(),
+                Entry::new("y".to_string(), vec![]),
+            ),
+        ]);
+        let results = store.search("deploy", 10, SearchScope::KeysOnly);
+        assert_eq!(results.len(), 2);
+        let strongest = results[0].score;
+        assert!(strongest > results[1].score);
+
+        let options = SearchOptions {
+            min_score: strongest,
+            ..SearchOptions::new(10, SearchScope::KeysOnly)
+        };
+        let strong: Vec<_> = store
+            .search_with("deploy", &options)
+            .into_iter()
+            .map(|result| result.key)
+            .collect();
+        assert_eq!(strong, ["deploy"]);
+    }
+
+    #[test]
+    fn diff_reports_keys_on_each_side_and_changed_entries() {
+        let left = Store::from_entries(vec![
+            ("same".to_string(), Entry::new("1".to_string(), vec![])),
+            ("value".to_string(), Entry::new("2".to_string(), vec![])),
+            ("tags".to_string(), Entry::new("3".to_string(), vec![])),
+            ("notes".to_string(), Entry::new("6".to_string(), vec![])),
+            ("mine".to_string(), Entry::new("4".to_string(), vec![])),
+        ]);
+        let right = Store::from_entries(vec![
+            ("same".to_string(), Entry::new("1".to_string(), vec![])),
+            ("value".to_string(), Entry::new("two".to_string(), vec![])),
+            (
+                "tags".to_string(),
+                Entry::new("3".to_string(), vec!["t".to_string()]),
+            ),
+            (
+                "notes".to_string(),
+                Entry::new("6".to_string(), vec![]).with_notes(Some("n".to_string())),
+            ),
+            ("theirs".to_string(), Entry::new("5".to_string(), vec![])),
+        ]);
+
+        let diff = left.diff(&right);
+        assert_eq!(diff.only_left, ["mine"]);
+        assert_eq!(diff.only_right, ["theirs"]);
+        assert_eq!(diff.changed, ["notes", "tags", "value"]);
+        assert!(left.diff(&left).is_empty());
+    }
+
+    #[test]
+    fn keys_matching_glob_uses_shell_patterns() {
+        let store = store_with(&[
+            ("project/a", "v"),
+            ("project/b.md", "v"),
+            ("other/a", "v"),
+            ("project", "v"),
+        ]);
+        let pattern = |raw: &str| glob::Pattern::new(raw).unwrap();
+        assert_eq!(
+            store.keys_matching_glob(&pattern("project/*")),
+            ["project/a", "project/b.md"]
+        );
+        assert_eq!(
+            store.keys_matching_glob(&pattern("*/a")),
+            ["other/a", "project/a"]
+        );
+        assert!(store.keys_matching_glob(&pattern("nope*")).is_empty());
+    }
+
+    #[test]
+    fn tag_counts_counts_entries_per_tag() {
+        let store = Store::from_entries(vec![
+            (
+                "a".to_string(),
+                Entry::new("1".to_string(), vec!["rust".into(), "cli".into()]),
+            ),
+            (
+                "b".to_string(),
+                Entry::new("2".to_string(), vec!["rust".into()]),
+            ),
+            ("c".to_string(), Entry::new("3".to_string(), vec![])),
+        ]);
+        let counts: Vec<_> = store.tag_counts().into_iter().collect();
+        assert_eq!(counts, [("cli", 1), ("rust", 2)]);
+    }
+
+    #[test]
+    fn both_algorithms_use_smart_case() {
+        let store = Store::from_entries(vec![
+            ("deploy".to_string(), Entry::new("x".to_string(), vec![])),
+            ("Deploy".to_string(), Entry::new("y".to_string(), vec![])),
+        ]);
+        for algorithm in [SearchAlgorithm::Skim, SearchAlgorithm::Clangd] {
+            let options = SearchOptions {
+                algorithm,
+                ..SearchOptions::new(10, SearchScope::KeysOnly)
+            };
+            assert_eq!(store.search_with("dep", &options).len(), 2, "{algorithm:?}");
+            let exact: Vec<_> = store
+                .search_with("Dep", &options)
+                .into_iter()
+                .map(|result| result.key)
+                .collect();
+            assert_eq!(exact, ["Deploy"], "{algorithm:?}");
+        }
+    }
+
+    #[test]
+    fn offset_pages_through_ranked_results() {
+        let store = Store::from_entries(
+            ["api", "apx", "axpxi"]
+                .into_iter()
+                .map(|key| (key.to_string(), Entry::new("v".to_string(), vec![])))
+                .collect(),
+        );
+        let page = |offset| {
+            let options = SearchOptions {
+                offset,
+                ..SearchOptions::new(1, SearchScope::KeysOnly)
+            };
+            store
+                .search_with("ap", &options)
+                .into_iter()
+                .map(|result| result.key)
+                .collect::<Vec<_>>()
+        };
+        let all: Vec<_> = store
+            .search("ap", 10, SearchScope::KeysOnly)
+            .into_iter()
+            .map(|result| result.key)
+            .collect();
+        assert_eq!(all.len(), 3);
+        assert_eq!([page(0), page(1), page(2)].concat(), all);
+        assert!(page(3).is_empty());
+        assert!(page(100).is_empty());
+    }
+
+    #[test]
+    fn insertion_order_sorts_by_seq_with_unsaved_entries_last() {
+        let store = Store::from_entries(vec![
+            (
+                "a".to_string(),
+                Entry::new("1".to_string(), vec![]).with_seq(3),
+            ),
+            ("b".to_string(), Entry::new("2".to_string(), vec![])),
+            (
+                "c".to_string(),
+                Entry::new("3".to_string(), vec![]).with_seq(1),
+            ),
+        ]);
+        let keys = |order| -> Vec<String> {
+            store
+                .ordered_by(order)
+                .into_iter()
+                .map(|(key, _)| key.clone())
+                .collect()
+        };
+        assert_eq!(keys(SortOrder::Key), ["a", "b", "c"]);
+        assert_eq!(keys(SortOrder::Insertion), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn tag_policy_lowercases_then_rejects_non_matching_tags() {
+        let policy = TagPolicy {
+            pattern: Some(Regex::new("^[a-z0-9-]+$").unwrap()),
+            lowercase: true,
+            ..TagPolicy::default()
+        };
+        let tags = Store::normalize_tags_with(vec!["Rust".into(), "rust".into()], &policy);
+        assert_eq!(tags.unwrap(), ["rust"]);
+
+        let error = Store::normalize_tags_with(vec!["ok".into(), "not ok".into()], &policy)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'not ok'"), "{error}");
+
+        let permissive = Store::normalize_tags_with(vec!["Any Tag".into()], &TagPolicy::default());
+        assert_eq!(permissive.unwrap(), ["Any Tag"]);
+    }
+
+    #[test]
+    fn key_policy_rejects_empty_long_and_non_matching_keys() {
+        let permissive = KeyPolicy::default();
+        assert!(permissive.check("any key at all").is_ok());
+        for blank in ["", "  \t"] {
+            assert!(matches!(
+                permissive.check(blank),
+                Err(KvError::InvalidInput(_))
+            ));
+        }
+
+        let policy = KeyPolicy {
+            pattern: Some(Regex::new("^[a-z]+(/[a-z]+)*$").unwrap()),
+            max_length: 8,
+            ..KeyPolicy::default()
+        };
+        assert!(policy.check("team/api").is_ok());
+        let error = policy.check("Team/API").unwrap_err().to_string();
+        assert!(error.contains("'Team/API'"), "{error}");
+        let error = policy.check("team/apis").unwrap_err().to_string();
+        assert!(error.contains("limit is 8"), "{error}");
+    }
+
+    #[test]
+    fn folded_keys_follow_inserts_and_removals() {
+        let policy = KeyPolicy {
+            case_insensitive: true,
+            ..KeyPolicy::default()
+        };
+        let mut store = Store::from_entries(vec![(
+            "Legacy".to_string(),
+            Entry::new("v".to_string(), vec![]),
+        )]);
+        assert!(store.check_key("legacy", &policy).is_err());
+        assert!(store.check_key("Legacy", &policy).is_ok());
+
+        store.insert("Other".to_string(), Entry::new("v".to_string(), vec![]));
+        assert_eq!(store.folded_keys().collision("other"), Some("Other"));
+        store.remove("Legacy");
+        store.remove("Other");
+        assert!(store.check_key("legacy", &policy).is_ok());
+        assert_eq!(store.folded_keys().collision("other"), None);
+    }
+
+    #[test]
+    fn fold_case_collapses_tags_to_first_spelling() {
+        let raw = || vec!["Rust".into(), "rust".into(), " RUST ".into(), "cli".into()];
+        let folded = TagPolicy {
+            fold_case: true,
+            ..TagPolicy::default()
+        };
+        assert_eq!(
+            Store::normalize_tags_with(raw(), &folded).unwrap(),
+            ["cli", "Rust"]
+        );
+        assert_eq!(
+            Store::normalize_tags_with(raw(), &TagPolicy::default()).unwrap(),
+            ["RUST", "Rust", "cli", "rust"]
+        );
+    }
+
+    #[test]
+    fn random_samples_without_replacement_within_tag() {
+        let mut entries = sample_entries();
+        entries.push((
+            "delta".to_string(),
+            Entry::new("D".to_string(), vec!["review".to_string()]),
+        ));
+        let store = Store::from_entries(entries);
+
+        let picked = store.random(10, None);
+        let keys: HashSet<_> = picked.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!((picked.len(), keys.len()), (4, 4));
+
+        let tagged = store.random(3, Some("review"));
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].0, "delta");
+        assert!(store.random(0, None).is_empty());
+    }
+
+    #[test]
+    fn zero_limit_returns_every_match_by_score() {
+        let store = store_with(&[
+            ("alpha", "v"),
+            ("al", "v"),
+            ("a-l-p-h-a", "v"),
+            ("beta", "v"),
+        ]);
+        let results = store.search("al", 0, SearchScope::KeysOnly);
+        assert_eq!(results.len(), 3);
+        assert!(results
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        assert_eq!(store.search("al", 1, SearchScope::KeysOnly).len(), 1);
+        assert!(store.search("", 0, SearchScope::KeysOnly).is_empty());
+    }
+
+    #[test]
+    fn prefix_limits_listing_and_search_to_key_remainder() {
+        let store = Store::from_entries(vec![
+            (
+                "project/api".to_string(),
+                Entry::new("1".to_string(), vec![]),
+            ),
+            (
+                "project/app".to_string(),
+                Entry::new("2".to_string(), vec![]),
+            ),
+            ("projector".to_string(), Entry::new("3".to_string(), vec![])),
+            ("other/api".to_string(), Entry::new("4".to_string(), vec![])),
+        ]);
+
+        let keys: Vec<_> = store
+            .with_prefix("project/")
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["project/api", "project/app"]);
+
+        let options = SearchOptions {
+            prefix: "project/",
+            with_indices: true,
+            ..SearchOptions::new(10, SearchScope::KeysOnly)
+        };
+        // "pj" would match "project/..." itself; with a prefix only the remainder counts.
+        assert!(store.search_with("pj", &options).is_empty());
+        let results = store.search_with("api", &options);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].key, "project/api");
+        assert_eq!(results[0].matches.as_ref().unwrap().key, vec![8, 9, 10]);
+    }
+
+    #[test]
+    fn subset_store_keeps_recent_keys_it_did_not_load() {
+        let temp = tempdir().unwrap();
+        let recent_path = temp.path().join("recent.log");
+        fs::write(&recent_path, "other\nfoo\n").unwrap();
+
+        let mut store = Store::from_subset(vec![(
+            "foo".to_string(),
+            Entry::new("bar".to_string(), vec![]),
+        )]);
+        store.enable_recent_history(RecentConfig::new(recent_path.clone(), 5));
+        store.record_access("foo");
+
+        assert_eq!(store.recent(5), ["foo", "other"]);
+        assert_eq!(fs::read_to_string(&recent_path).unwrap().lines().count(), 2);
+    }
+
     #[test]
     fn record_access_persists_recent_history() {
         let temp = tempdir().unwrap();
@@ -475,17 +1619,502 @@ mod tests {
     }
 }
 
+/// Parameters for [`Store::search_with`].
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions<'p> {
+    /// Maximum results returned; `0` returns every match.
+    pub limit: usize,
+    /// Ranked results to skip before `limit` applies, for paging.
+    pub offset: usize,
+    /// Drops results whose weighted score is below this; zero keeps every match.
+    pub min_score: i64,
+    pub scope: SearchScope,
+    /// Restricts candidates to keys starting with this string; empty means all keys.
+    pub prefix: &'p str,
+    /// Restricts candidates to entries carrying this exact tag.
+    pub tag: Option<&'p str>,
+    /// Record matched character positions for highlighting.
+    pub with_indices: bool,
+    pub weights: SearchWeights,
+    pub algorithm: SearchAlgorithm,
+}
+
+impl SearchOptions<'_> {
+    pub fn new(limit: usize, scope: SearchScope) -> Self {
+        Self {
+            limit,
+            offset: 0,
+            min_score: 0,
+            scope,
+            prefix: "",
+            tag: None,
+            with_indices: false,
+            weights: SearchWeights::default(),
+            algorithm: SearchAlgorithm::default(),
+        }
+    }
+}
+
+/// Multipliers applied to each field's fuzzy score before the best one is picked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchWeights {
+    pub key: f64,
+    pub tag: f64,
+}
+
+impl SearchWeights {
+    pub const DEFAULT_KEY: f64 = 1.0;
+    /// Slightly below the key weight so a key match wins ties and near-ties.
+    pub const DEFAULT_TAG: f64 = 0.9;
+
+    fn apply(weight: f64, score: i64) -> i64 {
+        (score as f64 * weight).round() as i64
+    }
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        Self {
+            key: Self::DEFAULT_KEY,
+            tag: Self::DEFAULT_TAG,
+        }
+    }
+}
+
+/// Aggregates returned by [`Store::stats`].
+#[derive(Debug, Default)]
+pub struct StoreStats<'a> {
+    pub entries: usize,
+    /// Sum of value sizes in bytes.
+    pub value_bytes: usize,
+    pub largest: Option<(&'a str, usize)>,
+    /// Entry with the earliest `created_at`.
+    pub oldest: Option<(&'a str, &'a Entry)>,
+    /// Entry with the latest `created_at`.
+    pub newest: Option<(&'a str, &'a Entry)>,
+}
+
 pub struct SearchResult<'a> {
     pub key: &'a str,
     pub entry: &'a Entry,
+    /// Weighted matcher score; only comparable within one query.
+    pub score: i64,
+    /// The field whose score ranked this result.
+    pub field: MatchField,
+    /// Every field that matched with its weighted score, best first.
+    pub fields: Vec<(MatchField, i64)>,
+    /// Matched character positions; only set by [`Store::search_with_indices`].
+    pub matches: Option<MatchIndices>,
+}
+
+/// Rules from `[tags]` applied to tags given on `add`.
+#[derive(Debug, Clone, Default)]
+pub struct TagPolicy {
+    /// Every tag must match this pattern; `None` allows any non-empty tag.
+    pub pattern: Option<Regex>,
+    /// Lowercase tags before deduplicating them.
+    pub lowercase: bool,
+    /// Treat tags differing only in case as duplicates, keeping the first spelling.
+    pub fold_case: bool,
+}
+
+/// Keys grouped by their lowercase form, so a case-only collision is one lookup.
+#[derive(Debug, Clone, Default)]
+pub struct FoldedKeys(HashMap<String, BTreeSet<String>>);
+
+impl FoldedKeys {
+    pub fn insert(&mut self, key: &str) {
+        self.0
+            .entry(key.to_lowercase())
+            .or_default()
+            .insert(key.to_string());
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        let folded = key.to_lowercase();
+        if let Some(keys) = self.0.get_mut(&folded) {
+            keys.remove(key);
+            if keys.is_empty() {
+                self.0.remove(&folded);
+            }
+        }
+    }
+
+    /// A key other than `key` that differs from it only in case.
+    pub fn collision(&self, key: &str) -> Option<&str> {
+        self.0
+            .get(&key.to_lowercase())?
+            .iter()
+            .map(String::as_str)
+            .find(|existing| *existing != key)
+    }
+
+    /// Rejects `key` when a stored key differs from it only in case.
+    pub fn check(&self, key: &str) -> KvResult<()> {
+        match self.collision(key) {
+            Some(existing) => Err(KvError::InvalidInput(format!(
+                "key '{key}' differs only in case from stored key '{existing}'; \
+                 lowercase existing keys before enabling [keys] case_insensitive"
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromIterator<String> for FoldedKeys {
+    fn from_iter<I: IntoIterator<Item = String>>(keys: I) -> Self {
+        let mut folded = Self::default();
+        for key in keys {
+            folded.insert(&key);
+        }
+        folded
+    }
+}
+
+/// Rules from `[keys]` applied to keys written by `add` and `import`.
+#[derive(Debug, Clone, Default)]
+pub struct KeyPolicy {
+    /// Every key must match this pattern; `None` allows any non-empty key.
+    pub pattern: Option<Regex>,
+    /// Longest allowed key in characters; `0` means unlimited.
+    pub max_length: usize,
+    /// Keys are stored and looked up in lowercase.
+    pub case_insensitive: bool,
+}
+
+impl KeyPolicy {
+    /// Lowercases `key` in place when keys are case-insensitive.
+    pub fn fold(&self, key: &mut String) {
+        if self.case_insensitive {
+            *key = key.to_lowercase();
+        }
+    }
+
+    /// Rejects empty keys always, and keys breaking the configured rules.
+    pub fn check(&self, key: &str) -> KvResult<()> {
+        if key.trim().is_empty() {
+            return Err(KvError::InvalidInput(
+                "keys cannot be empty or only whitespace".to_string(),
+            ));
+        }
+        let length = key.chars().count();
+        if self.max_length > 0 && length > self.max_length {
+            return Err(KvError::InvalidInput(format!(
+                "key '{key}' is {length} characters long; the limit is {}",
+                self.max_length
+            )));
+        }
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(key) {
+                return Err(KvError::InvalidInput(format!(
+                    "key '{key}' does not match the required pattern '{}'",
+                    pattern.as_str()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Result of [`Store::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoreDiff {
+    pub only_left: Vec<String>,
+    pub only_right: Vec<String>,
+    /// Keys in both stores whose content differs (see [`Entry::same_content`]).
+    pub changed: Vec<String>,
+}
+
+impl StoreDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_left.is_empty() && self.only_right.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Order used by `list` and `export`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Lexical key order.
+    #[default]
+    Key,
+    /// The order keys were first added; unsaved entries come last.
+    Insertion,
+}
+
+impl SortOrder {
+    /// Sorts key-ordered entries in place; a no-op for [`SortOrder::Key`].
+    pub fn sort(self, entries: &mut [(&String, &Entry)]) {
+        if self == SortOrder::Insertion {
+            entries.sort_by_key(|(_, entry)| (entry.seq.is_none(), entry.seq));
+        }
+    }
+}
+
+/// Which part of an entry produced its search score.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchField {
+    Key,
+    Tag(String),
+}
+
+impl fmt::Display for MatchField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchField::Key => f.write_str("key"),
+            MatchField::Tag(tag) => write!(f, "tag '{tag}'"),
+        }
+    }
+}
+
+/// Character positions that matched a fuzzy query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchIndices {
+    pub key: Vec<usize>,
+    /// One list per tag, in the entry's tag order.
+    pub tags: Vec<Vec<usize>>,
+}
+
+impl MatchIndices {
+    /// Maps the per-tag positions onto `tags.join(separator)`.
+    pub fn joined_tags(&self, tags: &[String], separator: &str) -> Vec<usize> {
+        let separator_len = separator.chars().count();
+        let mut offset = 0;
+        let mut joined = Vec::new();
+        for (tag, indices) in tags.iter().zip(&self.tags) {
+            joined.extend(indices.iter().map(|idx| offset + idx));
+            offset += tag.chars().count() + separator_len;
+        }
+        joined
+    }
+}
+
+/// Picks the page of `scored` that `options` asks for, best first, after recording
+/// every key in `matched`. `redact_log_keys` hides the pattern in the debug log.
+fn rank_results<'a>(
+    scored: impl IntoIterator<Item = Scored<'a>>,
+    matcher: &dyn FuzzyMatcher,
+    pattern: &str,
+    options: &SearchOptions<'_>,
+    mut matched: Option<&mut Vec<String>>,
+    redact_log_keys: bool,
+) -> Vec<SearchResult<'a>> {
+    let SearchOptions {
+        limit,
+        offset,
+        min_score,
+        scope,
+        prefix,
+        with_indices,
+        algorithm,
+        ..
+    } = *options;
+
+    let scored = scored
+        .into_iter()
+        .inspect(|scored| {
+            if let Some(matched) = matched.as_deref_mut() {
+                matched.push(scored.key.to_string());
+            }
+        })
+        .filter(|scored| scored.score >= min_score);
+    // `limit` 0 keeps every match, so only a real limit can bound the heap.
+    let mut scored = top_ranked(scored, (limit > 0).then(|| offset.saturating_add(limit)));
+    scored.drain(..offset.min(scored.len()));
+
+    let prefix_chars = prefix.chars().count();
+    let results: Vec<_> = scored
+        .into_iter()
+        .map(|scored| SearchResult {
+            key: scored.key,
+            entry: scored.entry,
+            score: scored.score,
+            field: scored.field(0),
+            fields: (0..scored.fields.len())
+                .map(|idx| (scored.field(idx), scored.fields[idx].1))
+                .collect(),
+            matches: with_indices.then(|| {
+                let mut matches = match_indices(
+                    matcher,
+                    &scored.key[prefix.len()..],
+                    scored.entry,
+                    pattern,
+                    scope,
+                );
+                for idx in &mut matches.key {
+                    *idx += prefix_chars;
+                }
+                matches
+            }),
+        })
+        .collect();
+
+    debug!(
+        "fuzzy search pattern='{}' prefix='{}' scope={:?} algorithm={:?} results={}",
+        log_key(pattern, redact_log_keys),
+        log_key(prefix, redact_log_keys),
+        scope,
+        algorithm,
+        results.len()
+    );
+
+    results
+}
+
+/// Scores one candidate; `None` if no field matched.
+fn score_candidate<'a>(
+    matcher: &dyn FuzzyMatcher,
+    key: &'a str,
+    entry: &'a Entry,
+    pattern: &str,
+    options: &SearchOptions<'_>,
+) -> Option<Scored<'a>> {
+    let remainder = &key[options.prefix.len()..];
+    // `None` is the key, `Some(i)` the entry's i-th tag.
+    let mut fields = Vec::new();
+    if matches_keys(options.scope) {
+        if let Some(score) = matcher.fuzzy_match(remainder, pattern) {
+            fields.push((None, SearchWeights::apply(options.weights.key, score)));
+        }
+    }
+    if matches_tags(options.scope) {
+        for (idx, tag) in entry.tags.iter().enumerate() {
+            if let Some(score) = matcher.fuzzy_match(tag, pattern) {
+                fields.push((Some(idx), SearchWeights::apply(options.weights.tag, score)));
+            }
+        }
+    }
+
+    // The sort is stable and the key is pushed first, so a key match wins
+    // ties and the reported field is the more specific one.
+    fields.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    let score = fields.first()?.1;
+    Some(Scored {
+        score,
+        fields,
+        key,
+        entry,
+    })
 }
 
 struct Scored<'a> {
     score: i64,
+    /// Matching fields as (tag index, score), best first; a `None` index is the key.
+    fields: Vec<(Option<usize>, i64)>,
     key: &'a str,
     entry: &'a Entry,
 }
 
+/// The best `keep` results (all of them for `None`), best first. A bounded heap
+/// holds the current top `keep`, so a small limit costs O(n log k), not a full sort.
+fn top_ranked<'a>(
+    scored: impl IntoIterator<Item = Scored<'a>>,
+    keep: Option<usize>,
+) -> Vec<Scored<'a>> {
+    let Some(keep) = keep else {
+        let mut all: Vec<_> = scored.into_iter().collect();
+        all.sort_unstable();
+        return all;
+    };
+
+    // A max-heap under `Scored`'s ordering has the worst kept result on top.
+    let mut heap = BinaryHeap::with_capacity(keep.saturating_add(1).min(1024));
+    for candidate in scored {
+        if heap.len() < keep {
+            heap.push(candidate);
+        } else if heap.peek().is_some_and(|worst| candidate < *worst) {
+            heap.pop();
+            heap.push(candidate);
+        }
+    }
+    heap.into_sorted_vec()
+}
+
+/// Orders by rank: higher score first, ties to the smaller key so results never
+/// depend on scoring order. Keys are unique, so this is a total order.
+impl Ord for Scored<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .score
+            .cmp(&self.score)
+            .then_with(|| self.key.cmp(other.key))
+    }
+}
+
+impl PartialOrd for Scored<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Scored<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored<'_> {}
+
+impl Scored<'_> {
+    fn field(&self, idx: usize) -> MatchField {
+        match self.fields[idx].0 {
+            None => MatchField::Key,
+            Some(tag) => MatchField::Tag(self.entry.tags[tag].clone()),
+        }
+    }
+}
+
+impl SearchScope {
+    /// Short name matching the `[search] default_scope` config values.
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchScope::All => "all",
+            SearchScope::KeysOnly => "keys",
+            SearchScope::TagsOnly => "tags",
+        }
+    }
+
+    /// Cycles all -> keys -> tags -> all.
+    pub fn next(self) -> Self {
+        match self {
+            SearchScope::All => SearchScope::KeysOnly,
+            SearchScope::KeysOnly => SearchScope::TagsOnly,
+            SearchScope::TagsOnly => SearchScope::All,
+        }
+    }
+}
+
+fn match_indices(
+    matcher: &dyn FuzzyMatcher,
+    key: &str,
+    entry: &Entry,
+    pattern: &str,
+    scope: SearchScope,
+) -> MatchIndices {
+    let indices_in = |text: &str| {
+        matcher
+            .fuzzy_indices(text, pattern)
+            .map(|(_, indices)| indices)
+            .unwrap_or_default()
+    };
+    let key = if matches_keys(scope) {
+        indices_in(key)
+    } else {
+        Vec::new()
+    };
+    let tags = entry
+        .tags
+        .iter()
+        .map(|tag| {
+            if matches_tags(scope) {
+                indices_in(tag)
+            } else {
+                Vec::new()
+            }
+        })
+        .collect();
+    MatchIndices { key, tags }
+}
+
 fn matches_keys(scope: SearchScope) -> bool {
     matches!(scope, SearchScope::All | SearchScope::KeysOnly)
 }
@@ -589,8 +2218,9 @@ impl Store {
         }
 
         let mut seen = HashSet::with_capacity(self.recent.len());
-        self.recent
-            .retain(|key| self.entries.contains_key(key) && seen.insert(key.clone()));
+        self.recent.retain(|key| {
+            (self.partial || self.entries.contains_key(key)) && seen.insert(key.clone())
+        });
         if self.recent.len() > self.recent_capacity {
             self.recent.truncate(self.recent_capacity);
         }